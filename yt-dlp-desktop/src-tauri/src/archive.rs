@@ -0,0 +1,187 @@
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use serde::Serialize;
+use crate::history::HistoryStore;
+use crate::types::{DownloadConfig, DownloadHistoryItem, VideoInfo};
+use crate::ytdlp;
+
+/// 仅音频下载产生的文件扩展名（用于区分之前的下载是音频还是视频）
+const AUDIO_EXTENSIONS: &[&str] = &["mp3", "m4a", "opus", "ogg", "flac", "wav", "aac"];
+
+/// 已下载过的视频
+#[derive(Debug, Clone, Serialize)]
+pub struct ArchiveMatch {
+    pub video_id: String,
+    pub title: String,
+    /// 之前的下载记录，为 None 表示只在导入的下载存档中
+    pub previous: Option<DownloadHistoryItem>,
+}
+
+/// 下载存档文件路径（每个来源一个，格式与 yt-dlp --download-archive 相同）
+pub fn archive_path(source_key: &str) -> Option<PathBuf> {
+    crate::config::app_data_dir().map(|dir| dir.join("archives").join(format!("{}.txt", source_key)))
+}
+
+/// 读取存档中已下载的视频 ID，文件不存在时返回空集合
+pub fn load_ids(path: &Path) -> HashSet<String> {
+    std::fs::read_to_string(path)
+        .map(|content| parse_archive(&content))
+        .unwrap_or_default()
+}
+
+/// 检查视频是否已下载过：历史中有同类（仅音频或视频）、画质不低于本次且文件仍存在的记录，或在导入的下载存档中
+pub async fn find_duplicate(config: &DownloadConfig, format_id: &str, info: &VideoInfo) -> Result<Option<ArchiveMatch>, String> {
+    // 无法确定本次画质时（如 best）按可用的最高画质比较
+    let requested_height = if config.audio_only {
+        None
+    } else {
+        ytdlp::describe_format(format_id, &info.formats)
+            .and_then(|quality| ytdlp::parse_height(&quality))
+            .or_else(|| info.formats.iter().filter_map(ytdlp::format_height).max())
+    };
+
+    // SQLite 和文件检查为同步调用，放到阻塞线程中执行
+    let video_id = info.id.clone();
+    let audio_only = config.audio_only;
+    let (previous, archived) = tokio::task::spawn_blocking(move || -> Result<_, String> {
+        let store = HistoryStore::get();
+        let previous = store
+            .find_downloads(&video_id)?
+            .into_iter()
+            .filter(|item| !item.file_path.is_empty() && Path::new(&item.file_path).exists())
+            .find(|item| covers(is_audio_file(&item.file_path), item.quality.as_deref(), audio_only, requested_height));
+        let archived = previous.is_some() || store.is_archived(&video_id)?;
+        Ok((previous, archived))
+    })
+    .await
+    .map_err(|e| format!("Failed to check download history: {}", e))??;
+
+    if !archived {
+        return Ok(None);
+    }
+    Ok(Some(ArchiveMatch {
+        video_id: info.id.clone(),
+        title: info.title.clone(),
+        previous,
+    }))
+}
+
+/// 之前的下载能否代替本次下载：类型（仅音频或视频）必须相同，视频的画质不低于本次要求的高度
+fn covers(previous_audio: bool, previous_quality: Option<&str>, audio_only: bool, requested_height: Option<u32>) -> bool {
+    if previous_audio != audio_only {
+        return false;
+    }
+    match (previous_quality.and_then(ytdlp::parse_height), requested_height) {
+        (_, None) => true,
+        (Some(previous), Some(requested)) => previous >= requested,
+        (None, Some(_)) => false,
+    }
+}
+
+/// 文件是否是仅音频下载的结果
+fn is_audio_file(path: &str) -> bool {
+    Path::new(path)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| AUDIO_EXTENSIONS.contains(&ext.to_lowercase().as_str()))
+}
+
+/// 从 yt-dlp --download-archive 格式的文件导入，返回新增的条数
+pub fn import_archive(path: &Path) -> Result<usize, String> {
+    let content = std::fs::read_to_string(path).map_err(|e| format!("Failed to read archive file: {}", e))?;
+    let entries: Vec<(String, String)> = content
+        .lines()
+        .filter_map(parse_archive_line)
+        .map(|(extractor, id)| (extractor.to_string(), id.to_string()))
+        .collect();
+
+    let added = HistoryStore::get().import_archive(&entries)?;
+    crate::logger::AppLogger::get().info(&format!(
+        "导入下载存档 {}: 共 {} 条，新增 {} 条",
+        path.display(),
+        entries.len(),
+        added
+    ));
+    Ok(added)
+}
+
+/// 导出为 yt-dlp --download-archive 格式的文件，返回导出的条数
+pub fn export_archive(path: &Path) -> Result<usize, String> {
+    let entries = HistoryStore::get().archive_entries()?;
+    std::fs::write(path, format_archive(&entries)).map_err(|e| format!("Failed to write archive file: {}", e))?;
+    crate::logger::AppLogger::get().info(&format!("导出下载存档 {}: 共 {} 条", path.display(), entries.len()));
+    Ok(entries.len())
+}
+
+fn format_archive(entries: &[(String, String)]) -> String {
+    entries
+        .iter()
+        .map(|(extractor, id)| format!("{} {}\n", extractor, id))
+        .collect()
+}
+
+/// 解析存档内容，每行为 "<提取器> <视频 ID>"
+fn parse_archive(content: &str) -> HashSet<String> {
+    content
+        .lines()
+        .filter_map(parse_archive_line)
+        .map(|(_, id)| id.to_string())
+        .collect()
+}
+
+/// 解析存档中的一行，返回 (提取器, 视频 ID)
+fn parse_archive_line(line: &str) -> Option<(&str, &str)> {
+    let (extractor, id) = line.trim().split_once(' ')?;
+    let id = id.trim();
+    if extractor.is_empty() || id.is_empty() {
+        return None;
+    }
+    Some((extractor, id))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_archive() {
+        let ids = parse_archive("youtube dQw4w9WgXcQ\n\nyoutube abc123\r\ninvalid\nvimeo 42\n");
+        assert_eq!(ids.len(), 3);
+        assert!(ids.contains("dQw4w9WgXcQ"));
+        assert!(ids.contains("abc123"));
+        assert!(ids.contains("42"));
+    }
+
+    #[test]
+    fn test_missing_archive_is_empty() {
+        assert!(load_ids(Path::new("/nonexistent/archive.txt")).is_empty());
+    }
+
+    #[test]
+    fn test_covers_quality() {
+        assert!(covers(false, Some("1080p"), false, Some(720)));
+        assert!(covers(false, Some("1080p60"), false, Some(1080)));
+        assert!(!covers(false, Some("720p"), false, Some(1080)));
+        assert!(!covers(false, None, false, Some(720)));
+        assert!(covers(true, None, true, None));
+    }
+
+    #[test]
+    fn test_covers_media_kind() {
+        // 已有视频时仍然可以下载音频，反之亦然
+        assert!(!covers(is_audio_file("/dl/Song.mp4"), Some("1080p"), true, None));
+        assert!(!covers(is_audio_file("/dl/Song.mp3"), None, false, Some(720)));
+        assert!(covers(is_audio_file("/dl/Song.M4A"), None, true, None));
+    }
+
+    #[test]
+    fn test_format_archive_roundtrip() {
+        let entries = vec![
+            ("youtube".to_string(), "dQw4w9WgXcQ".to_string()),
+            ("vimeo".to_string(), "42".to_string()),
+        ];
+        let content = format_archive(&entries);
+        assert_eq!(content, "youtube dQw4w9WgXcQ\nvimeo 42\n");
+        assert_eq!(parse_archive(&content).len(), 2);
+    }
+}
@@ -0,0 +1,146 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use serde::Deserialize;
+use tokio::process::Command;
+use crate::types::{ArtifactKind, DownloadArtifact};
+
+/// 文件移动到最终位置（后处理完成）后，让 yt-dlp 输出本次产生的文件路径
+const PRINT_TEMPLATE: &str = "after_move:%(.{filepath,__infojson_filename,requested_subtitles,thumbnails})j";
+
+/// --print-to-file 输出的一行（每个下载完成的视频一行）
+#[derive(Debug, Deserialize)]
+struct PrintedFiles {
+    filepath: Option<String>,
+    #[serde(rename = "__infojson_filename")]
+    infojson_filename: Option<String>,
+    requested_subtitles: Option<HashMap<String, PrintedFile>>,
+    thumbnails: Option<Vec<PrintedFile>>,
+}
+
+/// 字幕或缩略图，只有写入磁盘的才有 filepath
+#[derive(Debug, Deserialize)]
+struct PrintedFile {
+    filepath: Option<String>,
+}
+
+/// yt-dlp 报告的文件列表（位于应用数据目录，读取后删除）
+fn artifacts_file(task_id: &str) -> Option<PathBuf> {
+    crate::config::app_data_dir().map(|dir| dir.join("artifacts").join(format!("{}.jsonl", task_id)))
+}
+
+/// 添加让 yt-dlp 报告最终文件路径的参数
+pub fn add_print_args(cmd: &mut Command, task_id: &str) {
+    let Some(path) = artifacts_file(task_id) else {
+        return;
+    };
+    if let Some(dir) = path.parent() {
+        let _ = std::fs::create_dir_all(dir);
+    }
+    cmd.arg("--print-to-file").arg(PRINT_TEMPLATE).arg(path);
+}
+
+/// 读取并删除 yt-dlp 报告的文件列表，只保留仍然存在的文件（如嵌入后被删除的缩略图不计入）
+pub fn take_artifacts(task_id: &str) -> Vec<DownloadArtifact> {
+    let Some(path) = artifacts_file(task_id) else {
+        return Vec::new();
+    };
+    let content = std::fs::read_to_string(&path).unwrap_or_default();
+    discard(task_id);
+
+    // 用于记录历史的元数据文件是内部文件，不作为下载结果
+    let internal_dir = crate::history::metadata_file(task_id).and_then(|file| file.parent().map(Path::to_path_buf));
+    parse_artifacts(&content, internal_dir.as_deref())
+        .into_iter()
+        .filter_map(|(kind, path)| {
+            let size = std::fs::metadata(&path).ok().filter(|m| m.is_file())?.len();
+            Some(DownloadArtifact { kind, path, size })
+        })
+        .collect()
+}
+
+/// 删除文件列表（下载失败或取消时）
+pub fn discard(task_id: &str) {
+    if let Some(path) = artifacts_file(task_id) {
+        let _ = std::fs::remove_file(path);
+    }
+}
+
+/// 解析 --print-to-file 的输出，去除重复的路径（重试时同一文件可能报告多次）
+fn parse_artifacts(content: &str, internal_dir: Option<&Path>) -> Vec<(ArtifactKind, String)> {
+    let mut artifacts: Vec<(ArtifactKind, String)> = Vec::new();
+
+    for line in content.lines().filter(|line| !line.trim().is_empty()) {
+        let files: PrintedFiles = match serde_json::from_str(line) {
+            Ok(files) => files,
+            Err(e) => {
+                crate::logger::AppLogger::get().warn(&format!("解析 yt-dlp 文件列表失败: {}", e));
+                continue;
+            }
+        };
+
+        let subtitles = files.requested_subtitles.into_iter().flat_map(|subs| {
+            let mut subs: Vec<_> = subs.into_iter().collect();
+            subs.sort_by(|a, b| a.0.cmp(&b.0));
+            subs.into_iter().filter_map(|(_, sub)| sub.filepath)
+        });
+        let thumbnails = files.thumbnails.into_iter().flatten().filter_map(|thumb| thumb.filepath);
+
+        let found = files
+            .filepath
+            .map(|path| (ArtifactKind::Media, path))
+            .into_iter()
+            .chain(thumbnails.map(|path| (ArtifactKind::Thumbnail, path)))
+            .chain(subtitles.map(|path| (ArtifactKind::Subtitle, path)))
+            .chain(files.infojson_filename.map(|path| (ArtifactKind::InfoJson, path)));
+
+        for (kind, path) in found {
+            if internal_dir.is_some_and(|dir| Path::new(&path).starts_with(dir)) {
+                continue;
+            }
+            if !artifacts.iter().any(|(_, existing)| *existing == path) {
+                artifacts.push((kind, path));
+            }
+        }
+    }
+
+    artifacts
+}
+
+/// 下载的主文件（合并后的视频或音频）
+pub fn main_file(artifacts: &[DownloadArtifact]) -> Option<&DownloadArtifact> {
+    artifacts.iter().find(|artifact| artifact.kind == ArtifactKind::Media)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_artifacts() {
+        let content = concat!(
+            r#"{"filepath": "/dl/Talk.mkv", "__infojson_filename": "/data/metadata/abc.info.json", "#,
+            r#""requested_subtitles": {"fr": {"ext": "vtt", "filepath": "/dl/Talk.fr.vtt"}, "en": {"ext": "vtt", "filepath": "/dl/Talk.en.vtt"}}, "#,
+            r#""thumbnails": [{"id": "0", "url": "https://i.ytimg.com/a.jpg"}, {"id": "1", "filepath": "/dl/Talk.webp"}]}"#,
+            "\n",
+            r#"{"filepath": "/dl/Talk.mkv", "requested_subtitles": null, "thumbnails": null}"#,
+            "\nnot json\n",
+        );
+        let artifacts = parse_artifacts(content, Some(Path::new("/data/metadata")));
+        assert_eq!(
+            artifacts,
+            vec![
+                (ArtifactKind::Media, "/dl/Talk.mkv".to_string()),
+                (ArtifactKind::Thumbnail, "/dl/Talk.webp".to_string()),
+                (ArtifactKind::Subtitle, "/dl/Talk.en.vtt".to_string()),
+                (ArtifactKind::Subtitle, "/dl/Talk.fr.vtt".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_artifacts_keeps_user_info_json() {
+        let content = r#"{"filepath": "/dl/a.mp4", "__infojson_filename": "/dl/a.info.json"}"#;
+        let artifacts = parse_artifacts(content, Some(Path::new("/data/metadata")));
+        assert_eq!(artifacts[1], (ArtifactKind::InfoJson, "/dl/a.info.json".to_string()));
+    }
+}
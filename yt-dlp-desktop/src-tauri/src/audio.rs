@@ -0,0 +1,91 @@
+use crate::types::{AudioCodec, AudioOptions};
+
+impl AudioCodec {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AudioCodec::Mp3 => "mp3",
+            AudioCodec::M4a => "m4a",
+            AudioCodec::Opus => "opus",
+            AudioCodec::Flac => "flac",
+            AudioCodec::Wav => "wav",
+        }
+    }
+
+    /// 无损格式不需要设置码率
+    pub fn is_lossless(&self) -> bool {
+        matches!(self, AudioCodec::Flac | AudioCodec::Wav)
+    }
+
+    /// WAV 不支持嵌入封面
+    pub fn supports_cover(&self) -> bool {
+        !matches!(self, AudioCodec::Wav)
+    }
+}
+
+impl AudioOptions {
+    /// 提取音频的 yt-dlp 参数（由 yt-dlp 在下载完成后调用 ffmpeg 转换）
+    pub fn ytdlp_args(&self) -> Vec<String> {
+        let mut args = vec![
+            "--extract-audio".to_string(),
+            "--audio-format".to_string(),
+            self.codec.as_str().to_string(),
+        ];
+        if !self.codec.is_lossless() {
+            // 0 为最高质量的可变码率
+            let quality = match self.bitrate {
+                Some(bitrate) if bitrate > 0 => format!("{}K", bitrate),
+                _ => "0".to_string(),
+            };
+            args.extend(["--audio-quality".to_string(), quality]);
+        }
+        if self.embed_thumbnail && self.codec.supports_cover() {
+            args.push("--embed-thumbnail".to_string());
+        }
+        if !self.embed_metadata {
+            // 下载命令默认嵌入元数据，这里覆盖
+            args.push("--no-embed-metadata".to_string());
+        }
+        args
+    }
+}
+
+/// 下载使用的音频提取选项：下载未指定时使用应用设置
+pub fn resolve_options(requested: Option<&AudioOptions>, defaults: &AudioOptions) -> AudioOptions {
+    requested.cloned().unwrap_or_else(|| defaults.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lossy_args() {
+        let options = AudioOptions {
+            codec: AudioCodec::Opus,
+            bitrate: Some(160),
+            ..Default::default()
+        };
+        assert_eq!(
+            options.ytdlp_args(),
+            vec!["--extract-audio", "--audio-format", "opus", "--audio-quality", "160K", "--embed-thumbnail"]
+        );
+        assert_eq!(
+            AudioOptions::default().ytdlp_args(),
+            vec!["--extract-audio", "--audio-format", "mp3", "--audio-quality", "0", "--embed-thumbnail"]
+        );
+    }
+
+    #[test]
+    fn test_lossless_args() {
+        let options = AudioOptions {
+            codec: AudioCodec::Wav,
+            bitrate: Some(320),
+            embed_thumbnail: true,
+            embed_metadata: false,
+        };
+        assert_eq!(
+            options.ytdlp_args(),
+            vec!["--extract-audio", "--audio-format", "wav", "--no-embed-metadata"]
+        );
+    }
+}
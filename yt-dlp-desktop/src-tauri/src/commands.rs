@@ -1,13 +1,21 @@
+use crate::archive;
 use crate::config::AppConfig;
-use crate::types::{DownloadConfig, DownloadHistoryItem, VideoFormat, VideoInfo};
+use crate::filename::{self, FilenamePreset};
+use crate::history::{self, ExportFormat, HistoryQuery, HistoryStore};
+use crate::mirror::{self, MirrorJob, MirrorRunSummary};
+use crate::playlist::{self, PlaylistSelection};
+use crate::queue::{DownloadQueue, QueueItem};
+use crate::subscriptions::{self, Subscription, SubscriptionCheckResult};
+use crate::types::{DownloadConfig, DownloadHistoryPage, DownloadProgress, PlaylistInfo, VideoFormat, VideoInfo};
 use crate::ytdlp;
 use tauri::AppHandle;
 
 /// 获取视频信息
 #[tauri::command]
-pub async fn get_video_info(url: String) -> Result<VideoInfo, String> {
+pub async fn get_video_info(url: String, app: AppHandle) -> Result<VideoInfo, String> {
     println!("[COMMAND] get_video_info called with URL: {}", url);
-    let result = ytdlp::get_video_info(&url).await;
+    let timeout = AppConfig::load(&app).unwrap_or_default().info_timeout();
+    let result = ytdlp::get_video_info(&url, timeout).await;
     match &result {
         Ok(_) => println!("[COMMAND] get_video_info succeeded"),
         Err(e) => println!("[COMMAND] get_video_info failed: {}", e),
@@ -17,53 +25,254 @@ pub async fn get_video_info(url: String) -> Result<VideoInfo, String> {
 
 /// 列出可用格式
 #[tauri::command]
-pub async fn list_formats(url: String) -> Result<Vec<VideoFormat>, String> {
-    let video_info = ytdlp::get_video_info(&url).await?;
+pub async fn list_formats(url: String, app: AppHandle) -> Result<Vec<VideoFormat>, String> {
+    let timeout = AppConfig::load(&app).unwrap_or_default().info_timeout();
+    let video_info = ytdlp::get_video_info(&url, timeout).await?;
     Ok(video_info.formats)
 }
 
-/// 开始下载
+/// 预览按文件名模板下载后的路径（只获取视频信息，不下载）
 #[tauri::command]
-pub async fn start_download(config: DownloadConfig, window: tauri::Window) -> Result<String, String> {
+pub async fn preview_output_filename(
+    url: String,
+    template: String,
+    output_path: Option<String>,
+    app: AppHandle,
+) -> Result<String, String> {
+    filename::validate(&template)?;
+    let config = AppConfig::load(&app).unwrap_or_default();
+    let info = ytdlp::get_video_info(&url, config.info_timeout()).await?;
+    let output_path = output_path.or_else(|| config.get_default_save_path());
+    filename::preview(&info, output_path.as_deref(), &template, &config.filename)
+}
+
+/// 文件名模板预设
+#[tauri::command]
+pub async fn get_filename_presets() -> Result<Vec<FilenamePreset>, String> {
+    Ok(filename::PRESETS.to_vec())
+}
+
+/// 获取播放列表或频道的条目
+#[tauri::command]
+pub async fn get_playlist_info(url: String, app: AppHandle) -> Result<PlaylistInfo, String> {
+    println!("[COMMAND] get_playlist_info called with URL: {}", url);
+    let timeout = AppConfig::load(&app).unwrap_or_default().info_timeout();
+    playlist::get_playlist_info(&url, timeout).await
+}
+
+/// 将播放列表中选中的条目加入下载队列（config 中的格式和保存路径应用于所有条目）
+#[tauri::command]
+pub async fn enqueue_playlist(
+    config: DownloadConfig,
+    selection: PlaylistSelection,
+    app: AppHandle,
+) -> Result<Vec<String>, String> {
+    println!("[COMMAND] enqueue_playlist called with URL: {}", config.url);
+
+    let timeout = AppConfig::load(&app).unwrap_or_default().info_timeout();
+    let info = playlist::get_playlist_info(&config.url, timeout).await?;
+    let entries = playlist::select_entries(&info.entries, &selection)?;
+    if entries.is_empty() {
+        return Err("No playlist entries selected".to_string());
+    }
+
+    let configs = playlist::build_entry_configs(&info, &entries, &config);
+    let queue = DownloadQueue::get();
+    let ids = queue.enqueue_all(configs);
+    queue.schedule(&app);
+
+    crate::logger::AppLogger::get().info(&format!("播放列表 {} 已加入 {} 个任务", info.title, ids.len()));
+    Ok(ids)
+}
+
+/// 创建频道镜像任务
+#[tauri::command]
+pub async fn create_mirror_job(
+    config: DownloadConfig,
+    date_after: Option<String>,
+    date_before: Option<String>,
+) -> Result<MirrorJob, String> {
+    mirror::create_job(config, date_after, date_before)
+}
+
+/// 获取所有镜像任务
+#[tauri::command]
+pub async fn list_mirror_jobs() -> Result<Vec<MirrorJob>, String> {
+    mirror::list_jobs()
+}
+
+/// 删除镜像任务
+#[tauri::command]
+pub async fn delete_mirror_job(job_id: String) -> Result<(), String> {
+    mirror::delete_job(&job_id)
+}
+
+/// 运行镜像任务，只将新视频加入下载队列
+#[tauri::command]
+pub async fn run_mirror_job(job_id: String, app: AppHandle) -> Result<MirrorRunSummary, String> {
+    println!("[COMMAND] run_mirror_job called: {}", job_id);
+    mirror::run_job(&job_id, &app).await
+}
+
+/// 获取镜像任务的运行记录
+#[tauri::command]
+pub async fn list_mirror_runs(job_id: Option<String>) -> Result<Vec<MirrorRunSummary>, String> {
+    mirror::list_runs(job_id.as_deref())
+}
+
+/// 添加订阅（频道、播放列表或 RSS/Atom 订阅源）
+#[tauri::command]
+pub async fn add_subscription(
+    url: String,
+    preset: DownloadConfig,
+    interval_minutes: Option<u64>,
+    backfill: Option<bool>,
+) -> Result<Subscription, String> {
+    subscriptions::add_subscription(url, preset, interval_minutes, backfill.unwrap_or(false))
+}
+
+/// 获取所有订阅
+#[tauri::command]
+pub async fn list_subscriptions() -> Result<Vec<Subscription>, String> {
+    subscriptions::list_subscriptions()
+}
+
+/// 删除订阅
+#[tauri::command]
+pub async fn remove_subscription(subscription_id: String) -> Result<(), String> {
+    subscriptions::remove_subscription(&subscription_id)
+}
+
+/// 立即检查订阅（不指定 ID 时检查全部）
+#[tauri::command]
+pub async fn check_now(subscription_id: Option<String>, app: AppHandle) -> Result<Vec<SubscriptionCheckResult>, String> {
+    subscriptions::check_now(subscription_id.as_deref(), &app).await
+}
+
+/// 开始下载（加入下载队列）
+#[tauri::command]
+pub async fn start_download(config: DownloadConfig, app: AppHandle) -> Result<String, String> {
     println!("[COMMAND] start_download called");
     println!("[COMMAND] URL: {}", config.url);
     println!("[COMMAND] Format ID: {}", config.format_id);
     println!("[COMMAND] Output path: {}", config.output_path);
     println!("[COMMAND] Audio only: {}", config.audio_only);
     println!("[COMMAND] Include subtitles: {}", config.include_subtitles);
+    if let Some(template) = &config.filename_template {
+        filename::validate(template)?;
+    }
 
-    // 如果是仅音频下载，修改格式选择
-    let final_format_id = if config.audio_only {
-        // 对于仅音频，使用 bestaudio 或指定的音频格式
-        if config.format_id.contains("audio") {
-            config.format_id
-        } else {
-            "bestaudio/best".to_string()
-        }
-    } else {
-        config.format_id
-    };
+    let queue = DownloadQueue::get();
+    let task_id = queue.enqueue(config);
+    queue.schedule(&app);
 
-    println!("[COMMAND] Final format ID: {}", final_format_id);
+    println!("[COMMAND] Download queued: {}", task_id);
 
-    // 启动下载（传递 window 用于进度事件）
-    ytdlp::download_video(&config.url, &final_format_id, &config.output_path, window).await?;
+    Ok(task_id)
+}
+
+/// 批量添加下载任务
+#[tauri::command]
+pub async fn enqueue_downloads(configs: Vec<DownloadConfig>, app: AppHandle) -> Result<Vec<String>, String> {
+    println!("[COMMAND] enqueue_downloads called with {} items", configs.len());
+    for template in configs.iter().filter_map(|config| config.filename_template.as_ref()) {
+        filename::validate(template)?;
+    }
 
-    println!("[COMMAND] Download started successfully");
-    
-    // 返回下载任务 ID（这里简化为 URL）
-    Ok(config.url)
+    let queue = DownloadQueue::get();
+    let ids = queue.enqueue_all(configs);
+    queue.schedule(&app);
+
+    Ok(ids)
+}
+
+/// 获取下载队列
+#[tauri::command]
+pub async fn list_queue() -> Result<Vec<QueueItem>, String> {
+    Ok(DownloadQueue::get().list())
+}
+
+/// 调整下载队列顺序
+#[tauri::command]
+pub async fn reorder_queue(ids: Vec<String>, app: AppHandle) -> Result<(), String> {
+    let queue = DownloadQueue::get();
+    queue.reorder(&ids)?;
+    queue.emit_changed(&app);
+    Ok(())
+}
+
+/// 从下载队列中移除任务
+#[tauri::command]
+pub async fn remove_from_queue(download_id: String, app: AppHandle) -> Result<(), String> {
+    let queue = DownloadQueue::get();
+    queue.remove(&download_id)?;
+    queue.schedule(&app);
+    Ok(())
 }
 
 /// 取消下载
 #[tauri::command]
-pub async fn cancel_download(download_id: String) -> Result<(), String> {
-    // TODO: 实现取消下载逻辑
-    // 需要维护一个下载任务列表，并能够终止进程
-    println!("Cancelling download: {}", download_id);
+pub async fn cancel_download(download_id: String, app: AppHandle) -> Result<(), String> {
+    println!("[COMMAND] Cancelling download: {}", download_id);
+    let queue = DownloadQueue::get();
+    queue.cancel(&download_id, &app)?;
+    queue.emit_changed(&app);
+    Ok(())
+}
+
+/// 暂停下载
+#[tauri::command]
+pub async fn pause_download(download_id: String, app: AppHandle) -> Result<(), String> {
+    println!("[COMMAND] Pausing download: {}", download_id);
+    DownloadQueue::get().pause(&download_id)?;
+    // 暂停释放了并发名额，启动下一个排队任务
+    DownloadQueue::get().schedule(&app);
+    Ok(())
+}
+
+/// 恢复下载
+#[tauri::command]
+pub async fn resume_download(download_id: String, app: AppHandle) -> Result<(), String> {
+    println!("[COMMAND] Resuming download: {}", download_id);
+    let queue = DownloadQueue::get();
+    queue.resume(&download_id)?;
+    queue.schedule(&app);
+    Ok(())
+}
+
+/// 确认重新下载已下载过的视频
+#[tauri::command]
+pub async fn redownload(download_id: String, app: AppHandle) -> Result<(), String> {
+    let queue = DownloadQueue::get();
+    queue.redownload(&download_id)?;
+    queue.schedule(&app);
     Ok(())
 }
 
+/// 获取所有未结束下载的进度快照
+#[tauri::command]
+pub async fn get_active_downloads() -> Result<Vec<DownloadProgress>, String> {
+    Ok(DownloadQueue::get().active_progress())
+}
+
+/// 获取上次退出时未完成的下载
+#[tauri::command]
+pub async fn list_interrupted_downloads() -> Result<Vec<QueueItem>, String> {
+    Ok(DownloadQueue::get().interrupted())
+}
+
+/// 恢复上次退出时未完成的下载（复用磁盘上已有的部分文件）
+#[tauri::command]
+pub async fn resume_interrupted_downloads(app: AppHandle) -> Result<usize, String> {
+    let queue = DownloadQueue::get();
+    let interrupted = queue.interrupted();
+    for item in &interrupted {
+        queue.resume(&item.id)?;
+    }
+    queue.schedule(&app);
+    Ok(interrupted.len())
+}
+
 /// 选择保存路径
 #[tauri::command]
 pub async fn select_save_path(app: AppHandle) -> Result<Option<String>, String> {
@@ -87,7 +296,12 @@ pub async fn get_app_config(app: AppHandle) -> Result<AppConfig, String> {
 /// 保存应用配置
 #[tauri::command]
 pub async fn save_app_config(config: AppConfig, app: AppHandle) -> Result<(), String> {
-    config.save(&app)
+    filename::validate(&config.filename_template)?;
+    crate::rules::validate_rules(&config.organize_rules)?;
+    config.save(&app)?;
+    // 并发数可能已调整，重新调度队列
+    DownloadQueue::get().schedule(&app);
+    Ok(())
 }
 
 /// 获取默认保存路径
@@ -105,18 +319,97 @@ pub async fn set_default_save_path(path: String, app: AppHandle) -> Result<(), S
     config.save(&app)
 }
 
-/// 获取下载历史（Mock 实现）
+/// 分页获取下载历史（最新的在前，页码从 1 开始）
+#[tauri::command]
+pub async fn get_download_history(page: Option<u32>, page_size: Option<u32>) -> Result<DownloadHistoryPage, String> {
+    blocking(move || HistoryStore::get().list(page.unwrap_or(1), page_size.unwrap_or(history::DEFAULT_PAGE_SIZE))).await
+}
+
+/// 按条件查询下载历史（标题搜索、日期范围、上传者、格式、状态）
 #[tauri::command]
-pub async fn get_download_history() -> Result<Vec<DownloadHistoryItem>, String> {
-    // TODO: 实现真实的历史记录存储（使用文件或数据库）
-    Ok(vec![])
+pub async fn query_download_history(query: HistoryQuery) -> Result<DownloadHistoryPage, String> {
+    blocking(move || HistoryStore::get().query(&query)).await
+}
+
+/// 将符合条件的下载历史导出为 CSV 或 JSON，返回保存路径（用户取消时返回 None）
+#[tauri::command]
+pub async fn export_download_history(query: HistoryQuery, format: ExportFormat, app: AppHandle) -> Result<Option<String>, String> {
+    use tauri_plugin_dialog::DialogExt;
+
+    let content = blocking(move || HistoryStore::get().export(&query, format)).await?;
+    let extension = format.extension();
+    let result = app.dialog()
+        .file()
+        .set_title("导出下载历史")
+        .add_filter(extension.to_uppercase(), &[extension])
+        .set_file_name(format!("download-history-{}.{}", chrono::Local::now().format("%Y%m%d"), extension))
+        .blocking_save_file();
+
+    let Some(path) = result.and_then(|path| path.as_path().map(|p| p.to_path_buf())) else {
+        return Ok(None);
+    };
+    let written = path.clone();
+    blocking(move || std::fs::write(&written, content).map_err(|e| format!("Failed to write export file: {}", e))).await?;
+    crate::logger::AppLogger::get().info(&format!("已导出下载历史: {}", path.display()));
+    Ok(Some(path.to_string_lossy().to_string()))
+}
+
+/// 从 yt-dlp --download-archive 格式的文件导入下载存档，返回新增条数（用户取消时返回 None）
+#[tauri::command]
+pub async fn import_download_archive(app: AppHandle) -> Result<Option<usize>, String> {
+    use tauri_plugin_dialog::DialogExt;
+
+    let result = app.dialog()
+        .file()
+        .set_title("导入下载存档")
+        .add_filter("Text", &["txt"])
+        .blocking_pick_file();
+
+    match result.and_then(|path| path.as_path().map(|p| p.to_path_buf())) {
+        Some(path) => blocking(move || archive::import_archive(&path)).await.map(Some),
+        None => Ok(None),
+    }
+}
+
+/// 导出 yt-dlp --download-archive 格式的下载存档，返回导出条数（用户取消时返回 None）
+#[tauri::command]
+pub async fn export_download_archive(app: AppHandle) -> Result<Option<usize>, String> {
+    use tauri_plugin_dialog::DialogExt;
+
+    let result = app.dialog()
+        .file()
+        .set_title("导出下载存档")
+        .add_filter("Text", &["txt"])
+        .set_file_name("archive.txt")
+        .blocking_save_file();
+
+    match result.and_then(|path| path.as_path().map(|p| p.to_path_buf())) {
+        Some(path) => blocking(move || archive::export_archive(&path)).await.map(Some),
+        None => Ok(None),
+    }
+}
+
+/// 删除单条下载历史
+#[tauri::command]
+pub async fn delete_history_item(id: String) -> Result<(), String> {
+    blocking(move || HistoryStore::get().delete(&id)).await
 }
 
 /// 清空下载历史
 #[tauri::command]
 pub async fn clear_download_history() -> Result<(), String> {
-    // TODO: 实现清空历史记录
-    Ok(())
+    blocking(|| HistoryStore::get().clear()).await
+}
+
+/// 在阻塞线程中执行同步的数据库和文件操作，不占用异步命令的工作线程
+async fn blocking<T, F>(f: F) -> Result<T, String>
+where
+    T: Send + 'static,
+    F: FnOnce() -> Result<T, String> + Send + 'static,
+{
+    tokio::task::spawn_blocking(f)
+        .await
+        .map_err(|e| format!("Background task failed: {}", e))?
 }
 
 /// 打开文件位置
@@ -1,12 +1,102 @@
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 use serde::{Deserialize, Serialize};
 use tauri::{AppHandle, Manager};
+use crate::filename::FilenameOptions;
+use crate::retry::RetryPolicy;
+use crate::rules::OrganizeRule;
+use crate::staging::StagingConfig;
+use crate::types::{AudioOptions, SubtitleOptions};
+use crate::watchdog::WatchdogConfig;
+
+/// 应用数据目录（日志、下载队列等运行时数据）
+pub fn app_data_dir() -> Option<PathBuf> {
+    dirs::data_dir().map(|dir| dir.join("com.youtube-downloader.desktop"))
+}
+
+/// 先写临时文件再重命名，避免崩溃时留下损坏的数据文件
+pub fn write_file_atomic(path: &Path, content: &str) -> Result<(), String> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    }
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    std::fs::write(&tmp, content).map_err(|e| e.to_string())?;
+    std::fs::rename(&tmp, path).map_err(|e| e.to_string())
+}
+
+/// 默认同时进行的下载数
+pub const DEFAULT_MAX_CONCURRENT_DOWNLOADS: usize = 3;
+
+/// 默认视频信息获取超时（秒）
+pub const DEFAULT_INFO_TIMEOUT_SECS: u64 = 120;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct AppConfig {
     pub default_save_path: Option<String>,
     pub language: String,
     pub prefer_hdr: bool,
+    /// 为所有下载包含字幕
     pub include_subtitles: bool,
+    /// 下载未指定字幕选项时使用的默认选项
+    pub subtitles: SubtitleOptions,
+    /// 仅音频下载未指定提取选项时使用的默认选项
+    pub audio: AudioOptions,
+    /// 文件名模板（yt-dlp 输出模板，相对于保存路径）
+    pub filename_template: String,
+    /// 文件名处理：同名文件、长度限制和字符限制
+    pub filename: FilenameOptions,
+    /// 下载暂存目录（完成后移动到保存目录）
+    pub staging: StagingConfig,
+    /// 整理规则（按顺序匹配，第一个匹配的规则决定保存目录）
+    pub organize_rules: Vec<OrganizeRule>,
+    /// 下载队列同时运行的最大任务数
+    pub max_concurrent_downloads: usize,
+    /// 获取视频信息的超时时间（秒），超时后终止 yt-dlp 进程
+    pub info_timeout_secs: u64,
+    /// 下载停滞检测
+    pub watchdog: WatchdogConfig,
+    /// 网络错误时自动降低画质重试
+    pub quality_fallback: QualityFallbackConfig,
+    /// yt-dlp 重试参数和下载失败后的自动重试
+    pub retry: RetryPolicy,
+    /// 视频已下载过时的处理方式
+    pub duplicate_policy: DuplicatePolicy,
+}
+
+/// 视频已下载过（画质不低于本次且文件仍在，或在导入的下载存档中）时的处理方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DuplicatePolicy {
+    /// 跳过下载
+    Skip,
+    /// 暂停任务，等待用户确认是否重新下载
+    #[default]
+    Prompt,
+    /// 不检查，总是重新下载
+    Redownload,
+}
+
+/// 画质自动降级策略（默认关闭）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct QualityFallbackConfig {
+    pub enabled: bool,
+    /// 最多降级尝试次数
+    pub max_attempts: u32,
+    /// 不低于该高度（如 360 表示 360p）
+    pub min_height: u32,
+}
+
+impl Default for QualityFallbackConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_attempts: 3,
+            min_height: 360,
+        }
+    }
 }
 
 impl Default for AppConfig {
@@ -16,6 +106,21 @@ impl Default for AppConfig {
             language: "auto".to_string(),
             prefer_hdr: false,
             include_subtitles: false,
+            subtitles: SubtitleOptions {
+                languages: vec!["en".to_string()],
+                ..Default::default()
+            },
+            audio: AudioOptions::default(),
+            filename_template: crate::filename::DEFAULT_TEMPLATE.to_string(),
+            filename: FilenameOptions::default(),
+            staging: StagingConfig::default(),
+            organize_rules: Vec::new(),
+            max_concurrent_downloads: DEFAULT_MAX_CONCURRENT_DOWNLOADS,
+            info_timeout_secs: DEFAULT_INFO_TIMEOUT_SECS,
+            watchdog: WatchdogConfig::default(),
+            quality_fallback: QualityFallbackConfig::default(),
+            retry: RetryPolicy::default(),
+            duplicate_policy: DuplicatePolicy::default(),
         }
     }
 }
@@ -58,6 +163,11 @@ impl AppConfig {
         Ok(())
     }
     
+    /// 视频信息获取超时
+    pub fn info_timeout(&self) -> Duration {
+        Duration::from_secs(self.info_timeout_secs.max(1))
+    }
+
     pub fn get_default_save_path(&self) -> Option<String> {
         self.default_save_path.clone().or_else(|| {
             // 如果没有设置默认路径，使用系统下载目录
@@ -0,0 +1,674 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use serde::{Deserialize, Serialize};
+use crate::types::{PlaylistPosition, VideoInfo};
+
+/// 默认文件名模板
+pub const DEFAULT_TEMPLATE: &str = "%(title)s.%(ext)s";
+
+/// 预览时使用的扩展名（合并 DASH 格式时的默认容器）
+const PREVIEW_EXT: &str = "mp4";
+
+/// 字段缺失时的占位符（与 yt-dlp 一致）
+const MISSING_VALUE: &str = "NA";
+
+/// 文件名（不含扩展名）默认的最大字节数，为 .f137.webm.part、.en.srt 等后缀预留空间
+const DEFAULT_MAX_BYTES: usize = 200;
+
+/// 文件名最大字节数设置的下限
+const MIN_MAX_BYTES: usize = 16;
+
+/// 常见文件系统（NTFS、exFAT、ext4、APFS）中单个路径组成部分的最大字节数
+const MAX_COMPONENT_BYTES: usize = 255;
+
+/// Windows 和 exFAT 不允许的字符，替换为相似的全角字符（与 yt-dlp 一致）
+const RESERVED_CHARS: &[(char, char)] = &[
+    ('<', '＜'),
+    ('>', '＞'),
+    (':', '：'),
+    ('"', '＂'),
+    ('/', '⧸'),
+    ('\\', '⧹'),
+    ('|', '｜'),
+    ('?', '？'),
+    ('*', '＊'),
+];
+
+/// Windows 保留的设备名
+const RESERVED_NAMES: &[&str] = &[
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8", "COM9",
+    "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// 判断同名文件时视为下载结果的扩展名（不包括缩略图、字幕和临时文件）
+const MEDIA_EXTENSIONS: &[&str] = &[
+    "mp4", "mkv", "webm", "mov", "m4v", "flv", "avi", "m4a", "mp3", "opus", "ogg", "flac", "wav", "aac",
+];
+
+/// 模板中可以使用的字段（均可由视频信息渲染预览）
+const FIELDS: &[&str] = &[
+    "id",
+    "title",
+    "uploader",
+    "upload_date",
+    "duration",
+    "view_count",
+    "ext",
+    "playlist_title",
+    "playlist_index",
+    "playlist_count",
+];
+
+/// 由应用填入的播放列表字段：每个条目以单个视频下载，yt-dlp 无法得知其所在的播放列表
+const PLAYLIST_FIELDS: &[&str] = &["playlist_title", "playlist_index", "playlist_count"];
+
+/// 文件名模板预设
+#[derive(Debug, Clone, Serialize)]
+pub struct FilenamePreset {
+    pub name: &'static str,
+    pub template: &'static str,
+}
+
+pub const PRESETS: &[FilenamePreset] = &[
+    FilenamePreset { name: "title", template: DEFAULT_TEMPLATE },
+    FilenamePreset { name: "title [id]", template: "%(title)s [%(id)s].%(ext)s" },
+    FilenamePreset { name: "date - title", template: "%(upload_date)s - %(title)s.%(ext)s" },
+    FilenamePreset { name: "uploader/title", template: "%(uploader)s/%(title)s.%(ext)s" },
+    FilenamePreset { name: "uploader/date - title [id]", template: "%(uploader)s/%(upload_date)s - %(title)s [%(id)s].%(ext)s" },
+    FilenamePreset { name: "playlist/index - title", template: "%(playlist_title)s/%(playlist_index)03d - %(title)s.%(ext)s" },
+];
+
+/// 已存在同名文件时的处理方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CollisionPolicy {
+    /// 保留已有文件，不重新下载
+    #[default]
+    Skip,
+    /// 删除已有文件后重新下载
+    Overwrite,
+    /// 添加数字后缀，如 title (1)
+    Number,
+    /// 添加视频 ID，如 title [abc123]（仍然重名时再添加数字后缀）
+    Id,
+}
+
+/// 文件名处理设置
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FilenameOptions {
+    pub collision: CollisionPolicy,
+    /// 只使用 ASCII 字符（其他字符和空格替换为 _）
+    pub restrict_ascii: bool,
+    /// 文件名（不含扩展名）的最大字节数，超出时优先截短最长的字段
+    pub max_bytes: usize,
+}
+
+impl Default for FilenameOptions {
+    fn default() -> Self {
+        Self {
+            collision: CollisionPolicy::default(),
+            restrict_ascii: false,
+            max_bytes: DEFAULT_MAX_BYTES,
+        }
+    }
+}
+
+impl FilenameOptions {
+    /// 对应的 yt-dlp 参数：无法预先获取视频信息、由 yt-dlp 渲染字段时按相同规则处理
+    pub fn ytdlp_args(&self) -> Vec<&'static str> {
+        let mut args = vec!["--windows-filenames"];
+        if self.restrict_ascii {
+            args.push("--restrict-filenames");
+        }
+        // 覆盖时由应用删除已有文件（--force-overwrites 会同时禁用断点续传）
+        if self.collision == CollisionPolicy::Skip {
+            args.push("--no-overwrites");
+        }
+        args
+    }
+
+    fn max_bytes(&self) -> usize {
+        self.max_bytes.clamp(MIN_MAX_BYTES, MAX_COMPONENT_BYTES)
+    }
+}
+
+/// 解析后的输出路径
+#[derive(Debug, Clone, PartialEq)]
+pub struct OutputPath {
+    /// 传给 yt-dlp -o 的模板
+    pub template: String,
+    /// 同名的已有文件（处理方式为跳过或覆盖时）
+    pub existing: Vec<PathBuf>,
+}
+
+/// 模板片段
+#[derive(Debug, Clone, PartialEq)]
+enum Segment {
+    Literal(String),
+    /// 字段及其格式（如 %(playlist_index)03d 的 name 为 playlist_index，spec 为 03d）
+    Field { name: String, spec: String },
+}
+
+/// 解析 yt-dlp 风格的模板，只支持 %(name)s、%(name)d（可带宽度，如 %(name)03d）和 %%
+fn parse(template: &str) -> Result<Vec<Segment>, String> {
+    let mut segments = Vec::new();
+    let mut literal = String::new();
+    let mut chars = template.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            literal.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('%') => literal.push('%'),
+            Some('(') => {
+                let mut name = String::new();
+                loop {
+                    match chars.next() {
+                        Some(')') => break,
+                        Some(c) => name.push(c),
+                        None => return Err(format!("Unclosed field in filename template: %({}", name)),
+                    }
+                }
+                let mut spec = String::new();
+                while let Some(c) = chars.next_if(|c| c.is_ascii_digit() || *c == '-') {
+                    spec.push(c);
+                }
+                match chars.next() {
+                    Some(conversion @ ('s' | 'd')) => spec.push(conversion),
+                    _ => return Err(format!("Field %({}) must end with s or d", name)),
+                }
+                if !literal.is_empty() {
+                    segments.push(Segment::Literal(std::mem::take(&mut literal)));
+                }
+                segments.push(Segment::Field { name, spec });
+            }
+            _ => return Err("Use %% for a literal % in filename templates".to_string()),
+        }
+    }
+    if !literal.is_empty() {
+        segments.push(Segment::Literal(literal));
+    }
+    Ok(segments)
+}
+
+/// 按路径分隔符拆分模板片段
+fn split_components(segments: &[Segment]) -> Vec<Vec<Segment>> {
+    let mut components = vec![Vec::new()];
+    for segment in segments {
+        match segment {
+            Segment::Literal(text) => {
+                for (i, part) in text.split(['/', '\\']).enumerate() {
+                    if i > 0 {
+                        components.push(Vec::new());
+                    }
+                    if !part.is_empty() {
+                        components.last_mut().unwrap().push(Segment::Literal(part.to_string()));
+                    }
+                }
+            }
+            field => components.last_mut().unwrap().push(field.clone()),
+        }
+    }
+    components
+}
+
+/// 拆分文件名模板：返回目录部分和文件名部分（不含结尾的 .%(ext)s）
+fn split_path(mut segments: Vec<Segment>) -> Result<(Vec<Vec<Segment>>, Vec<Segment>), String> {
+    let ext = segments.pop();
+    let ends_with_ext = matches!(&ext, Some(Segment::Field { name, spec }) if name == "ext" && spec == "s")
+        && matches!(segments.last(), Some(Segment::Literal(text)) if text.ends_with('.'));
+    if !ends_with_ext {
+        return Err("Filename template must end with .%(ext)s".to_string());
+    }
+    if let Some(Segment::Literal(text)) = segments.last_mut() {
+        text.pop();
+        if text.is_empty() {
+            segments.pop();
+        }
+    }
+
+    let mut components = split_components(&segments);
+    let stem = components.pop().unwrap_or_default();
+    if stem.is_empty() {
+        return Err("Filename template has an empty file name".to_string());
+    }
+    Ok((components, stem))
+}
+
+/// 校验模板：字段必须已知，必须以扩展名结尾，且只能是保存目录下的相对路径
+pub fn validate(template: &str) -> Result<(), String> {
+    if template.trim().is_empty() {
+        return Err("Filename template is empty".to_string());
+    }
+    let segments = parse(template)?;
+    check_fields(&segments)?;
+
+    // 字段值中的路径分隔符会被替换，只需检查模板本身的路径
+    let skeleton: String = segments
+        .iter()
+        .map(|segment| match segment {
+            Segment::Literal(text) => text.as_str(),
+            Segment::Field { .. } => "x",
+        })
+        .collect();
+    let is_absolute = skeleton.starts_with(['/', '\\', '~']) || skeleton.chars().nth(1) == Some(':');
+    if is_absolute || skeleton.split(['/', '\\']).any(|part| part.trim() == "..") {
+        return Err("Filename template must be a path inside the save folder".to_string());
+    }
+    let is_reserved = |c: char| c != '/' && c != '\\' && RESERVED_CHARS.iter().any(|(reserved, _)| *reserved == c);
+    if skeleton.chars().any(|c| c.is_control() || is_reserved(c)) {
+        return Err("Filename template contains characters that are not allowed in file names".to_string());
+    }
+
+    split_path(segments).map(|_| ())
+}
+
+/// 校验目录模板（整理规则的保存目录）：字段必须已知，可以是绝对路径
+pub fn validate_directory(template: &str) -> Result<(), String> {
+    let segments = parse(template)?;
+    check_fields(&segments)?;
+    if segments.iter().any(|s| matches!(s, Segment::Field { name, .. } if name == "ext")) {
+        return Err("%(ext)s cannot be used in a folder".to_string());
+    }
+    Ok(())
+}
+
+fn check_fields(segments: &[Segment]) -> Result<(), String> {
+    for segment in segments {
+        if let Segment::Field { name, .. } = segment {
+            if !FIELDS.contains(&name.as_str()) {
+                return Err(format!("Unknown field in filename template: {}", name));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// 清理字段值：去掉控制字符，替换 Windows/exFAT 不允许的字符（包括路径分隔符，防止值创建目录或跳出保存目录）
+fn sanitize_value(value: &str, restrict_ascii: bool) -> String {
+    if matches!(value.trim(), "." | "..") {
+        return "_".to_string();
+    }
+    let chars = value.chars().filter(|c| !c.is_control());
+    if !restrict_ascii {
+        return chars
+            .map(|c| RESERVED_CHARS.iter().find(|(reserved, _)| *reserved == c).map_or(c, |(_, replacement)| *replacement))
+            .collect();
+    }
+
+    // 只保留 ASCII 字母数字和少量标点，其余替换为 _ 并合并连续的 _
+    let mut result = String::new();
+    for c in chars {
+        let c = if c.is_ascii_alphanumeric() || "-.,+()[]".contains(c) { c } else { '_' };
+        if !(c == '_' && result.ends_with('_')) {
+            result.push(c);
+        }
+    }
+    result.trim_matches('_').to_string()
+}
+
+/// 清理路径组成部分：去掉 Windows 会忽略的结尾的点和空格，避开保留的设备名
+fn sanitize_component(component: &str) -> String {
+    if matches!(component, "" | "." | "..") {
+        return component.to_string();
+    }
+    let trimmed = component.trim().trim_end_matches(['.', ' ']);
+    if trimmed.is_empty() {
+        return "_".to_string();
+    }
+    let base = trimmed.split('.').next().unwrap_or_default();
+    if RESERVED_NAMES.iter().any(|name| name.eq_ignore_ascii_case(base)) {
+        return format!("{}_{}", base, &trimmed[base.len()..]);
+    }
+    trimmed.to_string()
+}
+
+/// 在字符边界处截断到最多 max 字节
+fn truncate_bytes(value: &str, max: usize) -> &str {
+    if value.len() <= max {
+        return value;
+    }
+    let end = (0..=max).rev().find(|i| value.is_char_boundary(*i)).unwrap_or(0);
+    &value[..end]
+}
+
+/// 按格式渲染字段值（与 Python 的 % 格式化一致：默认右对齐，- 左对齐，数字可用 0 补齐）
+fn format_value(value: &str, spec: &str) -> String {
+    let (flags, conversion) = spec.split_at(spec.len() - 1);
+    let width: usize = flags.trim_start_matches(['-', '0']).parse().unwrap_or(0);
+    match value.parse::<i64>() {
+        Ok(number) if conversion == "d" && flags.starts_with('0') => format!("{:0width$}", number, width = width),
+        // 数字字段缺失（NA）时不补齐
+        Err(_) if conversion == "d" => value.to_string(),
+        _ if flags.starts_with('-') => format!("{:<width$}", value, width = width),
+        _ => format!("{:>width$}", value, width = width),
+    }
+}
+
+/// 渲染路径的一个组成部分：清理字段值，超过 max_bytes 时优先截短最长的字段
+fn render_component(segments: &[Segment], values: &HashMap<&str, String>, restrict_ascii: bool, max_bytes: usize) -> String {
+    let mut parts: Vec<(bool, String)> = segments
+        .iter()
+        .map(|segment| match segment {
+            Segment::Literal(text) => (false, text.clone()),
+            Segment::Field { name, spec } => {
+                let value = values.get(name.as_str()).map_or(MISSING_VALUE, String::as_str);
+                (true, format_value(&sanitize_value(value, restrict_ascii), spec))
+            }
+        })
+        .collect();
+
+    loop {
+        let total: usize = parts.iter().map(|(_, text)| text.len()).sum();
+        if total <= max_bytes {
+            break;
+        }
+        let longest = parts
+            .iter_mut()
+            .filter(|(is_field, text)| *is_field && !text.is_empty())
+            .max_by_key(|(_, text)| text.len());
+        let Some((_, longest)) = longest else {
+            break;
+        };
+        let keep = longest.len().saturating_sub(total - max_bytes);
+        *longest = truncate_bytes(longest, keep).trim_end().to_string();
+    }
+
+    let joined: String = parts.into_iter().map(|(_, text)| text).collect();
+    sanitize_component(truncate_bytes(&joined, max_bytes))
+}
+
+/// 渲染相对于保存目录、不含扩展名的路径
+fn render_stem_path(template: &str, values: &HashMap<&str, String>, options: &FilenameOptions) -> Result<PathBuf, String> {
+    let (dirs, stem) = split_path(parse(template)?)?;
+    let mut path = PathBuf::new();
+    for dir in &dirs {
+        path.push(render_component(dir, values, options.restrict_ascii, MAX_COMPONENT_BYTES));
+    }
+    path.push(render_component(&stem, values, options.restrict_ascii, options.max_bytes()));
+    Ok(path)
+}
+
+/// 与 stem_path 同名（只有扩展名不同）的已下载文件
+fn existing_media(stem_path: &Path) -> Vec<PathBuf> {
+    let (Some(dir), Some(stem)) = (stem_path.parent(), stem_path.file_name()) else {
+        return Vec::new();
+    };
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.file_stem() == Some(stem))
+        .filter(|path| {
+            path.extension()
+                .and_then(|ext| ext.to_str())
+                .is_some_and(|ext| MEDIA_EXTENSIONS.contains(&ext.to_lowercase().as_str()))
+        })
+        .collect()
+}
+
+/// 给文件名添加后缀，必要时截短原文件名使总长度不超过 max_bytes
+fn with_suffix(stem_path: &Path, suffix: &str, max_bytes: usize) -> PathBuf {
+    let name = stem_path.file_name().map(|name| name.to_string_lossy()).unwrap_or_default();
+    let name = truncate_bytes(&name, max_bytes.saturating_sub(suffix.len())).trim_end();
+    stem_path.with_file_name(format!("{}{}", name, suffix))
+}
+
+/// 给文件名（不含扩展名）添加数字后缀，如 title (1)
+pub fn numbered(stem_path: &Path, n: usize, options: &FilenameOptions) -> PathBuf {
+    with_suffix(stem_path, &format!(" ({})", n), options.max_bytes())
+}
+
+/// 按同名处理方式确定最终路径（不含扩展名），返回路径和需要保留或覆盖的已有文件
+fn avoid_collision(stem_path: PathBuf, video_id: &str, options: &FilenameOptions) -> (PathBuf, Vec<PathBuf>) {
+    let existing = existing_media(&stem_path);
+    if existing.is_empty() {
+        return (stem_path, existing);
+    }
+    let numbered = |path: &Path| {
+        (1..)
+            .map(|n| numbered(path, n, options))
+            .find(|candidate| existing_media(candidate).is_empty())
+            .unwrap_or_else(|| path.to_path_buf())
+    };
+    match options.collision {
+        CollisionPolicy::Skip | CollisionPolicy::Overwrite => (stem_path, existing),
+        CollisionPolicy::Number => (numbered(&stem_path), Vec::new()),
+        CollisionPolicy::Id => {
+            let suffix = format!(" [{}]", sanitize_value(video_id, options.restrict_ascii));
+            let with_id = with_suffix(&stem_path, &suffix, options.max_bytes());
+            if existing_media(&with_id).is_empty() {
+                (with_id, Vec::new())
+            } else {
+                (numbered(&with_id), Vec::new())
+            }
+        }
+    }
+}
+
+/// 播放列表字段的值（不是来自播放列表时与 yt-dlp 一样为 NA）
+fn playlist_values(playlist: Option<&PlaylistPosition>) -> HashMap<&'static str, String> {
+    match playlist {
+        Some(position) => HashMap::from([
+            ("playlist_title", position.playlist_title.clone()),
+            ("playlist_index", position.index.to_string()),
+            ("playlist_count", position.count.to_string()),
+        ]),
+        None => PLAYLIST_FIELDS.iter().map(|field| (*field, MISSING_VALUE.to_string())).collect(),
+    }
+}
+
+/// 视频信息中可以用于模板的字段值
+fn video_values(info: &VideoInfo) -> HashMap<&'static str, String> {
+    let mut values = HashMap::from([
+        ("id", info.id.clone()),
+        ("title", info.title.clone()),
+        ("ext", PREVIEW_EXT.to_string()),
+        ("duration", info.duration.to_string()),
+        ("view_count", info.view_count.to_string()),
+    ]);
+    for (field, value) in [("uploader", &info.uploader), ("upload_date", &info.upload_date)] {
+        if !value.is_empty() {
+            values.insert(field, value.clone());
+        }
+    }
+    values
+}
+
+/// 只填入已知字段、其余字段交给 yt-dlp 渲染的模板；由 yt-dlp 渲染的文本字段按字节截短（如 %(title).200B）
+fn render_for_ytdlp(segments: &[Segment], values: &HashMap<&str, String>, options: &FilenameOptions) -> String {
+    let is_text = |name: &str, spec: &str| spec == "s" && name != "ext" && !values.contains_key(name);
+    let unresolved = segments
+        .iter()
+        .filter(|s| matches!(s, Segment::Field { name, spec } if is_text(name, spec)))
+        .count();
+    let budget = options.max_bytes() / unresolved.max(1);
+
+    segments
+        .iter()
+        .map(|segment| match segment {
+            Segment::Literal(text) => text.replace('%', "%%"),
+            Segment::Field { name, spec } => match values.get(name.as_str()) {
+                Some(value) => format_value(&sanitize_value(value, options.restrict_ascii), spec).replace('%', "%%"),
+                None if is_text(name, spec) => format!("%({}).{}B", name, budget),
+                None => format!("%({}){}", name, spec),
+            },
+        })
+        .collect()
+}
+
+/// 构建传给 yt-dlp 的输出模板
+///
+/// 有视频信息时由应用渲染完整路径（清理字符、按字节截短、处理同名文件），只把扩展名留给 yt-dlp，与预览一致；
+/// 否则只填入播放列表字段，其余字段由 yt-dlp 按 ytdlp_args 中对应的规则渲染
+pub fn output_template(
+    output_path: &str,
+    template: &str,
+    playlist: Option<&PlaylistPosition>,
+    info: Option<&VideoInfo>,
+    options: &FilenameOptions,
+) -> Result<OutputPath, String> {
+    validate(template)?;
+    let mut values = playlist_values(playlist);
+
+    let Some(info) = info else {
+        let rendered = render_for_ytdlp(&parse(template)?, &values, options);
+        // 保存路径中的 % 也会被 yt-dlp 当作模板解析
+        let output_dir = output_path.replace('%', "%%");
+        return Ok(OutputPath {
+            template: Path::new(&output_dir).join(rendered).to_string_lossy().to_string(),
+            existing: Vec::new(),
+        });
+    };
+
+    values.extend(video_values(info));
+    let stem_path = Path::new(output_path).join(render_stem_path(template, &values, options)?);
+    let (stem_path, existing) = avoid_collision(stem_path, &info.id, options);
+    Ok(OutputPath {
+        template: format!("{}.%(ext)s", stem_path.to_string_lossy().replace('%', "%%")),
+        existing,
+    })
+}
+
+/// 用视频信息渲染目录模板（整理规则的保存目录），字段值按与文件名相同的规则清理
+pub fn render_directory(template: &str, info: &VideoInfo, options: &FilenameOptions) -> Result<String, String> {
+    validate_directory(template)?;
+    let values = video_values(info);
+    let components: Vec<String> = split_components(&parse(template)?)
+        .iter()
+        .map(|component| render_component(component, &values, options.restrict_ascii, MAX_COMPONENT_BYTES))
+        .collect();
+    Ok(components.join("/"))
+}
+
+/// 预览视频按模板下载后的路径（不下载），与下载时使用相同的清理、截短和同名处理规则
+pub fn preview(info: &VideoInfo, output_path: Option<&str>, template: &str, options: &FilenameOptions) -> Result<String, String> {
+    validate(template)?;
+    let mut values = playlist_values(None);
+    values.extend(video_values(info));
+    let relative = render_stem_path(template, &values, options)?;
+    let stem_path = match output_path {
+        Some(dir) => avoid_collision(Path::new(dir).join(relative), &info.id, options).0,
+        None => relative,
+    };
+    Ok(format!("{}.{}", stem_path.to_string_lossy(), PREVIEW_EXT))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::test_support::video_info;
+
+    #[test]
+    fn test_validate() {
+        for preset in PRESETS {
+            assert!(validate(preset.template).is_ok(), "{}", preset.template);
+        }
+        assert!(validate("%(title)s 100%%.%(ext)s").is_ok());
+        assert!(validate("").is_err());
+        assert!(validate("%(title)s").is_err());
+        assert!(validate("%(ext)s/%(title)s").is_err());
+        assert!(validate(".%(ext)s").is_err());
+        assert!(validate("a|b %(title)s.%(ext)s").is_err());
+        assert!(validate("%(channel_url)s.%(ext)s").is_err());
+        assert!(validate("%(title.%(ext)s").is_err());
+        assert!(validate("%(title)x.%(ext)s").is_err());
+        assert!(validate("100% %(title)s.%(ext)s").is_err());
+        assert!(validate("../%(title)s.%(ext)s").is_err());
+        assert!(validate("a/../../%(title)s.%(ext)s").is_err());
+        assert!(validate("/tmp/%(title)s.%(ext)s").is_err());
+        assert!(validate("C:\\%(title)s.%(ext)s").is_err());
+    }
+
+    #[test]
+    fn test_preview() {
+        let options = FilenameOptions::default();
+        let template = "%(uploader)s/%(upload_date)s - %(title)s [%(id)s].%(ext)s";
+        assert_eq!(
+            preview(&video_info("abc123", "AC/DC Live"), Some("/dl"), template, &options).unwrap(),
+            "/dl/Someone/20240102 - AC⧸DC Live [abc123].mp4"
+        );
+        let info = VideoInfo { uploader: String::new(), ..video_info("abc123", "AC/DC Live") };
+        assert_eq!(
+            preview(&info, None, "%(uploader)s - %(playlist_index)03d.%(ext)s", &options).unwrap(),
+            "NA - NA.mp4"
+        );
+    }
+
+    #[test]
+    fn test_sanitize() {
+        assert_eq!(sanitize_value("What? <Live> | \"Best\": 1/2*", false), "What？ ＜Live＞ ｜ ＂Best＂： 1⧸2＊");
+        assert_eq!(sanitize_value("Café déjà vu / 東京", true), "Caf_d_j_vu");
+        assert_eq!(sanitize_value("..", false), "_");
+        assert_eq!(sanitize_component("Ends with dots... "), "Ends with dots");
+        assert_eq!(sanitize_component("con"), "con_");
+        assert_eq!(sanitize_component("CON.backup"), "CON_.backup");
+        assert_eq!(sanitize_component(".."), "..");
+    }
+
+    #[test]
+    fn test_truncates_by_bytes() {
+        let options = FilenameOptions { max_bytes: 40, ..Default::default() };
+        // 每个汉字 3 字节：截短标题，保留 ID
+        let info = VideoInfo { title: "非常长的中文标题".repeat(5), ..video_info("abc123", "AC/DC Live") };
+        let path = preview(&info, None, "%(title)s [%(id)s].%(ext)s", &options).unwrap();
+        assert_eq!(path, "非常长的中文标题非常 [abc123].mp4");
+
+        let info = VideoInfo { title: "标题".repeat(100), ..video_info("abc123", "AC/DC Live") };
+        let stem = preview(&info, None, DEFAULT_TEMPLATE, &FilenameOptions::default()).unwrap();
+        assert_eq!(stem.strip_suffix(".mp4").unwrap().len(), 198);
+    }
+
+    #[test]
+    fn test_collision_policies() {
+        let dir = std::env::temp_dir().join(format!("filename-test-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("AC⧸DC Live.mp4"), b"").unwrap();
+        std::fs::write(dir.join("AC⧸DC Live (1).webm"), b"").unwrap();
+        // 缩略图和未完成的下载不算重名
+        std::fs::write(dir.join("Other.jpg"), b"").unwrap();
+        std::fs::write(dir.join("Other.mp4.part"), b"").unwrap();
+        let out = dir.to_string_lossy().to_string();
+
+        let resolve = |template: &str, collision: CollisionPolicy| {
+            let options = FilenameOptions { collision, ..Default::default() };
+            output_template(&out, template, None, Some(&video_info("abc123", "AC/DC Live")), &options).unwrap()
+        };
+        let skip = resolve(DEFAULT_TEMPLATE, CollisionPolicy::Skip);
+        assert_eq!(skip.template, format!("{}/AC⧸DC Live.%(ext)s", out));
+        assert_eq!(skip.existing, vec![dir.join("AC⧸DC Live.mp4")]);
+        assert_eq!(resolve(DEFAULT_TEMPLATE, CollisionPolicy::Number).template, format!("{}/AC⧸DC Live (2).%(ext)s", out));
+        assert_eq!(resolve(DEFAULT_TEMPLATE, CollisionPolicy::Id).template, format!("{}/AC⧸DC Live [abc123].%(ext)s", out));
+        let other = resolve("Other.%(ext)s", CollisionPolicy::Number);
+        assert_eq!(other.template, format!("{}/Other.%(ext)s", out));
+        assert!(other.existing.is_empty());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_output_template_without_info() {
+        let playlist = PlaylistPosition {
+            playlist_id: "PL1".to_string(),
+            playlist_title: "Mix 100%".to_string(),
+            index: 7,
+            count: 12,
+        };
+        let options = FilenameOptions::default();
+        let template = "%(playlist_title)s/%(playlist_index)03d - %(title)s.%(ext)s";
+        assert_eq!(
+            output_template("/dl", template, Some(&playlist), None, &options).unwrap().template,
+            "/dl/Mix 100%%/007 - %(title).200B.%(ext)s"
+        );
+        assert_eq!(
+            output_template("/dl", template, None, None, &options).unwrap().template,
+            "/dl/NA/NA - %(title).200B.%(ext)s"
+        );
+        assert!(output_template("/dl", "../%(title)s.%(ext)s", None, None, &options).is_err());
+    }
+}
@@ -0,0 +1,745 @@
+use std::path::PathBuf;
+use std::sync::Mutex;
+use once_cell::sync::Lazy;
+use rusqlite::{params, params_from_iter, Connection, Row};
+use serde::{Deserialize, Serialize};
+use crate::types::{DownloadArtifact, DownloadAttempt, DownloadHistoryItem, DownloadHistoryPage, HistoryStatus, VideoInfo};
+
+/// 默认每页条数
+pub const DEFAULT_PAGE_SIZE: u32 = 50;
+
+/// 每页最多条数
+const MAX_PAGE_SIZE: u32 = 500;
+
+/// 下载历史存储（单例模式，SQLite 数据库位于应用数据目录）
+pub struct HistoryStore {
+    conn: Mutex<Option<Connection>>,
+}
+
+/// 全局历史存储，首次使用时打开数据库
+static STORE: Lazy<HistoryStore> = Lazy::new(|| HistoryStore {
+    conn: Mutex::new(None),
+});
+
+/// 当前数据库结构版本（PRAGMA user_version）
+const SCHEMA_VERSION: i32 = 1;
+
+/// yt-dlp --write-info-json 输出中需要的字段
+#[derive(Debug, Deserialize)]
+struct InfoJson {
+    id: String,
+    title: Option<String>,
+    uploader: Option<String>,
+    channel: Option<String>,
+    extractor_key: Option<String>,
+    duration: Option<f64>,
+    thumbnail: Option<String>,
+}
+
+/// 一次下载的结果（成功或最终失败），用于写入历史
+pub struct DownloadRecord {
+    pub task_id: String,
+    pub url: String,
+    pub file_path: Option<String>,
+    /// yt-dlp 报告的全部文件
+    pub artifacts: Vec<DownloadArtifact>,
+    pub format_id: String,
+    pub quality: Option<String>,
+    /// 决定保存目录的整理规则
+    pub rule: Option<String>,
+    pub started_at: String,
+    pub status: HistoryStatus,
+    /// 失败或跳过的原因
+    pub error: Option<String>,
+    /// 下载前获取的视频信息，yt-dlp 没有写入元数据时（如跳过下载）使用
+    pub info: Option<VideoInfo>,
+}
+
+/// 历史查询条件（字段均为可选，多个条件同时生效）
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HistoryQuery {
+    /// 标题关键字（不区分大小写）
+    pub search: Option<String>,
+    /// 完成日期下限（YYYY-MM-DD，包含当天）
+    pub date_from: Option<String>,
+    /// 完成日期上限（YYYY-MM-DD，包含当天）
+    pub date_to: Option<String>,
+    pub uploader: Option<String>,
+    pub format_id: Option<String>,
+    pub status: Option<HistoryStatus>,
+    /// 页码（从 1 开始，默认 1）
+    pub page: Option<u32>,
+    /// 每页条数（默认 DEFAULT_PAGE_SIZE）
+    pub page_size: Option<u32>,
+}
+
+/// 历史导出格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    Csv,
+    Json,
+}
+
+impl ExportFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Json => "json",
+        }
+    }
+}
+
+fn database_file() -> Option<PathBuf> {
+    crate::config::app_data_dir().map(|dir| dir.join("history.db"))
+}
+
+/// 下载时 yt-dlp 写入的视频元数据文件（下载完成后读取并删除）
+pub fn metadata_file(task_id: &str) -> Option<PathBuf> {
+    crate::config::app_data_dir().map(|dir| dir.join("metadata").join(format!("{}.info.json", task_id)))
+}
+
+/// 传给 yt-dlp 的元数据输出模板（yt-dlp 会把扩展名替换为 .info.json）
+pub fn metadata_template(task_id: &str) -> Option<String> {
+    crate::config::app_data_dir()
+        .map(|dir| dir.join("metadata").join(format!("{}.%(ext)s", task_id)).to_string_lossy().to_string())
+}
+
+impl HistoryStore {
+    /// 获取全局历史存储
+    pub fn get() -> &'static HistoryStore {
+        &STORE
+    }
+
+    fn with_conn<T, F: FnOnce(&Connection) -> rusqlite::Result<T>>(&self, f: F) -> Result<T, String> {
+        let mut conn = self.conn.lock().unwrap();
+        if conn.is_none() {
+            let path = database_file().ok_or("Failed to get app data dir")?;
+            if let Some(dir) = path.parent() {
+                std::fs::create_dir_all(dir).map_err(|e| format!("Failed to create data dir: {}", e))?;
+            }
+            let opened = Connection::open(&path).map_err(|e| format!("Failed to open history database: {}", e))?;
+            init_schema(&opened).map_err(|e| format!("Failed to initialize history database: {}", e))?;
+            *conn = Some(opened);
+        }
+        f(conn.as_ref().unwrap()).map_err(|e| format!("History database error: {}", e))
+    }
+
+    /// 根据 yt-dlp 写入的元数据记录一次下载（元数据缺失时只记录已知字段）
+    pub fn record_download(&self, record: DownloadRecord) -> Result<(), String> {
+        let info = match read_metadata(&record.task_id) {
+            Ok(info) => Some(info),
+            Err(e) => {
+                if record.status == HistoryStatus::Finished {
+                    crate::logger::AppLogger::get().warn(&format!("读取视频元数据失败: {}", e));
+                }
+                None
+            }
+        };
+        discard_metadata(&record.task_id);
+
+        let file_path = record.file_path.filter(|_| record.status != HistoryStatus::Failed);
+        let fetched = record.info.as_ref();
+        let item = DownloadHistoryItem {
+            id: record.task_id,
+            video_id: info
+                .as_ref()
+                .map(|info| info.id.clone())
+                .or_else(|| fetched.map(|fetched| fetched.id.clone()))
+                .unwrap_or_default(),
+            title: info
+                .as_ref()
+                .map(|info| info.title.clone().unwrap_or_else(|| info.id.clone()))
+                .or_else(|| fetched.map(|fetched| fetched.title.clone()))
+                .unwrap_or_else(|| record.url.clone()),
+            uploader: info
+                .as_ref()
+                .and_then(|info| info.uploader.clone().or_else(|| info.channel.clone()))
+                .or_else(|| fetched.map(|fetched| fetched.uploader.clone()).filter(|uploader| !uploader.is_empty())),
+            // 与 yt-dlp --download-archive 一致，使用小写的提取器名
+            extractor: info.as_ref().and_then(|info| info.extractor_key.as_deref()).map(str::to_lowercase),
+            url: record.url,
+            file_path: file_path.unwrap_or_default(),
+            filesize: crate::artifacts::main_file(&record.artifacts).map(|artifact| artifact.size),
+            artifacts: record.artifacts,
+            format_id: record.format_id,
+            quality: record.quality,
+            rule: record.rule,
+            duration: info
+                .as_ref()
+                .and_then(|info| info.duration)
+                .or_else(|| fetched.map(|fetched| fetched.duration as f64).filter(|duration| *duration > 0.0)),
+            thumbnail: info
+                .and_then(|info| info.thumbnail)
+                .or_else(|| fetched.map(|fetched| fetched.thumbnail.clone()))
+                .unwrap_or_default(),
+            started_at: record.started_at,
+            download_date: chrono::Local::now().to_rfc3339(),
+            status: record.status,
+            error: record.error,
+            // 下载尝试在任务结束后由队列写入（暂停前后的尝试只有队列知道）
+            attempts: Vec::new(),
+        };
+
+        crate::logger::AppLogger::get().info(&format!(
+            "记录下载历史: {} ({}) [{}]",
+            item.title,
+            item.url,
+            item.status.as_str()
+        ));
+        self.with_conn(|conn| insert(conn, &item))
+    }
+
+    /// 保存任务的全部下载尝试，任务没有历史记录时忽略
+    pub fn record_attempts(&self, task_id: &str, attempts: &[DownloadAttempt]) -> Result<(), String> {
+        self.with_conn(|conn| update_attempts(conn, task_id, attempts))
+    }
+
+    /// 分页获取历史（最新的在前，页码从 1 开始）
+    pub fn list(&self, page: u32, page_size: u32) -> Result<DownloadHistoryPage, String> {
+        self.query(&HistoryQuery {
+            page: Some(page),
+            page_size: Some(page_size),
+            ..Default::default()
+        })
+    }
+
+    /// 按条件分页查询历史
+    pub fn query(&self, query: &HistoryQuery) -> Result<DownloadHistoryPage, String> {
+        let filter = Filter::build(query)?;
+        let page = query.page.unwrap_or(1).max(1);
+        let page_size = query.page_size.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE);
+        self.with_conn(|conn| list(conn, &filter, page, page_size))
+    }
+
+    /// 导出符合条件的全部历史（忽略分页），返回文件内容
+    pub fn export(&self, query: &HistoryQuery, format: ExportFormat) -> Result<String, String> {
+        let filter = Filter::build(query)?;
+        let items = self.with_conn(|conn| select_all(conn, &filter))?;
+        match format {
+            ExportFormat::Csv => to_csv(&items),
+            ExportFormat::Json => serde_json::to_string_pretty(&items)
+                .map_err(|e| format!("Failed to serialize history: {}", e)),
+        }
+    }
+
+    /// 查找某个视频成功下载的记录（最新的在前）
+    pub fn find_downloads(&self, video_id: &str) -> Result<Vec<DownloadHistoryItem>, String> {
+        self.with_conn(|conn| find_downloads(conn, video_id))
+    }
+
+    /// 视频是否在导入的下载存档中
+    pub fn is_archived(&self, video_id: &str) -> Result<bool, String> {
+        self.with_conn(|conn| is_archived(conn, video_id))
+    }
+
+    /// 导入下载存档条目 (提取器, 视频 ID)，返回新增的条数
+    pub fn import_archive(&self, entries: &[(String, String)]) -> Result<usize, String> {
+        self.with_conn(|conn| import_archive(conn, entries))
+    }
+
+    /// 所有已下载的视频 (提取器, 视频 ID)：导入的存档加上成功下载的历史
+    pub fn archive_entries(&self) -> Result<Vec<(String, String)>, String> {
+        self.with_conn(archive_entries)
+    }
+
+    /// 删除单条历史，记录不存在时返回错误
+    pub fn delete(&self, id: &str) -> Result<(), String> {
+        if self.with_conn(|conn| delete(conn, id))? {
+            Ok(())
+        } else {
+            Err(format!("History item not found: {}", id))
+        }
+    }
+
+    /// 清空历史
+    pub fn clear(&self) -> Result<(), String> {
+        let removed = self.with_conn(clear)?;
+        crate::logger::AppLogger::get().info(&format!("已清空下载历史（{} 条）", removed));
+        Ok(())
+    }
+}
+
+/// 删除下载失败或取消时残留的元数据文件
+pub fn discard_metadata(task_id: &str) {
+    if let Some(path) = metadata_file(task_id) {
+        let _ = std::fs::remove_file(path);
+    }
+}
+
+fn read_metadata(task_id: &str) -> Result<InfoJson, String> {
+    let metadata = metadata_file(task_id).ok_or("Failed to get app data dir")?;
+    let content = std::fs::read_to_string(&metadata)
+        .map_err(|e| format!("Failed to read video metadata: {}", e))?;
+    serde_json::from_str(&content).map_err(|e| format!("Failed to parse video metadata: {}", e))
+}
+
+fn init_schema(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS downloads (
+            id TEXT PRIMARY KEY,
+            video_id TEXT NOT NULL,
+            title TEXT NOT NULL,
+            uploader TEXT,
+            extractor TEXT,
+            url TEXT NOT NULL,
+            file_path TEXT NOT NULL,
+            artifacts TEXT NOT NULL DEFAULT '[]',
+            format_id TEXT NOT NULL,
+            quality TEXT,
+            rule TEXT,
+            filesize INTEGER,
+            duration REAL,
+            thumbnail TEXT NOT NULL,
+            started_at TEXT NOT NULL,
+            finished_at TEXT NOT NULL,
+            status TEXT NOT NULL DEFAULT 'finished',
+            error TEXT,
+            attempts TEXT NOT NULL DEFAULT '[]'
+        );
+        CREATE INDEX IF NOT EXISTS idx_downloads_finished_at ON downloads (finished_at);
+        CREATE INDEX IF NOT EXISTS idx_downloads_video_id ON downloads (video_id);
+        CREATE INDEX IF NOT EXISTS idx_downloads_uploader ON downloads (uploader);
+        -- 从 yt-dlp 下载存档导入的条目
+        CREATE TABLE IF NOT EXISTS archive (
+            extractor TEXT NOT NULL,
+            video_id TEXT NOT NULL,
+            added_at TEXT NOT NULL,
+            PRIMARY KEY (extractor, video_id)
+        );
+        CREATE INDEX IF NOT EXISTS idx_archive_video_id ON archive (video_id);",
+    )?;
+
+    conn.pragma_update(None, "user_version", SCHEMA_VERSION)
+}
+
+fn insert(conn: &Connection, item: &DownloadHistoryItem) -> rusqlite::Result<()> {
+    conn.execute(
+        "INSERT OR REPLACE INTO downloads
+            (id, video_id, title, uploader, extractor, url, file_path, artifacts, format_id, quality, rule,
+             filesize, duration, thumbnail, started_at, finished_at, status, error, attempts)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19)",
+        params![
+            item.id,
+            item.video_id,
+            item.title,
+            item.uploader,
+            item.extractor,
+            item.url,
+            item.file_path,
+            serde_json::to_string(&item.artifacts).unwrap_or_else(|_| "[]".to_string()),
+            item.format_id,
+            item.quality,
+            item.rule,
+            item.filesize.map(|size| size as i64),
+            item.duration,
+            item.thumbnail,
+            item.started_at,
+            item.download_date,
+            item.status.as_str(),
+            item.error,
+            serde_json::to_string(&item.attempts).unwrap_or_else(|_| "[]".to_string()),
+        ],
+    )?;
+    Ok(())
+}
+
+fn update_attempts(conn: &Connection, id: &str, attempts: &[DownloadAttempt]) -> rusqlite::Result<()> {
+    conn.execute(
+        "UPDATE downloads SET attempts = ?2 WHERE id = ?1",
+        params![id, serde_json::to_string(attempts).unwrap_or_else(|_| "[]".to_string())],
+    )?;
+    Ok(())
+}
+
+fn from_row(row: &Row) -> rusqlite::Result<DownloadHistoryItem> {
+    Ok(DownloadHistoryItem {
+        id: row.get("id")?,
+        video_id: row.get("video_id")?,
+        title: row.get("title")?,
+        uploader: row.get("uploader")?,
+        extractor: row.get("extractor")?,
+        url: row.get("url")?,
+        file_path: row.get("file_path")?,
+        artifacts: serde_json::from_str(&row.get::<_, String>("artifacts")?).unwrap_or_default(),
+        format_id: row.get("format_id")?,
+        quality: row.get("quality")?,
+        rule: row.get("rule")?,
+        filesize: row.get::<_, Option<i64>>("filesize")?.map(|size| size as u64),
+        duration: row.get("duration")?,
+        thumbnail: row.get("thumbnail")?,
+        started_at: row.get("started_at")?,
+        download_date: row.get("finished_at")?,
+        status: HistoryStatus::parse(&row.get::<_, String>("status")?).unwrap_or_default(),
+        error: row.get("error")?,
+        attempts: serde_json::from_str(&row.get::<_, String>("attempts")?).unwrap_or_default(),
+    })
+}
+
+/// 查询条件对应的 WHERE 子句及参数
+struct Filter {
+    clause: String,
+    params: Vec<String>,
+}
+
+impl Filter {
+    fn build(query: &HistoryQuery) -> Result<Filter, String> {
+        let mut conditions = Vec::new();
+        let mut params = Vec::new();
+        let non_empty = |value: &Option<String>| value.as_deref().map(str::trim).filter(|value| !value.is_empty()).map(str::to_string);
+
+        if let Some(search) = non_empty(&query.search) {
+            conditions.push("title LIKE ? ESCAPE '\\'");
+            params.push(format!("%{}%", escape_like(&search)));
+        }
+        // finished_at 为 RFC 3339 本地时间，前 10 位即日期
+        if let Some(date) = non_empty(&query.date_from) {
+            conditions.push("substr(finished_at, 1, 10) >= ?");
+            params.push(validate_date(&date)?);
+        }
+        if let Some(date) = non_empty(&query.date_to) {
+            conditions.push("substr(finished_at, 1, 10) <= ?");
+            params.push(validate_date(&date)?);
+        }
+        if let Some(uploader) = non_empty(&query.uploader) {
+            conditions.push("uploader = ? COLLATE NOCASE");
+            params.push(uploader);
+        }
+        if let Some(format_id) = non_empty(&query.format_id) {
+            conditions.push("format_id = ?");
+            params.push(format_id);
+        }
+        if let Some(status) = query.status {
+            conditions.push("status = ?");
+            params.push(status.as_str().to_string());
+        }
+
+        let clause = if conditions.is_empty() {
+            String::new()
+        } else {
+            format!(" WHERE {}", conditions.join(" AND "))
+        };
+        Ok(Filter { clause, params })
+    }
+}
+
+/// 转义 LIKE 通配符
+fn escape_like(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_")
+}
+
+fn validate_date(date: &str) -> Result<String, String> {
+    chrono::NaiveDate::parse_from_str(date, "%Y-%m-%d")
+        .map(|date| date.format("%Y-%m-%d").to_string())
+        .map_err(|_| format!("Invalid date: {}", date))
+}
+
+fn list(conn: &Connection, filter: &Filter, page: u32, page_size: u32) -> rusqlite::Result<DownloadHistoryPage> {
+    let total: i64 = conn.query_row(
+        &format!("SELECT COUNT(*) FROM downloads{}", filter.clause),
+        params_from_iter(&filter.params),
+        |row| row.get(0),
+    )?;
+    let offset = (page as i64 - 1) * page_size as i64;
+
+    let mut stmt = conn.prepare(&format!(
+        "SELECT * FROM downloads{} ORDER BY finished_at DESC, rowid DESC LIMIT {} OFFSET {}",
+        filter.clause, page_size, offset
+    ))?;
+    let items = stmt
+        .query_map(params_from_iter(&filter.params), from_row)?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    Ok(DownloadHistoryPage {
+        items,
+        total: total as u64,
+        page,
+        page_size,
+    })
+}
+
+fn select_all(conn: &Connection, filter: &Filter) -> rusqlite::Result<Vec<DownloadHistoryItem>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT * FROM downloads{} ORDER BY finished_at DESC, rowid DESC",
+        filter.clause
+    ))?;
+    let items = stmt
+        .query_map(params_from_iter(&filter.params), from_row)?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(items)
+}
+
+fn to_csv(items: &[DownloadHistoryItem]) -> Result<String, String> {
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    writer
+        .write_record([
+            "id", "video_id", "title", "uploader", "url", "file_path", "format_id", "quality",
+            "rule", "filesize", "duration", "started_at", "finished_at", "status", "error",
+        ])
+        .map_err(|e| format!("Failed to write CSV: {}", e))?;
+
+    for item in items {
+        writer
+            .write_record([
+                item.id.clone(),
+                item.video_id.clone(),
+                item.title.clone(),
+                item.uploader.clone().unwrap_or_default(),
+                item.url.clone(),
+                item.file_path.clone(),
+                item.format_id.clone(),
+                item.quality.clone().unwrap_or_default(),
+                item.rule.clone().unwrap_or_default(),
+                item.filesize.map(|size| size.to_string()).unwrap_or_default(),
+                item.duration.map(|duration| duration.to_string()).unwrap_or_default(),
+                item.started_at.clone(),
+                item.download_date.clone(),
+                item.status.as_str().to_string(),
+                item.error.clone().unwrap_or_default(),
+            ])
+            .map_err(|e| format!("Failed to write CSV: {}", e))?;
+    }
+
+    let bytes = writer.into_inner().map_err(|e| format!("Failed to write CSV: {}", e))?;
+    String::from_utf8(bytes).map_err(|e| format!("Failed to write CSV: {}", e))
+}
+
+fn find_downloads(conn: &Connection, video_id: &str) -> rusqlite::Result<Vec<DownloadHistoryItem>> {
+    let mut stmt = conn.prepare(
+        "SELECT * FROM downloads WHERE video_id = ?1 AND status = 'finished' ORDER BY finished_at DESC, rowid DESC",
+    )?;
+    let items = stmt
+        .query_map(params![video_id], from_row)?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(items)
+}
+
+fn is_archived(conn: &Connection, video_id: &str) -> rusqlite::Result<bool> {
+    conn.query_row("SELECT EXISTS (SELECT 1 FROM archive WHERE video_id = ?1)", params![video_id], |row| row.get(0))
+}
+
+fn import_archive(conn: &Connection, entries: &[(String, String)]) -> rusqlite::Result<usize> {
+    let added_at = chrono::Local::now().to_rfc3339();
+    let mut stmt = conn.prepare("INSERT OR IGNORE INTO archive (extractor, video_id, added_at) VALUES (?1, ?2, ?3)")?;
+    let mut inserted = 0;
+    for (extractor, video_id) in entries {
+        inserted += stmt.execute(params![extractor, video_id, added_at])?;
+    }
+    Ok(inserted)
+}
+
+fn archive_entries(conn: &Connection) -> rusqlite::Result<Vec<(String, String)>> {
+    let mut stmt = conn.prepare(
+        "SELECT extractor, video_id FROM archive
+         UNION
+         SELECT extractor, video_id FROM downloads WHERE status = 'finished' AND extractor IS NOT NULL AND video_id != ''
+         ORDER BY 1, 2",
+    )?;
+    let entries = stmt
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(entries)
+}
+
+fn delete(conn: &Connection, id: &str) -> rusqlite::Result<bool> {
+    Ok(conn.execute("DELETE FROM downloads WHERE id = ?1", params![id])? > 0)
+}
+
+fn clear(conn: &Connection) -> rusqlite::Result<usize> {
+    conn.execute("DELETE FROM downloads", [])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn connection() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        init_schema(&conn).unwrap();
+        conn
+    }
+
+    fn item(id: &str, finished_at: &str) -> DownloadHistoryItem {
+        DownloadHistoryItem {
+            id: id.to_string(),
+            video_id: format!("video-{}", id),
+            title: format!("Lecture {}", id),
+            uploader: Some("Uni".to_string()),
+            extractor: Some("youtube".to_string()),
+            url: format!("https://www.youtube.com/watch?v=video-{}", id),
+            file_path: format!("/tmp/Lecture {}.mp4", id),
+            artifacts: vec![DownloadArtifact {
+                kind: crate::types::ArtifactKind::Media,
+                path: format!("/tmp/Lecture {}.mp4", id),
+                size: 1_234_567,
+            }],
+            format_id: "137+140".to_string(),
+            quality: Some("1080p".to_string()),
+            rule: None,
+            filesize: Some(1_234_567),
+            duration: Some(3600.5),
+            thumbnail: String::new(),
+            started_at: "2024-01-01T00:00:00+00:00".to_string(),
+            download_date: finished_at.to_string(),
+            status: HistoryStatus::Finished,
+            error: None,
+            attempts: Vec::new(),
+        }
+    }
+
+    fn all() -> Filter {
+        Filter::build(&HistoryQuery::default()).unwrap()
+    }
+
+    #[test]
+    fn test_insert_and_paginate() {
+        let conn = connection();
+        for i in 1..=5 {
+            insert(&conn, &item(&i.to_string(), &format!("2024-01-0{}T00:00:00+00:00", i))).unwrap();
+        }
+
+        let first = list(&conn, &all(), 1, 2).unwrap();
+        assert_eq!(first.total, 5);
+        let ids: Vec<&str> = first.items.iter().map(|item| item.id.as_str()).collect();
+        assert_eq!(ids, vec!["5", "4"]);
+
+        let last = list(&conn, &all(), 3, 2).unwrap();
+        assert_eq!(last.items.len(), 1);
+        assert_eq!(last.items[0], item("1", "2024-01-01T00:00:00+00:00"));
+    }
+
+    #[test]
+    fn test_delete_and_clear() {
+        let conn = connection();
+        insert(&conn, &item("a", "2024-01-01T00:00:00+00:00")).unwrap();
+        insert(&conn, &item("b", "2024-01-02T00:00:00+00:00")).unwrap();
+
+        assert!(delete(&conn, "a").unwrap());
+        assert!(!delete(&conn, "a").unwrap());
+        let remaining = list(&conn, &all(), 1, 10).unwrap();
+        assert_eq!(remaining.total, 1);
+        assert_eq!(remaining.items[0].id, "b");
+
+        assert_eq!(clear(&conn).unwrap(), 1);
+        assert_eq!(list(&conn, &all(), 1, 10).unwrap().total, 0);
+    }
+
+    #[test]
+    fn test_query_filters() {
+        let conn = connection();
+        insert(&conn, &item("1", "2024-01-01T10:00:00+08:00")).unwrap();
+        insert(&conn, &DownloadHistoryItem {
+            title: "100% Rust_intro".to_string(),
+            uploader: Some("Other".to_string()),
+            format_id: "22".to_string(),
+            ..item("2", "2024-01-05T10:00:00+08:00")
+        })
+        .unwrap();
+        insert(&conn, &DownloadHistoryItem {
+            file_path: String::new(),
+            status: HistoryStatus::Failed,
+            error: Some("HTTP Error 403".to_string()),
+            ..item("3", "2024-01-10T10:00:00+08:00")
+        })
+        .unwrap();
+
+        let ids = |query: HistoryQuery| -> Vec<String> {
+            let filter = Filter::build(&query).unwrap();
+            list(&conn, &filter, 1, 10).unwrap().items.into_iter().map(|item| item.id).collect()
+        };
+
+        assert_eq!(ids(HistoryQuery { search: Some("lecture".to_string()), ..Default::default() }), vec!["3", "1"]);
+        // 通配符按字面匹配
+        assert_eq!(ids(HistoryQuery { search: Some("0% rust_".to_string()), ..Default::default() }), vec!["2"]);
+        assert!(ids(HistoryQuery { search: Some("_intro%".to_string()), ..Default::default() }).is_empty());
+        assert_eq!(ids(HistoryQuery { uploader: Some("uni".to_string()), ..Default::default() }), vec!["3", "1"]);
+        assert_eq!(ids(HistoryQuery { format_id: Some("22".to_string()), ..Default::default() }), vec!["2"]);
+        assert_eq!(ids(HistoryQuery { status: Some(HistoryStatus::Failed), ..Default::default() }), vec!["3"]);
+        assert_eq!(
+            ids(HistoryQuery {
+                date_from: Some("2024-01-05".to_string()),
+                date_to: Some("2024-01-09".to_string()),
+                ..Default::default()
+            }),
+            vec!["2"]
+        );
+        assert!(Filter::build(&HistoryQuery { date_from: Some("2024/01/05".to_string()), ..Default::default() }).is_err());
+    }
+
+    #[test]
+    fn test_csv_export() {
+        let failed = DownloadHistoryItem {
+            title: "Quote \"this\", please".to_string(),
+            status: HistoryStatus::Failed,
+            error: Some("HTTP Error 403".to_string()),
+            ..item("a", "2024-01-01T00:00:00+00:00")
+        };
+        let csv = to_csv(&[failed]).unwrap();
+        let mut lines = csv.lines();
+        assert!(lines.next().unwrap().starts_with("id,video_id,title,uploader,"));
+        let row = lines.next().unwrap();
+        assert!(row.contains("\"Quote \"\"this\"\", please\""));
+        assert!(row.ends_with(",failed,HTTP Error 403"));
+    }
+
+    #[test]
+    fn test_update_attempts() {
+        let conn = connection();
+        insert(&conn, &item("a", "2024-01-01T00:10:00+00:00")).unwrap();
+        let attempts = vec![
+            DownloadAttempt {
+                attempt: 1,
+                format_id: "137+140".to_string(),
+                started_at: "2024-01-01T00:00:00+00:00".to_string(),
+                finished_at: "2024-01-01T00:05:00+00:00".to_string(),
+                exit_code: Some(1),
+                error: Some("HTTP Error 403".to_string()),
+            },
+            DownloadAttempt {
+                attempt: 2,
+                format_id: "22".to_string(),
+                started_at: "2024-01-01T00:05:00+00:00".to_string(),
+                finished_at: "2024-01-01T00:10:00+00:00".to_string(),
+                exit_code: Some(0),
+                error: None,
+            },
+        ];
+
+        update_attempts(&conn, "a", &attempts).unwrap();
+        // 没有历史记录的任务（如已取消）不报错
+        update_attempts(&conn, "missing", &attempts).unwrap();
+
+        let page = list(&conn, &all(), 1, 10).unwrap();
+        assert_eq!(page.total, 1);
+        assert_eq!(page.items[0].attempts, attempts);
+    }
+
+    #[test]
+    fn test_archive_entries_merge_history_and_imports() {
+        let conn = connection();
+        insert(&conn, &item("a", "2024-01-01T00:00:00+00:00")).unwrap();
+        insert(&conn, &DownloadHistoryItem {
+            status: HistoryStatus::Failed,
+            ..item("b", "2024-01-02T00:00:00+00:00")
+        })
+        .unwrap();
+
+        let imported = vec![
+            ("youtube".to_string(), "video-a".to_string()),
+            ("vimeo".to_string(), "42".to_string()),
+            ("vimeo".to_string(), "42".to_string()),
+        ];
+        assert_eq!(import_archive(&conn, &imported).unwrap(), 2);
+        assert!(is_archived(&conn, "42").unwrap());
+        assert!(!is_archived(&conn, "video-b").unwrap());
+
+        let entries = archive_entries(&conn).unwrap();
+        assert_eq!(
+            entries,
+            vec![("vimeo".to_string(), "42".to_string()), ("youtube".to_string(), "video-a".to_string())]
+        );
+        assert_eq!(find_downloads(&conn, "video-b").unwrap().len(), 0);
+        assert_eq!(find_downloads(&conn, "video-a").unwrap().len(), 1);
+    }
+}
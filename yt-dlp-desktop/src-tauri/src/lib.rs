@@ -1,9 +1,25 @@
 // 模块声明
+mod archive;
+mod artifacts;
+mod audio;
 mod commands;
 mod config;
+mod filename;
 mod types;
 mod ytdlp;
+mod history;
 mod logger;
+mod mirror;
+mod playlist;
+mod progress;
+mod queue;
+mod retry;
+mod rules;
+mod staging;
+mod subscriptions;
+mod subtitles;
+mod tasks;
+mod watchdog;
 
 use commands::*;
 
@@ -21,17 +37,56 @@ pub fn run() {
     tauri::Builder::default()
         .plugin(tauri_plugin_opener::init())
         .plugin(tauri_plugin_dialog::init())
+        .setup(|app| {
+            // 恢复上次退出时未完成的下载任务，等待前端确认后再继续
+            queue::DownloadQueue::get().restore();
+            // 清理上次运行残留的暂存文件，保留恢复的任务已下载的部分
+            let app_config = config::AppConfig::load(app.handle()).unwrap_or_default();
+            let unfinished: Vec<String> = queue::DownloadQueue::get().list().into_iter().map(|item| item.id).collect();
+            staging::cleanup(&app_config.staging, &unfinished);
+            // 定期检查订阅的新视频
+            subscriptions::start_scheduler(app.handle().clone());
+            Ok(())
+        })
         .invoke_handler(tauri::generate_handler![
             get_video_info,
+            preview_output_filename,
+            get_filename_presets,
             list_formats,
+            get_playlist_info,
+            enqueue_playlist,
+            create_mirror_job,
+            list_mirror_jobs,
+            delete_mirror_job,
+            run_mirror_job,
+            list_mirror_runs,
+            add_subscription,
+            list_subscriptions,
+            remove_subscription,
+            check_now,
             start_download,
             cancel_download,
+            pause_download,
+            resume_download,
+            redownload,
+            enqueue_downloads,
+            list_queue,
+            reorder_queue,
+            remove_from_queue,
+            get_active_downloads,
+            list_interrupted_downloads,
+            resume_interrupted_downloads,
             select_save_path,
             get_app_config,
             save_app_config,
             get_default_save_path,
             set_default_save_path,
             get_download_history,
+            query_download_history,
+            export_download_history,
+            delete_history_item,
+            import_download_archive,
+            export_download_archive,
             clear_download_history,
             open_file_location,
             get_log_path,
@@ -66,9 +66,8 @@ impl AppLogger {
     /// 初始化日志系统
     fn init() -> Result<Self, Box<dyn std::error::Error>> {
         // 使用 Application Support 目录
-        let app_data_dir = dirs::data_dir()
-            .ok_or("无法获取应用数据目录")?
-            .join("com.youtube-downloader.desktop");
+        let app_data_dir = crate::config::app_data_dir()
+            .ok_or("无法获取应用数据目录")?;
 
         // 确保目录存在
         fs::create_dir_all(&app_data_dir)?;
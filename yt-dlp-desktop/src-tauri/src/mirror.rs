@@ -0,0 +1,392 @@
+use std::collections::HashSet;
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+use crate::archive;
+use crate::config::AppConfig;
+use crate::playlist;
+use crate::queue::DownloadQueue;
+use crate::types::{DownloadConfig, PlaylistEntry, PlaylistInfo};
+
+/// 频道/播放列表镜像任务：每次运行只下载存档中没有的视频
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MirrorJob {
+    pub id: String,
+    /// 下载配置模板（url 为频道或播放列表地址）
+    pub config: DownloadConfig,
+    /// 只下载该日期及之后上传的视频（YYYYMMDD）
+    pub date_after: Option<String>,
+    /// 只下载该日期及之前上传的视频（YYYYMMDD）
+    pub date_before: Option<String>,
+    pub created_at: String,
+    pub last_run_at: Option<String>,
+}
+
+/// 一次镜像运行的结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MirrorRunSummary {
+    pub job_id: String,
+    pub url: String,
+    pub started_at: String,
+    pub finished_at: String,
+    /// 频道/播放列表中的条目总数
+    pub total_entries: usize,
+    /// 存档中已有的条目
+    pub already_archived: usize,
+    /// 已在下载队列中的条目
+    pub already_queued: usize,
+    /// 不在日期范围内的条目
+    pub filtered_out: usize,
+    /// 没有上传日期、无法按日期筛选的条目（仍会下载）
+    pub undated: usize,
+    /// 本次加入队列的任务 ID
+    pub task_ids: Vec<String>,
+}
+
+/// 镜像任务文件写入锁
+static JOBS_LOCK: Lazy<Mutex<()>> = Lazy::new(|| Mutex::new(()));
+
+fn jobs_file() -> Option<PathBuf> {
+    crate::config::app_data_dir().map(|dir| dir.join("mirrors.json"))
+}
+
+fn runs_file() -> Option<PathBuf> {
+    crate::config::app_data_dir().map(|dir| dir.join("mirror_runs.jsonl"))
+}
+
+/// 读取所有镜像任务
+pub fn list_jobs() -> Result<Vec<MirrorJob>, String> {
+    let Some(path) = jobs_file() else {
+        return Ok(Vec::new());
+    };
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let content = std::fs::read_to_string(&path).map_err(|e| format!("Failed to read mirror jobs: {}", e))?;
+    serde_json::from_str(&content).map_err(|e| format!("Failed to parse mirror jobs: {}", e))
+}
+
+fn save_jobs(jobs: &[MirrorJob]) -> Result<(), String> {
+    let path = jobs_file().ok_or("Failed to get app data dir")?;
+    let content = serde_json::to_string_pretty(jobs).map_err(|e| format!("Failed to serialize mirror jobs: {}", e))?;
+    crate::config::write_file_atomic(&path, &content).map_err(|e| format!("Failed to write mirror jobs: {}", e))
+}
+
+/// 创建镜像任务
+pub fn create_job(config: DownloadConfig, date_after: Option<String>, date_before: Option<String>) -> Result<MirrorJob, String> {
+    let date_after = normalize_date(date_after)?;
+    let date_before = normalize_date(date_before)?;
+    if let (Some(after), Some(before)) = (&date_after, &date_before) {
+        if after > before {
+            return Err(format!("date_after {} is later than date_before {}", after, before));
+        }
+    }
+
+    let job = MirrorJob {
+        id: uuid::Uuid::new_v4().to_string(),
+        config,
+        date_after,
+        date_before,
+        created_at: chrono::Local::now().to_rfc3339(),
+        last_run_at: None,
+    };
+
+    let _guard = JOBS_LOCK.lock().unwrap();
+    let mut jobs = list_jobs()?;
+    jobs.push(job.clone());
+    save_jobs(&jobs)?;
+
+    crate::logger::AppLogger::get().info(&format!("创建镜像任务: {} ({})", job.id, job.config.url));
+    Ok(job)
+}
+
+/// 删除镜像任务（保留存档文件，重新创建同一来源时不会重复下载）
+pub fn delete_job(id: &str) -> Result<(), String> {
+    let _guard = JOBS_LOCK.lock().unwrap();
+    let mut jobs = list_jobs()?;
+    let len = jobs.len();
+    jobs.retain(|job| job.id != id);
+    if jobs.len() == len {
+        return Err(format!("Mirror job not found: {}", id));
+    }
+    save_jobs(&jobs)
+}
+
+/// 运行镜像任务：获取频道条目，将存档中没有且在日期范围内的视频加入下载队列
+pub async fn run_job(id: &str, app: &AppHandle) -> Result<MirrorRunSummary, String> {
+    let logger = crate::logger::AppLogger::get();
+    let started_at = chrono::Local::now().to_rfc3339();
+
+    let job = list_jobs()?
+        .into_iter()
+        .find(|job| job.id == id)
+        .ok_or_else(|| format!("Mirror job not found: {}", id))?;
+
+    let timeout = AppConfig::load(app).unwrap_or_default().info_timeout();
+    let info = playlist::get_playlist_info(&job.config.url, timeout).await?;
+    let archive_path = archive::archive_path(&source_key(&info, &job.config.url)).ok_or("Failed to get app data dir")?;
+
+    let archived = archive::load_ids(&archive_path);
+    let queued: HashSet<String> = DownloadQueue::get().pending_urls();
+    let plan = plan_run(&info.entries, &archived, &queued, job.date_after.as_deref(), job.date_before.as_deref());
+
+    // 频道按从新到旧排列，镜像时从最旧的开始下载
+    let mut entries = plan.selected;
+    entries.reverse();
+
+    let template = DownloadConfig {
+        archive: Some(archive_path.to_string_lossy().to_string()),
+        ..job.config.clone()
+    };
+    let configs = playlist::build_entry_configs(&info, &entries, &template);
+    let queue = DownloadQueue::get();
+    let task_ids = queue.enqueue_all(configs);
+    queue.schedule(app);
+
+    let summary = MirrorRunSummary {
+        job_id: job.id.clone(),
+        url: job.config.url.clone(),
+        started_at,
+        finished_at: chrono::Local::now().to_rfc3339(),
+        total_entries: info.entries.len(),
+        already_archived: plan.already_archived,
+        already_queued: plan.already_queued,
+        filtered_out: plan.filtered_out,
+        undated: plan.undated,
+        task_ids,
+    };
+
+    logger.info(&format!(
+        "镜像任务 {} 运行完成: 共 {} 个条目，已存档 {}，已在队列 {}，日期不符 {}，新加入 {}",
+        job.id,
+        summary.total_entries,
+        summary.already_archived,
+        summary.already_queued,
+        summary.filtered_out,
+        summary.task_ids.len()
+    ));
+
+    record_run(&summary);
+    mark_run(&job.id, &summary.finished_at);
+    Ok(summary)
+}
+
+/// 来源的存档名：按频道/播放列表而不是镜像任务区分，删除后重新创建同一来源的任务时沿用；
+/// 优先使用频道/播放列表 ID，没有时使用规范化 URL 的哈希
+fn source_key(info: &PlaylistInfo, url: &str) -> String {
+    let id: String = info
+        .id
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric() || *c == '-' || *c == '_')
+        .collect();
+    if !id.is_empty() {
+        return format!("mirror-{}", id);
+    }
+    // FNV-1a：结果不随 Rust 版本变化，存档名保持稳定
+    let hash = canonical_url(url)
+        .bytes()
+        .fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3));
+    format!("mirror-url-{:016x}", hash)
+}
+
+/// 规范化 URL：去掉协议、www./m. 前缀和结尾的 /，主机名不区分大小写（路径中的 ID 区分大小写）
+fn canonical_url(url: &str) -> String {
+    let url = url.trim().trim_end_matches('/');
+    let url = url.split_once("://").map_or(url, |(_, rest)| rest);
+    let url = url.strip_prefix("www.").or_else(|| url.strip_prefix("m.")).unwrap_or(url);
+    match url.split_once('/') {
+        Some((host, path)) => format!("{}/{}", host.to_lowercase(), path),
+        None => url.to_lowercase(),
+    }
+}
+
+/// 读取镜像任务的运行记录（最新的在前）
+pub fn list_runs(job_id: Option<&str>) -> Result<Vec<MirrorRunSummary>, String> {
+    let Some(path) = runs_file() else {
+        return Ok(Vec::new());
+    };
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let content = std::fs::read_to_string(&path).map_err(|e| format!("Failed to read mirror runs: {}", e))?;
+    let mut runs: Vec<MirrorRunSummary> = content
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .filter(|run: &MirrorRunSummary| job_id.is_none_or(|id| run.job_id == id))
+        .collect();
+    runs.reverse();
+    Ok(runs)
+}
+
+/// 追加运行记录
+fn record_run(summary: &MirrorRunSummary) {
+    let result = (|| -> Result<(), String> {
+        let path = runs_file().ok_or("Failed to get app data dir")?;
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+        }
+        let line = serde_json::to_string(summary).map_err(|e| e.to_string())?;
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .map_err(|e| e.to_string())?;
+        writeln!(file, "{}", line).map_err(|e| e.to_string())
+    })();
+
+    if let Err(e) = result {
+        crate::logger::AppLogger::get().error(&format!("保存镜像运行记录失败: {}", e));
+    }
+}
+
+/// 更新任务的最近运行时间
+fn mark_run(id: &str, finished_at: &str) {
+    let _guard = JOBS_LOCK.lock().unwrap();
+    let result = list_jobs().and_then(|mut jobs| {
+        if let Some(job) = jobs.iter_mut().find(|job| job.id == id) {
+            job.last_run_at = Some(finished_at.to_string());
+        }
+        save_jobs(&jobs)
+    });
+    if let Err(e) = result {
+        crate::logger::AppLogger::get().error(&format!("更新镜像任务失败: {}", e));
+    }
+}
+
+/// 本次运行要下载的条目及跳过的统计
+struct RunPlan {
+    selected: Vec<PlaylistEntry>,
+    already_archived: usize,
+    already_queued: usize,
+    filtered_out: usize,
+    undated: usize,
+}
+
+fn plan_run(
+    entries: &[PlaylistEntry],
+    archived: &HashSet<String>,
+    queued_urls: &HashSet<String>,
+    date_after: Option<&str>,
+    date_before: Option<&str>,
+) -> RunPlan {
+    let mut plan = RunPlan {
+        selected: Vec::new(),
+        already_archived: 0,
+        already_queued: 0,
+        filtered_out: 0,
+        undated: 0,
+    };
+
+    for entry in entries {
+        if archived.contains(&entry.id) {
+            plan.already_archived += 1;
+            continue;
+        }
+        if queued_urls.contains(&entry.url) {
+            plan.already_queued += 1;
+            continue;
+        }
+        match entry.upload_date.as_deref() {
+            Some(date) if date_after.is_some_and(|after| date < after) || date_before.is_some_and(|before| date > before) => {
+                plan.filtered_out += 1;
+                continue;
+            }
+            None if date_after.is_some() || date_before.is_some() => plan.undated += 1,
+            _ => {}
+        }
+        plan.selected.push(entry.clone());
+    }
+
+    plan
+}
+
+/// 校验日期格式，接受 YYYYMMDD 或 YYYY-MM-DD
+fn normalize_date(date: Option<String>) -> Result<Option<String>, String> {
+    let Some(date) = date.map(|date| date.trim().replace('-', "")).filter(|date| !date.is_empty()) else {
+        return Ok(None);
+    };
+    chrono::NaiveDate::parse_from_str(&date, "%Y%m%d")
+        .map(|_| Some(date.clone()))
+        .map_err(|_| format!("Invalid date: {}", date))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::test_support::{playlist_entry, playlist_info};
+
+    fn entry(id: &str, upload_date: Option<&str>) -> PlaylistEntry {
+        PlaylistEntry {
+            upload_date: upload_date.map(str::to_string),
+            ..playlist_entry(id, 0)
+        }
+    }
+
+    #[test]
+    fn test_plan_skips_archived_and_queued() {
+        let entries = vec![entry("a", None), entry("b", None), entry("c", None)];
+        let archived = HashSet::from(["a".to_string()]);
+        let queued = HashSet::from(["https://www.youtube.com/watch?v=b".to_string()]);
+
+        let plan = plan_run(&entries, &archived, &queued, None, None);
+        assert_eq!(plan.selected.len(), 1);
+        assert_eq!(plan.selected[0].id, "c");
+        assert_eq!((plan.already_archived, plan.already_queued, plan.undated), (1, 1, 0));
+    }
+
+    #[test]
+    fn test_plan_applies_date_range() {
+        let entries = vec![
+            entry("old", Some("20221231")),
+            entry("in", Some("20230615")),
+            entry("new", Some("20240101")),
+            entry("unknown", None),
+        ];
+        let plan = plan_run(&entries, &HashSet::new(), &HashSet::new(), Some("20230101"), Some("20231231"));
+        let ids: Vec<&str> = plan.selected.iter().map(|entry| entry.id.as_str()).collect();
+        assert_eq!(ids, vec!["in", "unknown"]);
+        assert_eq!((plan.filtered_out, plan.undated), (2, 1));
+    }
+
+    #[test]
+    fn test_normalize_date() {
+        assert_eq!(normalize_date(Some("2023-06-15".to_string())).unwrap(), Some("20230615".to_string()));
+        assert_eq!(normalize_date(Some(" ".to_string())).unwrap(), None);
+        assert!(normalize_date(Some("20231345".to_string())).is_err());
+    }
+
+    #[test]
+    fn test_recreated_job_reuses_archive() {
+        let job = |id: &str| MirrorJob {
+            id: id.to_string(),
+            config: DownloadConfig {
+                url: "https://www.youtube.com/@Someone/videos/".to_string(),
+                ..Default::default()
+            },
+            date_after: None,
+            date_before: None,
+            created_at: String::new(),
+            last_run_at: None,
+        };
+        let info = |id: &str| playlist_info(id, Vec::new());
+        // 删除后重新创建的任务 ID 不同，来源相同
+        let (deleted, recreated) = (job(&uuid::Uuid::new_v4().to_string()), job(&uuid::Uuid::new_v4().to_string()));
+        assert_eq!(source_key(&info("UC123"), &deleted.config.url), "mirror-UC123");
+        assert_eq!(
+            archive::archive_path(&source_key(&info("UC123"), &deleted.config.url)),
+            archive::archive_path(&source_key(&info("UC123"), &recreated.config.url))
+        );
+        // 没有来源 ID 时按规范化的 URL 区分
+        assert_eq!(
+            source_key(&info(""), &deleted.config.url),
+            source_key(&info(""), "https://YouTube.com/@Someone/videos")
+        );
+        assert_ne!(
+            source_key(&info(""), "https://www.youtube.com/playlist?list=PLabc"),
+            source_key(&info(""), "https://www.youtube.com/playlist?list=PLABC")
+        );
+    }
+}
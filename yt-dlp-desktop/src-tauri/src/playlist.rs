@@ -0,0 +1,283 @@
+use std::time::Duration;
+use serde::{Deserialize, Serialize};
+use tokio::process::Command;
+use crate::types::{DownloadConfig, PlaylistEntry, PlaylistInfo, PlaylistPosition};
+use crate::ytdlp;
+
+/// 播放列表条目选择
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PlaylistSelection {
+    /// 手动勾选的条目序号（为空时按 items 范围选择）
+    pub indices: Vec<u32>,
+    /// 序号范围，格式同 yt-dlp --playlist-items（如 "1-10,15,20-"），为空表示全部
+    pub items: Option<String>,
+    /// 倒序下载（从最旧的视频开始）
+    pub reverse: bool,
+}
+
+/// yt-dlp --flat-playlist 输出
+#[derive(Debug, Deserialize)]
+struct YtDlpPlaylist {
+    id: String,
+    title: Option<String>,
+    uploader: Option<String>,
+    channel: Option<String>,
+    #[serde(default)]
+    entries: Vec<YtDlpPlaylistEntry>,
+}
+
+#[derive(Debug, Deserialize)]
+struct YtDlpPlaylistEntry {
+    #[serde(rename = "_type")]
+    entry_type: Option<String>,
+    ie_key: Option<String>,
+    id: String,
+    title: Option<String>,
+    url: Option<String>,
+    webpage_url: Option<String>,
+    duration: Option<f64>,
+    upload_date: Option<String>,
+    timestamp: Option<i64>,
+}
+
+impl YtDlpPlaylistEntry {
+    /// 条目本身是播放列表（如频道首页的“视频”“Shorts”标签页）
+    fn is_nested_playlist(&self) -> bool {
+        self.entry_type.as_deref() == Some("playlist")
+            || self.ie_key.as_deref().is_some_and(|key| key.ends_with("Tab"))
+    }
+}
+
+/// 获取播放列表或频道的条目（平铺提取，不解析各视频的格式）
+pub async fn get_playlist_info(url: &str, timeout: Duration) -> Result<PlaylistInfo, String> {
+    let logger = crate::logger::AppLogger::get();
+    logger.info(&format!("开始获取播放列表信息: {}", url));
+
+    let playlist = fetch_flat_playlist(url, timeout).await?;
+
+    // 频道首页返回的是各个标签页，改为读取第一个标签页（通常是“视频”）
+    let playlist = match playlist.entries.first() {
+        Some(tab) if playlist.entries.iter().all(YtDlpPlaylistEntry::is_nested_playlist) => {
+            let tab_url = tab.url.clone().or_else(|| tab.webpage_url.clone()).unwrap_or_default();
+            logger.info(&format!("检测到频道标签页，读取: {}", tab_url));
+            fetch_flat_playlist(&tab_url, timeout).await?
+        }
+        _ => playlist,
+    };
+
+    let info = into_playlist_info(playlist);
+    logger.info(&format!("播放列表 {} 共 {} 个条目", info.title, info.entries.len()));
+    Ok(info)
+}
+
+async fn fetch_flat_playlist(url: &str, timeout: Duration) -> Result<YtDlpPlaylist, String> {
+    let _permit = ytdlp::acquire_info_permit().await?;
+
+    let mut cmd = Command::new(ytdlp::get_ytdlp_path());
+    cmd.arg("-J")
+        .arg("--flat-playlist")
+        .arg("--yes-playlist")
+        // 平铺提取时 YouTube 默认不返回上传日期，使用近似日期供日期筛选
+        .arg("--extractor-args")
+        .arg("youtubetab:approximate_date")
+        .arg(url)
+        .kill_on_drop(true);
+
+    let output = ytdlp::run_with_timeout(&mut cmd, timeout).await?;
+    if !output.status.success() {
+        let error = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("yt-dlp error: {}", error));
+    }
+
+    serde_json::from_slice(&output.stdout)
+        .map_err(|e| format!("Failed to parse yt-dlp playlist output: {}", e))
+}
+
+fn into_playlist_info(playlist: YtDlpPlaylist) -> PlaylistInfo {
+    let entries = playlist
+        .entries
+        .into_iter()
+        .filter(|entry| !entry.is_nested_playlist())
+        .enumerate()
+        .map(|(i, entry)| PlaylistEntry {
+            upload_date: entry.upload_date.or_else(|| {
+                entry
+                    .timestamp
+                    .and_then(|ts| chrono::DateTime::from_timestamp(ts, 0))
+                    .map(|date| date.format("%Y%m%d").to_string())
+            }),
+            url: entry
+                .url
+                .or(entry.webpage_url)
+                .unwrap_or_else(|| entry.id.clone()),
+            id: entry.id,
+            title: entry.title.unwrap_or_default(),
+            duration: entry.duration,
+            index: i as u32 + 1,
+        })
+        .collect();
+
+    PlaylistInfo {
+        title: playlist.title.unwrap_or_else(|| playlist.id.clone()),
+        uploader: playlist.uploader.or(playlist.channel).unwrap_or_default(),
+        id: playlist.id,
+        entries,
+    }
+}
+
+/// 按选择条件筛选要下载的条目
+pub fn select_entries(entries: &[PlaylistEntry], selection: &PlaylistSelection) -> Result<Vec<PlaylistEntry>, String> {
+    let mut selected: Vec<PlaylistEntry> = if !selection.indices.is_empty() {
+        entries
+            .iter()
+            .filter(|entry| selection.indices.contains(&entry.index))
+            .cloned()
+            .collect()
+    } else if let Some(items) = selection.items.as_deref().filter(|items| !items.trim().is_empty()) {
+        let ranges = parse_playlist_items(items)?;
+        entries
+            .iter()
+            .filter(|entry| ranges.iter().any(|(start, end)| entry.index >= *start && end.is_none_or(|end| entry.index <= end)))
+            .cloned()
+            .collect()
+    } else {
+        entries.to_vec()
+    };
+
+    if selection.reverse {
+        selected.reverse();
+    }
+    Ok(selected)
+}
+
+/// 解析 "1-10,15,20-" 形式的序号范围，返回 (起始, 结束) 列表，结束为 None 表示到末尾
+fn parse_playlist_items(items: &str) -> Result<Vec<(u32, Option<u32>)>, String> {
+    let parse = |value: &str| {
+        value
+            .trim()
+            .parse::<u32>()
+            .ok()
+            .filter(|n| *n > 0)
+            .ok_or_else(|| format!("Invalid playlist items: {}", items))
+    };
+
+    items
+        .split(',')
+        .map(str::trim)
+        .filter(|part| !part.is_empty())
+        .map(|part| match part.split_once('-') {
+            Some((start, "")) => Ok((parse(start)?, None)),
+            Some((start, end)) => {
+                let (start, end) = (parse(start)?, parse(end)?);
+                if start > end {
+                    return Err(format!("Invalid playlist items: {}", items));
+                }
+                Ok((start, Some(end)))
+            }
+            None => parse(part).map(|n| (n, Some(n))),
+        })
+        .collect()
+}
+
+/// 为选中的条目生成下载配置，记录各条目在本次下载中的位置
+pub fn build_entry_configs(playlist: &PlaylistInfo, entries: &[PlaylistEntry], template: &DownloadConfig) -> Vec<DownloadConfig> {
+    let count = entries.len() as u32;
+    entries
+        .iter()
+        .enumerate()
+        .map(|(i, entry)| DownloadConfig {
+            url: entry.url.clone(),
+            playlist: Some(PlaylistPosition {
+                playlist_id: playlist.id.clone(),
+                playlist_title: playlist.title.clone(),
+                index: i as u32 + 1,
+                count,
+            }),
+            ..template.clone()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::test_support::{playlist_entry, playlist_info};
+
+    fn entries(count: u32) -> Vec<PlaylistEntry> {
+        (1..=count)
+            .map(|index| playlist_entry(&format!("video{}", index), index))
+            .collect()
+    }
+
+    fn indices(selected: &[PlaylistEntry]) -> Vec<u32> {
+        selected.iter().map(|entry| entry.index).collect()
+    }
+
+    #[test]
+    fn test_parse_playlist_items() {
+        assert_eq!(
+            parse_playlist_items("1-3, 7,10-").unwrap(),
+            vec![(1, Some(3)), (7, Some(7)), (10, None)]
+        );
+        assert!(parse_playlist_items("5-2").is_err());
+        assert!(parse_playlist_items("a-b").is_err());
+        assert!(parse_playlist_items("0").is_err());
+    }
+
+    #[test]
+    fn test_select_entries_by_range_and_reverse() {
+        let selection = PlaylistSelection {
+            items: Some("2-4,9-".to_string()),
+            reverse: true,
+            ..Default::default()
+        };
+        let selected = select_entries(&entries(10), &selection).unwrap();
+        assert_eq!(indices(&selected), vec![10, 9, 4, 3, 2]);
+    }
+
+    #[test]
+    fn test_explicit_indices_take_precedence() {
+        let selection = PlaylistSelection {
+            indices: vec![5, 1],
+            items: Some("2-4".to_string()),
+            reverse: false,
+        };
+        let selected = select_entries(&entries(10), &selection).unwrap();
+        assert_eq!(indices(&selected), vec![1, 5]);
+    }
+
+    #[test]
+    fn test_flat_playlist_parsing_skips_tabs() {
+        let json = r#"{
+            "_type": "playlist", "id": "PL123", "title": "Lectures", "channel": "Uni",
+            "entries": [
+                {"_type": "url", "ie_key": "Youtube", "id": "a", "title": "One", "url": "https://www.youtube.com/watch?v=a", "duration": 61.0},
+                {"_type": "url", "ie_key": "YoutubeTab", "id": "UCx", "url": "https://www.youtube.com/@uni/shorts"},
+                {"_type": "url", "ie_key": "Youtube", "id": "b", "title": null, "url": null, "duration": null, "timestamp": 1700000000}
+            ]
+        }"#;
+        let info = into_playlist_info(serde_json::from_str(json).unwrap());
+        assert_eq!(info.title, "Lectures");
+        assert_eq!(info.uploader, "Uni");
+        assert_eq!(info.entries.len(), 2);
+        assert_eq!(info.entries[1].index, 2);
+        assert_eq!(info.entries[1].url, "b");
+        assert_eq!(info.entries[1].upload_date.as_deref(), Some("20231114"));
+    }
+
+    #[test]
+    fn test_build_entry_configs_records_position() {
+        let playlist = playlist_info("PL123", entries(3));
+        let template = DownloadConfig {
+            format_id: "best".to_string(),
+            output_path: "/tmp".to_string(),
+            ..Default::default()
+        };
+        let configs = build_entry_configs(&playlist, &playlist.entries[1..], &template);
+        assert_eq!(configs.len(), 2);
+        assert_eq!(configs[0].url, "https://www.youtube.com/watch?v=video2");
+        let position = configs[1].playlist.as_ref().unwrap();
+        assert_eq!((position.index, position.count), (2, 2));
+    }
+}
@@ -0,0 +1,652 @@
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::{Deserialize, Deserializer};
+
+/// 进度模板输出行的前缀
+const TEMPLATE_PREFIX: &str = "[progress]";
+
+/// 传给 yt-dlp --progress-template 的下载进度模板，每行输出一个 JSON 对象
+pub const DOWNLOAD_TEMPLATE: &str = "download:[progress] %(progress.{status,downloaded_bytes,total_bytes,total_bytes_estimate,speed,eta,fragment_index,fragment_count,filename})j";
+
+/// 从一行 yt-dlp 输出中解析出的下载进度
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ProgressUpdate {
+    /// 进度百分比（文本输出中直接给出，模板输出中由字节数计算）
+    pub percent: Option<f64>,
+    pub downloaded: Option<u64>,
+    pub total: Option<u64>,
+    /// 总大小是估算值（分片下载时 yt-dlp 按已下载分片估算）
+    pub estimated: bool,
+    /// 速度（字节/秒）
+    pub speed: Option<f64>,
+    /// 剩余时间（秒）
+    pub eta: Option<f64>,
+    pub fragment_index: Option<u32>,
+    pub fragment_count: Option<u32>,
+    /// 当前下载的流对应的文件（视频流和音频流分别下载）
+    pub filename: Option<String>,
+    /// 当前流已下载完成
+    pub finished: bool,
+}
+
+impl ProgressUpdate {
+    /// 进度百分比：优先使用字节数，未知总大小时按分片计算
+    pub fn percent(&self) -> f64 {
+        if self.finished {
+            return 100.0;
+        }
+        if let Some(percent) = self.percent {
+            return percent;
+        }
+        match (self.downloaded, self.total, self.fragment_index, self.fragment_count) {
+            (Some(downloaded), Some(total), _, _) if total > 0 => (downloaded as f64 / total as f64 * 100.0).min(100.0),
+            (_, _, Some(index), Some(count)) if count > 0 => (index as f64 / count as f64 * 100.0).min(100.0),
+            _ => 0.0,
+        }
+    }
+
+    /// 已下载字节数：未直接给出时按百分比和总大小估算
+    pub fn downloaded(&self) -> u64 {
+        self.downloaded
+            .or_else(|| self.total.map(|total| (total as f64 * self.percent() / 100.0) as u64))
+            .unwrap_or(0)
+    }
+}
+
+/// 后处理器输出行的名称，例如 `[Merger] Merging formats into ...` 中的 `Merger`
+///
+/// 后处理器以类名输出（首字母大写），与 `[download]`、`[info]` 和提取器的小写前缀区分
+pub fn postprocessor_name(line: &str) -> Option<&str> {
+    let name = line.trim_start().strip_prefix('[')?.split_once(']')?.0;
+    let mut chars = name.chars();
+    (chars.next()?.is_ascii_uppercase() && chars.all(|c| c.is_ascii_alphanumeric())).then_some(name)
+}
+
+/// 解析一行输出，优先按进度模板解析，否则按 yt-dlp 默认的文本格式解析
+pub fn parse_line(line: &str) -> Option<ProgressUpdate> {
+    parse_template_line(line).or_else(|| parse_text_line(line))
+}
+
+/// --progress-template 输出（progress 字典中的字段，缺失时为 null 或 "NA"）
+#[derive(Debug, Deserialize)]
+struct TemplateProgress {
+    status: Option<String>,
+    #[serde(default, deserialize_with = "lenient_number")]
+    downloaded_bytes: Option<f64>,
+    #[serde(default, deserialize_with = "lenient_number")]
+    total_bytes: Option<f64>,
+    #[serde(default, deserialize_with = "lenient_number")]
+    total_bytes_estimate: Option<f64>,
+    #[serde(default, deserialize_with = "lenient_number")]
+    speed: Option<f64>,
+    #[serde(default, deserialize_with = "lenient_number")]
+    eta: Option<f64>,
+    #[serde(default, deserialize_with = "lenient_number")]
+    fragment_index: Option<f64>,
+    #[serde(default, deserialize_with = "lenient_number")]
+    fragment_count: Option<f64>,
+    filename: Option<String>,
+}
+
+/// 接受数字、数字字符串、null 和 "NA"
+fn lenient_number<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<f64>, D::Error> {
+    Ok(match Option::<serde_json::Value>::deserialize(deserializer)? {
+        Some(serde_json::Value::Number(number)) => number.as_f64(),
+        Some(serde_json::Value::String(value)) => value.trim().parse().ok(),
+        _ => None,
+    }
+    .filter(|value: &f64| value.is_finite() && *value >= 0.0))
+}
+
+fn parse_template_line(line: &str) -> Option<ProgressUpdate> {
+    let payload = line.trim().strip_prefix(TEMPLATE_PREFIX)?.trim();
+    if payload == "NA" {
+        return None;
+    }
+    let progress: TemplateProgress = serde_json::from_str(payload).ok()?;
+
+    let finished = progress.status.as_deref() == Some("finished");
+    let (total, estimated) = match (progress.total_bytes, progress.total_bytes_estimate) {
+        (Some(total), _) => (Some(total as u64), false),
+        (None, Some(estimate)) => (Some(estimate as u64), true),
+        (None, None) => (None, false),
+    };
+    let downloaded = progress.downloaded_bytes.map(|bytes| bytes as u64);
+
+    Some(ProgressUpdate {
+        percent: None,
+        // 完成时 yt-dlp 只给出最终大小
+        total: total.or(downloaded.filter(|_| finished)),
+        downloaded,
+        estimated,
+        speed: progress.speed,
+        eta: progress.eta,
+        fragment_index: progress.fragment_index.map(|index| index as u32),
+        fragment_count: progress.fragment_count.map(|count| count as u32),
+        filename: progress.filename,
+        finished,
+    })
+}
+
+/// yt-dlp 默认的进度文本，例如：
+/// `[download]   2.3% of ~  10.15GiB at   49.23MiB/s ETA 03:26 (frag 5/212)`
+/// `[download] 100% of  364.32MiB in 00:00:12 at 28.36MiB/s`
+/// `[download]   15.00MiB at    1.23MiB/s (00:00:12)`（未知总大小）
+static PROGRESS_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(concat!(
+        r"^\[download\]\s+",
+        r"(?:(?P<percent>[\d.]+)%\s+of\s+(?P<approx>~)?\s*(?P<total>[\d.]+\s*[KMGTPE]?i?B)|(?P<downloaded>[\d.]+\s*[KMGTPE]?i?B))",
+        r"(?:\s+in\s+(?P<elapsed>[\d:]+))?",
+        r"(?:\s+at\s+(?:(?P<speed>[\d.]+\s*[KMGTPE]?i?B)/s|Unknown(?:\s+\S+)?))?",
+        r"(?:\s+ETA\s+(?:(?P<eta>[\d:]+)|Unknown))?",
+        r"(?:\s+\((?:frag\s+(?P<frag>\d+)/(?P<frags>\d+)|[\d:]+)\))?",
+    ))
+    .unwrap()
+});
+
+fn parse_text_line(line: &str) -> Option<ProgressUpdate> {
+    let captures = PROGRESS_REGEX.captures(line.trim())?;
+    let number = |name: &str| captures.name(name).and_then(|m| m.as_str().parse::<u32>().ok());
+
+    let percent = captures.name("percent").and_then(|m| m.as_str().parse::<f64>().ok());
+    let total = captures.name("total").and_then(|m| parse_size_to_bytes(m.as_str()));
+    let finished = captures.name("elapsed").is_some() && percent == Some(100.0);
+
+    Some(ProgressUpdate {
+        percent,
+        downloaded: captures.name("downloaded").and_then(|m| parse_size_to_bytes(m.as_str())),
+        total,
+        estimated: captures.name("approx").is_some(),
+        speed: captures.name("speed").and_then(|m| parse_size_to_bytes(m.as_str())).map(|speed| speed as f64),
+        eta: captures.name("eta").and_then(|m| parse_eta_to_seconds(m.as_str())).map(|eta| eta as f64),
+        fragment_index: number("frag"),
+        fragment_count: number("frags"),
+        filename: None,
+        finished,
+    })
+}
+
+/// 解析文件大小字符串为字节数（KiB/MiB 为二进制单位，KB/MB 为十进制单位）
+pub fn parse_size_to_bytes(size_str: &str) -> Option<u64> {
+    let size_str = size_str.trim();
+    let pos = size_str.find(|c: char| c.is_alphabetic())?;
+    let (number_part, unit_part) = size_str.split_at(pos);
+    let number = number_part.trim().parse::<f64>().ok()?;
+
+    let multiplier: f64 = match unit_part.trim() {
+        "B" => 1.0,
+        "KiB" => 1024.0,
+        "MiB" => 1024f64.powi(2),
+        "GiB" => 1024f64.powi(3),
+        "TiB" => 1024f64.powi(4),
+        "PiB" => 1024f64.powi(5),
+        "EiB" => 1024f64.powi(6),
+        "KB" | "kB" => 1e3,
+        "MB" => 1e6,
+        "GB" => 1e9,
+        "TB" => 1e12,
+        "PB" => 1e15,
+        "EB" => 1e18,
+        _ => return None,
+    };
+    Some((number * multiplier) as u64)
+}
+
+/// 解析 ETA 字符串（SS、MM:SS、HH:MM:SS 或 D:HH:MM:SS）为秒数
+pub fn parse_eta_to_seconds(eta_str: &str) -> Option<u64> {
+    let parts: Option<Vec<u64>> = eta_str.trim().split(':').map(|part| part.parse().ok()).collect();
+    match parts?.as_slice() {
+        [seconds] => Some(*seconds),
+        [minutes, seconds] => Some(minutes * 60 + seconds),
+        [hours, minutes, seconds] => Some((hours * 60 + minutes) * 60 + seconds),
+        [days, hours, minutes, seconds] => Some(((days * 24 + hours) * 60 + minutes) * 60 + seconds),
+        _ => None,
+    }
+}
+
+/// 任务中的步骤（按执行顺序）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum JobStep {
+    /// 写入缩略图、元数据文件等下载前的准备
+    Thumbnail,
+    /// 下载第 n 个流（DASH 格式的视频流、音频流分别下载）
+    Stream(usize),
+    /// 合并视频和音频
+    Merge,
+    /// 提取音频（转换为目标格式）
+    Extract,
+    /// 写入元数据
+    Metadata,
+}
+
+/// 缩略图、合并、提取音频、元数据步骤在总进度中的权重，剩余部分按大小分配给各个流
+const THUMBNAIL_WEIGHT: f64 = 0.02;
+const MERGE_WEIGHT: f64 = 0.06;
+const EXTRACT_WEIGHT: f64 = 0.10;
+const METADATA_WEIGHT: f64 = 0.02;
+
+/// yt-dlp 写入的 info.json 中与流大小有关的字段
+#[derive(Debug, Deserialize)]
+struct InfoStreams {
+    format_id: Option<String>,
+    duration: Option<f64>,
+    #[serde(flatten)]
+    size: StreamSize,
+    requested_formats: Option<Vec<InfoFormat>>,
+}
+
+#[derive(Debug, Deserialize)]
+struct InfoFormat {
+    format_id: String,
+    #[serde(flatten)]
+    size: StreamSize,
+}
+
+#[derive(Debug, Deserialize)]
+struct StreamSize {
+    filesize: Option<f64>,
+    filesize_approx: Option<f64>,
+    /// 平均码率（kbit/s）
+    tbr: Option<f64>,
+}
+
+impl StreamSize {
+    /// 流大小：优先使用准确大小，其次是估算大小，最后按码率和时长估算
+    fn estimate(&self, duration: Option<f64>) -> Option<u64> {
+        self.filesize
+            .or(self.filesize_approx)
+            .or_else(|| Some(self.tbr? * 1000.0 / 8.0 * duration?))
+            .filter(|size| *size > 0.0)
+            .map(|size| size as u64)
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+struct Stream {
+    format_id: Option<String>,
+    filename: Option<String>,
+    size: Option<u64>,
+    downloaded: u64,
+}
+
+/// 整个下载任务的合并进度：各个流按大小加权，合并、元数据等步骤各占固定比例，进度不会倒退
+#[derive(Debug, Clone)]
+pub struct JobProgress {
+    streams: Vec<Stream>,
+    step: JobStep,
+    /// 下载后是否提取音频
+    extract: bool,
+    /// 已报告的最大进度，重启进程或估算大小变化时不倒退
+    floor: f64,
+}
+
+impl JobProgress {
+    /// floor 为已报告过的进度（如恢复下载时上次的进度）
+    pub fn new(floor: f64) -> Self {
+        Self {
+            streams: Vec::new(),
+            step: JobStep::Thumbnail,
+            extract: false,
+            floor: floor.clamp(0.0, 100.0),
+        }
+    }
+
+    /// 下载后会提取音频，为该步骤预留进度
+    pub fn expect_extraction(&mut self) {
+        self.extract = true;
+    }
+
+    /// 是否已从 info.json 读取各个流
+    pub fn has_streams(&self) -> bool {
+        !self.streams.is_empty()
+    }
+
+    /// 从 yt-dlp 写入的 info.json 读取要下载的流及其大小
+    pub fn load_info_json(&mut self, content: &str) -> Result<(), String> {
+        let info: InfoStreams = serde_json::from_str(content).map_err(|e| format!("Failed to parse video metadata: {}", e))?;
+        let streams: Vec<Stream> = match info.requested_formats {
+            Some(formats) if !formats.is_empty() => formats
+                .into_iter()
+                .map(|format| Stream {
+                    size: format.size.estimate(info.duration),
+                    format_id: Some(format.format_id),
+                    ..Default::default()
+                })
+                .collect(),
+            _ => vec![Stream {
+                size: info.size.estimate(info.duration),
+                format_id: info.format_id,
+                ..Default::default()
+            }],
+        };
+
+        // 保留在读取前已经开始的流的进度
+        let started = std::mem::take(&mut self.streams);
+        self.streams = streams;
+        for stream in started.into_iter().filter(|stream| stream.filename.is_some()) {
+            let index = self.stream_index(stream.filename.as_deref().unwrap_or_default());
+            self.streams[index].downloaded = stream.downloaded;
+            self.streams[index].size = stream.size.or(self.streams[index].size);
+        }
+        Ok(())
+    }
+
+    /// 进入新的步骤（只前进，不后退）
+    pub fn enter(&mut self, step: JobStep) {
+        if step > self.step {
+            self.step = step;
+        }
+    }
+
+    /// 记录当前流的下载进度
+    pub fn update_stream(&mut self, update: &ProgressUpdate) {
+        let index = match update.filename.as_deref() {
+            Some(filename) => self.stream_index(filename),
+            None => match self.step {
+                JobStep::Stream(index) => index,
+                _ => self.stream_index(""),
+            },
+        };
+        self.enter(JobStep::Stream(index));
+
+        let stream = &mut self.streams[index];
+        stream.downloaded = update.downloaded();
+        // 实际大小比 info.json 中的估算准确；分片下载的估算值在没有其他信息时才使用
+        if let Some(total) = update.total.filter(|total| *total > 0) {
+            if !update.estimated || stream.size.is_none() || update.finished {
+                stream.size = Some(total);
+            }
+        }
+        if update.finished {
+            stream.downloaded = stream.size.unwrap_or(stream.downloaded);
+        }
+    }
+
+    /// 找到文件名对应的流：先按文件名，再按文件名中的格式 ID（如 .f137.），否则取下一个尚未开始的流
+    fn stream_index(&mut self, filename: &str) -> usize {
+        if let Some(index) = self.streams.iter().position(|s| !filename.is_empty() && s.filename.as_deref() == Some(filename)) {
+            return index;
+        }
+
+        let current = match self.step {
+            JobStep::Stream(index) => index,
+            _ => 0,
+        };
+        let index = self
+            .streams
+            .iter()
+            .position(|s| s.filename.is_none() && s.format_id.as_ref().is_some_and(|id| filename.contains(&format!(".f{}.", id))))
+            .or_else(|| (current..self.streams.len()).find(|i| self.streams[*i].filename.is_none()))
+            .unwrap_or_else(|| {
+                self.streams.push(Stream::default());
+                self.streams.len() - 1
+            });
+
+        if !filename.is_empty() {
+            self.streams[index].filename = Some(filename.to_string());
+        }
+        index
+    }
+
+    fn is_merging(&self) -> bool {
+        self.streams.len() > 1
+    }
+
+    fn merge_weight(&self) -> f64 {
+        if self.is_merging() { MERGE_WEIGHT } else { 0.0 }
+    }
+
+    fn extract_weight(&self) -> f64 {
+        if self.extract { EXTRACT_WEIGHT } else { 0.0 }
+    }
+
+    /// 各个流在总进度中的权重（总和为下载部分的比例）
+    fn stream_weights(&self) -> Vec<f64> {
+        let fixed = THUMBNAIL_WEIGHT + METADATA_WEIGHT + self.merge_weight() + self.extract_weight();
+        let share = 1.0 - fixed;
+
+        let known: Vec<u64> = self.streams.iter().filter_map(|s| s.size).collect();
+        if known.is_empty() {
+            return vec![share / self.streams.len().max(1) as f64; self.streams.len()];
+        }
+        // 大小未知的流按已知流的平均大小计算
+        let average = known.iter().sum::<u64>() as f64 / known.len() as f64;
+        let sizes: Vec<f64> = self.streams.iter().map(|s| s.size.map(|size| size as f64).unwrap_or(average)).collect();
+        let total: f64 = sizes.iter().sum();
+        sizes.iter().map(|size| share * size / total).collect()
+    }
+
+    /// 总进度百分比
+    pub fn percent(&mut self) -> f64 {
+        let weights = self.stream_weights();
+        let streams_done = |count: usize| -> f64 { weights.iter().take(count).sum() };
+        let merge = self.merge_weight();
+
+        let fraction = match self.step {
+            JobStep::Thumbnail => 0.0,
+            JobStep::Stream(index) => {
+                let stream = &self.streams[index];
+                let current = match stream.size {
+                    Some(size) if size > 0 => (stream.downloaded as f64 / size as f64).min(1.0),
+                    _ => 0.0,
+                };
+                THUMBNAIL_WEIGHT + streams_done(index) + weights[index] * current
+            }
+            JobStep::Merge => THUMBNAIL_WEIGHT + streams_done(weights.len()),
+            JobStep::Extract => THUMBNAIL_WEIGHT + streams_done(weights.len()) + merge,
+            JobStep::Metadata => THUMBNAIL_WEIGHT + streams_done(weights.len()) + merge + self.extract_weight(),
+        };
+
+        self.floor = self.floor.max((fraction * 100.0).min(100.0));
+        self.floor
+    }
+
+    /// 所有流已下载的字节数
+    pub fn downloaded(&self) -> u64 {
+        self.streams.iter().map(|s| s.downloaded).sum()
+    }
+
+    /// 所有流的总字节数（部分为估算值）
+    pub fn total(&self) -> u64 {
+        self.streams.iter().map(|s| s.size.unwrap_or(s.downloaded)).sum()
+    }
+
+    /// 整个任务的剩余时间：剩余字节数按当前速度计算，速度未知时使用 yt-dlp 给出的当前流剩余时间
+    pub fn eta(&self, speed: Option<f64>, stream_eta: Option<f64>) -> f64 {
+        let remaining = self.total().saturating_sub(self.downloaded());
+        match speed.filter(|speed| *speed > 0.0) {
+            Some(speed) => remaining as f64 / speed,
+            None => stream_eta.unwrap_or(0.0),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// yt-dlp --progress-template 输出
+    const TEMPLATE_FIXTURES: &[&str] = &[
+        r#"[progress] {"status": "downloading", "downloaded_bytes": 1048576, "total_bytes": 4194304, "total_bytes_estimate": null, "speed": 524288.5, "eta": 6, "fragment_index": null, "fragment_count": null, "filename": "/dl/Talk.f137.mp4"}"#,
+        r#"[progress] {"status": "downloading", "downloaded_bytes": 2000000, "total_bytes_estimate": 80000000.0, "speed": null, "eta": "NA", "fragment_index": 3, "fragment_count": 120, "filename": "/dl/Live.mp4"}"#,
+        r#"[progress] {"status": "finished", "downloaded_bytes": 4194304, "total_bytes": null, "filename": "/dl/Talk.f137.mp4"}"#,
+        "[progress] NA",
+    ];
+
+    /// yt-dlp 默认的文本进度输出
+    const TEXT_FIXTURES: &[&str] = &[
+        "[download]   2.3% of   10.15GiB at   49.23MiB/s ETA 03:26",
+        "[download]  12.0% of ~ 150.00MiB at    2.50MiB/s ETA Unknown (frag 10/80)",
+        "[download]   0.0% of   20.00MB at  Unknown B/s ETA Unknown",
+        "[download] 100% of  364.32MiB in 00:00:12 at 28.36MiB/s",
+        "[download]   15.00MiB at    1.23MiB/s (00:00:12)",
+        "[download] Destination: /dl/Talk.f137.mp4",
+    ];
+
+    #[test]
+    fn test_template_progress() {
+        let update = parse_line(TEMPLATE_FIXTURES[0]).unwrap();
+        assert_eq!(update.downloaded, Some(1_048_576));
+        assert_eq!(update.total, Some(4_194_304));
+        assert!(!update.estimated);
+        assert_eq!(update.speed, Some(524_288.5));
+        assert_eq!(update.eta, Some(6.0));
+        assert_eq!(update.filename.as_deref(), Some("/dl/Talk.f137.mp4"));
+        assert_eq!(update.percent(), 25.0);
+    }
+
+    #[test]
+    fn test_template_fragments_and_na() {
+        let update = parse_line(TEMPLATE_FIXTURES[1]).unwrap();
+        assert_eq!(update.total, Some(80_000_000));
+        assert!(update.estimated);
+        assert_eq!((update.speed, update.eta), (None, None));
+        assert_eq!((update.fragment_index, update.fragment_count), (Some(3), Some(120)));
+        assert_eq!(update.percent(), 2.5);
+
+        let finished = parse_line(TEMPLATE_FIXTURES[2]).unwrap();
+        assert!(finished.finished);
+        assert_eq!(finished.total, Some(4_194_304));
+        assert_eq!(finished.percent(), 100.0);
+
+        assert_eq!(parse_line(TEMPLATE_FIXTURES[3]), None);
+    }
+
+    #[test]
+    fn test_text_progress() {
+        let update = parse_line(TEXT_FIXTURES[0]).unwrap();
+        assert_eq!(update.percent(), 2.3);
+        assert_eq!(update.total, Some((10.15 * 1024f64.powi(3)) as u64));
+        assert_eq!(update.speed, Some((49.23 * 1024f64.powi(2)) as u64 as f64));
+        assert_eq!(update.eta, Some(206.0));
+
+        let estimate = parse_line(TEXT_FIXTURES[1]).unwrap();
+        assert!(estimate.estimated);
+        assert_eq!(estimate.eta, None);
+        assert_eq!((estimate.fragment_index, estimate.fragment_count), (Some(10), Some(80)));
+        assert_eq!(estimate.downloaded(), 18_874_368);
+
+        let unknown = parse_line(TEXT_FIXTURES[2]).unwrap();
+        assert_eq!(unknown.total, Some(20_000_000));
+        assert_eq!((unknown.speed, unknown.eta), (None, None));
+
+        let complete = parse_line(TEXT_FIXTURES[3]).unwrap();
+        assert!(complete.finished);
+        assert_eq!(complete.speed, Some((28.36 * 1024f64.powi(2)) as u64 as f64));
+
+        let no_total = parse_line(TEXT_FIXTURES[4]).unwrap();
+        assert_eq!(no_total.total, None);
+        assert_eq!(no_total.downloaded(), 15 * 1024 * 1024);
+
+        assert_eq!(parse_line(TEXT_FIXTURES[5]), None);
+    }
+
+    #[test]
+    fn test_parse_size_units() {
+        assert_eq!(parse_size_to_bytes("1.5MB"), Some(1_500_000));
+        assert_eq!(parse_size_to_bytes("1.5MiB"), Some(1_572_864));
+        assert_eq!(parse_size_to_bytes("512kB"), Some(512_000));
+        assert_eq!(parse_size_to_bytes("2 GiB"), Some(2_147_483_648));
+        assert_eq!(parse_size_to_bytes("Unknown"), None);
+    }
+
+    #[test]
+    fn test_postprocessor_name() {
+        assert_eq!(postprocessor_name("[Merger] Merging formats into \"/dl/Talk.mp4\""), Some("Merger"));
+        assert_eq!(postprocessor_name("[FixupM3u8] Fixing MPEG-TS in MP4 container"), Some("FixupM3u8"));
+        assert_eq!(postprocessor_name("[SponsorBlock] Fetching SponsorBlock segments"), Some("SponsorBlock"));
+        assert_eq!(postprocessor_name("[download] Destination: /dl/Talk.mp4"), None);
+        assert_eq!(postprocessor_name("[youtube] abc: Downloading webpage"), None);
+        assert_eq!(postprocessor_name("[youtube:tab] Downloading page 1"), None);
+        assert_eq!(postprocessor_name("[progress] NA"), None);
+        assert_eq!(postprocessor_name("Deleting original file /dl/Talk.f137.mp4"), None);
+    }
+
+    #[test]
+    fn test_parse_eta() {
+        assert_eq!(parse_eta_to_seconds("45"), Some(45));
+        assert_eq!(parse_eta_to_seconds("03:26"), Some(206));
+        assert_eq!(parse_eta_to_seconds("1:00:00"), Some(3600));
+        assert_eq!(parse_eta_to_seconds("1:02:00:00"), Some(93_600));
+        assert_eq!(parse_eta_to_seconds("Unknown"), None);
+    }
+
+    const DASH_INFO_JSON: &str = r#"{
+        "id": "abc", "format_id": "137+140", "duration": 100.0,
+        "requested_formats": [
+            {"format_id": "137", "filesize": 9000000, "tbr": 720.0},
+            {"format_id": "140", "filesize": null, "filesize_approx": 1000000, "tbr": 128.0}
+        ]
+    }"#;
+
+    fn stream_update(filename: &str, downloaded: u64, total: u64) -> ProgressUpdate {
+        ProgressUpdate {
+            downloaded: Some(downloaded),
+            total: Some(total),
+            filename: Some(filename.to_string()),
+            finished: downloaded == total,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_job_progress_weights_streams_by_size() {
+        let mut job = JobProgress::new(0.0);
+        job.load_info_json(DASH_INFO_JSON).unwrap();
+        assert_eq!(job.total(), 10_000_000);
+
+        // 视频流占下载部分的 90%
+        job.update_stream(&stream_update("/dl/Talk.f137.mp4", 4_500_000, 9_000_000));
+        assert!((job.percent() - (2.0 + 90.0 * 0.9 * 0.5)).abs() < 1e-9);
+
+        job.update_stream(&stream_update("/dl/Talk.f137.mp4", 9_000_000, 9_000_000));
+        let video_done = job.percent();
+        assert!((video_done - (2.0 + 90.0 * 0.9)).abs() < 1e-9);
+
+        // 音频流开始时进度不倒退
+        job.update_stream(&stream_update("/dl/Talk.f140.m4a", 0, 1_000_000));
+        assert_eq!(job.percent(), video_done);
+        job.update_stream(&stream_update("/dl/Talk.f140.m4a", 500_000, 1_000_000));
+        assert!(job.percent() > video_done);
+        assert_eq!(job.downloaded(), 9_500_000);
+        assert_eq!(job.eta(Some(100_000.0), None), 5.0);
+
+        job.enter(JobStep::Merge);
+        assert!((job.percent() - 92.0).abs() < 1e-9);
+        job.enter(JobStep::Metadata);
+        assert!((job.percent() - 98.0).abs() < 1e-9);
+        // 步骤只前进
+        job.enter(JobStep::Stream(0));
+        assert!((job.percent() - 98.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_job_progress_without_info_json() {
+        let mut job = JobProgress::new(30.0);
+        assert!(!job.has_streams());
+        job.update_stream(&stream_update("/dl/clip.mp4", 10, 100));
+        // 恢复下载时不低于上次的进度
+        assert_eq!(job.percent(), 30.0);
+        job.update_stream(&stream_update("/dl/clip.mp4", 50, 100));
+        assert!((job.percent() - (2.0 + 96.0 * 0.5)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_job_progress_reserves_audio_extraction() {
+        let mut job = JobProgress::new(0.0);
+        job.expect_extraction();
+        job.update_stream(&stream_update("/dl/song.webm", 50, 100));
+        assert!((job.percent() - (2.0 + 86.0 * 0.5)).abs() < 1e-9);
+        job.enter(JobStep::Extract);
+        assert!((job.percent() - 88.0).abs() < 1e-9);
+        job.enter(JobStep::Metadata);
+        assert!((job.percent() - 98.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_stream_size_estimate_from_bitrate() {
+        let size = StreamSize { filesize: None, filesize_approx: None, tbr: Some(128.0) };
+        assert_eq!(size.estimate(Some(60.0)), Some(960_000));
+        assert_eq!(size.estimate(None), None);
+    }
+}
@@ -0,0 +1,636 @@
+use std::path::PathBuf;
+use std::sync::Mutex;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter};
+use crate::archive::{self, ArchiveMatch};
+use crate::config::{AppConfig, DuplicatePolicy};
+use crate::tasks::TaskRegistry;
+use crate::types::{DownloadAttempt, DownloadConfig, DownloadProgress, DownloadStatus, VideoInfo};
+use crate::ytdlp::{self, DownloadOutcome};
+
+/// 队列任务状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum QueueState {
+    Queued,
+    Running,
+    Paused,
+    /// 应用退出或崩溃时未完成，重启后等待用户确认恢复
+    Interrupted,
+    Finished,
+    Failed,
+    Cancelled,
+    /// 视频已下载过，按配置跳过
+    Skipped,
+    /// 视频已下载过，等待用户确认是否重新下载
+    Duplicate,
+}
+
+/// 发现已下载过的视频、等待确认时通知前端
+#[derive(Debug, Clone, Serialize)]
+pub struct DuplicateEvent {
+    pub task_id: String,
+    pub found: ArchiveMatch,
+}
+
+/// 队列中的下载任务
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueueItem {
+    pub id: String,
+    pub config: DownloadConfig,
+    pub state: QueueState,
+    pub error: Option<String>,
+    pub added_at: String,
+    pub started_at: Option<String>,
+    pub finished_at: Option<String>,
+    /// 每次启动下载进程的记录（包括自动重试）
+    #[serde(default)]
+    pub attempts: Vec<DownloadAttempt>,
+}
+
+/// 下载队列（单例模式）
+pub struct DownloadQueue {
+    items: Mutex<Vec<QueueItem>>,
+}
+
+/// 全局下载队列
+static QUEUE: Lazy<DownloadQueue> = Lazy::new(|| DownloadQueue {
+    items: Mutex::new(Vec::new()),
+});
+
+impl DownloadQueue {
+    /// 获取全局下载队列
+    pub fn get() -> &'static DownloadQueue {
+        &QUEUE
+    }
+
+    /// 添加下载任务到队列末尾，返回任务 ID
+    pub fn enqueue(&self, config: DownloadConfig) -> String {
+        self.enqueue_all(vec![config]).remove(0)
+    }
+
+    /// 批量添加下载任务（如播放列表条目），只写一次队列文件
+    pub fn enqueue_all(&self, configs: Vec<DownloadConfig>) -> Vec<String> {
+        let items: Vec<QueueItem> = configs
+            .into_iter()
+            .map(|config| {
+                let id = TaskRegistry::get().register(config.clone());
+                crate::logger::AppLogger::get().info(&format!("任务加入队列: {} ({})", id, config.url));
+                QueueItem {
+                    id,
+                    config,
+                    state: QueueState::Queued,
+                    error: None,
+                    added_at: now(),
+                    started_at: None,
+                    finished_at: None,
+                    attempts: Vec::new(),
+                }
+            })
+            .collect();
+        let ids = items.iter().map(|item| item.id.clone()).collect();
+
+        self.items.lock().unwrap().extend(items);
+        self.persist();
+        ids
+    }
+
+    /// 获取队列快照
+    pub fn list(&self) -> Vec<QueueItem> {
+        self.items.lock().unwrap().clone()
+    }
+
+    /// 尚未结束的任务的 URL（用于避免重复加入队列）
+    pub fn pending_urls(&self) -> std::collections::HashSet<String> {
+        pending_items(&self.items.lock().unwrap())
+            .into_iter()
+            .map(|item| item.config.url)
+            .collect()
+    }
+
+    /// 按给定的 ID 顺序重新排列队列，未列出的任务保持原有相对顺序排在后面
+    pub fn reorder(&self, order: &[String]) -> Result<(), String> {
+        reorder_items(&mut self.items.lock().unwrap(), order)?;
+        self.persist();
+        Ok(())
+    }
+
+    /// 从队列中移除任务（运行中的任务会先被取消）
+    pub fn remove(&self, id: &str) -> Result<(), String> {
+        let state = self
+            .state_of(id)
+            .ok_or_else(|| format!("Queue item not found: {}", id))?;
+
+        if state == QueueState::Running {
+            TaskRegistry::get().cancel(id)?;
+        }
+
+        self.items.lock().unwrap().retain(|item| item.id != id);
+        TaskRegistry::get().remove(id);
+        self.persist();
+        Ok(())
+    }
+
+    /// 取消任务：排队中的任务直接标记为已取消，运行中的任务终止进程
+    pub fn cancel(&self, id: &str, app: &AppHandle) -> Result<(), String> {
+        match self.state_of(id) {
+            Some(QueueState::Queued) => {
+                TaskRegistry::get().cancel(id)?;
+                TaskRegistry::get().remove(id);
+                self.update(id, |item| {
+                    item.state = QueueState::Cancelled;
+                    item.finished_at = Some(now());
+                });
+                Ok(())
+            }
+            Some(QueueState::Running) => TaskRegistry::get().cancel(id),
+            Some(QueueState::Duplicate) => {
+                self.update(id, |item| {
+                    item.state = QueueState::Cancelled;
+                    item.finished_at = Some(now());
+                });
+                Ok(())
+            }
+            Some(QueueState::Paused) | Some(QueueState::Interrupted) => {
+                // 暂停的任务没有运行中的进程，取消时清理保留的分片文件和暂存目录
+                TaskRegistry::get().cancel(id)?;
+                TaskRegistry::get().remove(id);
+                if let Some(dir) = AppConfig::load(app).unwrap_or_default().staging.task_dir(id) {
+                    crate::staging::discard(&dir);
+                }
+                self.update(id, |item| {
+                    item.state = QueueState::Cancelled;
+                    item.finished_at = Some(now());
+                });
+                Ok(())
+            }
+            Some(_) => Ok(()),
+            None => Err(format!("Queue item not found: {}", id)),
+        }
+    }
+
+    /// 暂停运行中的任务，保留已下载的部分
+    pub fn pause(&self, id: &str) -> Result<(), String> {
+        match self.state_of(id) {
+            Some(QueueState::Running) => TaskRegistry::get().pause(id),
+            Some(state) => Err(format!("Cannot pause download in state {:?}", state)),
+            None => Err(format!("Queue item not found: {}", id)),
+        }
+    }
+
+    /// 恢复已暂停或被中断的任务（重新排队，保持原有位置）
+    pub fn resume(&self, id: &str) -> Result<(), String> {
+        match self.state_of(id) {
+            Some(QueueState::Paused) | Some(QueueState::Interrupted) => {
+                TaskRegistry::get().resume(id)?;
+                self.update(id, |item| {
+                    item.state = QueueState::Queued;
+                    item.finished_at = None;
+                });
+                Ok(())
+            }
+            Some(state) => Err(format!("Cannot resume download in state {:?}", state)),
+            None => Err(format!("Queue item not found: {}", id)),
+        }
+    }
+
+    /// 确认重新下载已下载过的视频（跳过检查，重新排队）
+    pub fn redownload(&self, id: &str) -> Result<(), String> {
+        let item = self
+            .list()
+            .into_iter()
+            .find(|item| item.id == id)
+            .ok_or_else(|| format!("Queue item not found: {}", id))?;
+        if !matches!(item.state, QueueState::Duplicate | QueueState::Skipped) {
+            return Err(format!("Cannot redownload in state {:?}", item.state));
+        }
+
+        let config = DownloadConfig { force: true, ..item.config };
+        TaskRegistry::get().register_with_id(id.to_string(), config.clone());
+        self.update(id, |item| {
+            item.config = config;
+            item.state = QueueState::Queued;
+            item.started_at = None;
+            item.finished_at = None;
+        });
+        Ok(())
+    }
+
+    /// 获取未结束任务的最新进度快照（按队列顺序），用于重新加载的页面重新关联任务
+    pub fn active_progress(&self) -> Vec<DownloadProgress> {
+        self.list()
+            .into_iter()
+            .filter_map(|item| {
+                let status = match item.state {
+                    QueueState::Queued => DownloadStatus::Queued,
+                    QueueState::Running => DownloadStatus::Downloading,
+                    QueueState::Paused | QueueState::Interrupted => DownloadStatus::Paused,
+                    _ => return None,
+                };
+                let progress = TaskRegistry::get()
+                    .last_progress(&item.id)
+                    .unwrap_or_else(|| DownloadProgress {
+                        task_id: item.id.clone(),
+                        status,
+                        filename: item.config.url.clone(),
+                        ..Default::default()
+                    });
+                Some(progress)
+            })
+            .collect()
+    }
+
+    /// 获取上次运行时未完成的任务
+    pub fn interrupted(&self) -> Vec<QueueItem> {
+        self.items
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|item| item.state == QueueState::Interrupted)
+            .cloned()
+            .collect()
+    }
+
+    /// 从应用数据目录恢复上次未完成的任务，返回恢复的数量
+    pub fn restore(&self) -> usize {
+        let logger = crate::logger::AppLogger::get();
+        let Some(path) = queue_file() else {
+            return 0;
+        };
+        if !path.exists() {
+            return 0;
+        }
+
+        let saved: Vec<QueueItem> = match std::fs::read_to_string(&path)
+            .map_err(|e| e.to_string())
+            .and_then(|content| serde_json::from_str(&content).map_err(|e| e.to_string()))
+        {
+            Ok(saved) => saved,
+            Err(e) => {
+                logger.error(&format!("读取下载队列失败: {}", e));
+                return 0;
+            }
+        };
+
+        let restored = restore_items(saved);
+        for item in &restored {
+            TaskRegistry::get().register_with_id(item.id.clone(), item.config.clone());
+        }
+
+        let count = restored.len();
+        self.items.lock().unwrap().extend(restored);
+        logger.info(&format!("恢复了 {} 个未完成的下载任务", count));
+        count
+    }
+
+    /// 将未完成的任务写入应用数据目录
+    fn persist(&self) {
+        let Some(path) = queue_file() else {
+            return;
+        };
+        let pending = pending_items(&self.items.lock().unwrap());
+
+        let result = serde_json::to_string_pretty(&pending)
+            .map_err(|e| e.to_string())
+            .and_then(|content| crate::config::write_file_atomic(&path, &content));
+
+        if let Err(e) = result {
+            crate::logger::AppLogger::get().error(&format!("保存下载队列失败: {}", e));
+        }
+    }
+
+    /// 启动排队中的任务，直到达到并发上限
+    pub fn schedule(&'static self, app: &AppHandle) {
+        let max_concurrent = AppConfig::load(app)
+            .map(|config| config.max_concurrent_downloads)
+            .unwrap_or(crate::config::DEFAULT_MAX_CONCURRENT_DOWNLOADS);
+
+        let to_start = {
+            let mut items = self.items.lock().unwrap();
+            let ids = next_to_start(&items, max_concurrent);
+            items
+                .iter_mut()
+                .filter(|item| ids.contains(&item.id))
+                .map(|item| {
+                    item.state = QueueState::Running;
+                    item.started_at = Some(now());
+                    item.clone()
+                })
+                .collect::<Vec<_>>()
+        };
+
+        if !to_start.is_empty() {
+            self.persist();
+        }
+
+        for item in to_start {
+            let app = app.clone();
+            tauri::async_runtime::spawn(async move {
+                self.run(item, &app).await;
+                self.schedule(&app);
+            });
+        }
+
+        self.emit_changed(app);
+    }
+
+    /// 执行单个下载任务并记录结果
+    async fn run(&self, item: QueueItem, app: &AppHandle) {
+        let format_id = ytdlp::resolve_format_id(&item.config);
+        let info = video_info(&item, app).await;
+        if self.skip_duplicate(&item, &format_id, info.as_ref(), app).await {
+            return;
+        }
+        let result = ytdlp::download_video(&item.id, &item.config, &format_id, info, app.clone()).await;
+
+        let attempts = TaskRegistry::get().take_attempts(&item.id);
+        let cancelled = TaskRegistry::get().is_cancelled(&item.id);
+        let paused = !cancelled && TaskRegistry::get().is_paused(&item.id);
+        // 暂停的任务保留在注册表中，恢复时复用已记录的进度和目标文件
+        if !paused {
+            TaskRegistry::get().remove(&item.id);
+            crate::history::discard_metadata(&item.id);
+            crate::artifacts::discard(&item.id);
+        }
+
+        let mut all_attempts = Vec::new();
+        self.update(&item.id, |queued| {
+            queued.finished_at = Some(now());
+            queued.attempts.extend(attempts);
+            all_attempts = queued.attempts.clone();
+            match &result {
+                Ok(DownloadOutcome::Finished) => queued.state = QueueState::Finished,
+                Ok(DownloadOutcome::Skipped) => queued.state = QueueState::Skipped,
+                Err(_) if cancelled => queued.state = QueueState::Cancelled,
+                Err(_) if paused => {
+                    queued.state = QueueState::Paused;
+                    queued.finished_at = None;
+                }
+                Err(e) => {
+                    queued.state = QueueState::Failed;
+                    queued.error = Some(e.clone());
+                }
+            }
+        });
+
+        // 队列记录清除后尝试记录随历史保留（下载结束时已写入历史）
+        if !paused && !cancelled {
+            let id = item.id.clone();
+            let saved = tokio::task::spawn_blocking(move || {
+                crate::history::HistoryStore::get().record_attempts(&id, &all_attempts)
+            })
+            .await;
+            if let Ok(Err(e)) = saved {
+                crate::logger::AppLogger::get().warn(&format!("记录下载尝试失败: {}", e));
+            }
+        }
+
+        if let Err(e) = result {
+            crate::logger::AppLogger::get().warn(&format!("队列任务 {} 结束: {}", item.id, e));
+        }
+    }
+
+    /// 下载前检查视频是否已下载过，按配置跳过或等待确认，返回 true 表示不再下载
+    async fn skip_duplicate(&self, item: &QueueItem, format_id: &str, info: Option<&VideoInfo>, app: &AppHandle) -> bool {
+        let logger = crate::logger::AppLogger::get();
+        let config = AppConfig::load(app).unwrap_or_default();
+        if item.config.force || config.duplicate_policy == DuplicatePolicy::Redownload {
+            return false;
+        }
+        let Some(info) = info else {
+            logger.warn(&format!("任务 {} 没有视频信息，跳过已下载检查", item.id));
+            return false;
+        };
+
+        let found = match archive::find_duplicate(&item.config, format_id, info).await {
+            Ok(Some(found)) => found,
+            Ok(None) => return false,
+            Err(e) => {
+                logger.warn(&format!("检查任务 {} 是否已下载失败，继续下载: {}", item.id, e));
+                return false;
+            }
+        };
+
+        let message = match &found.previous {
+            Some(previous) => format!("Already downloaded: {}", previous.file_path),
+            None => "Already in download archive".to_string(),
+        };
+        let state = if config.duplicate_policy == DuplicatePolicy::Prompt {
+            QueueState::Duplicate
+        } else {
+            QueueState::Skipped
+        };
+        logger.info(&format!("任务 {} 的视频 {} 已下载过（{}），状态: {:?}", item.id, found.video_id, message, state));
+
+        ytdlp::emit_progress(app, &item.id, DownloadProgress {
+            status: DownloadStatus::Skipped,
+            filename: message,
+            ..Default::default()
+        });
+        if state == QueueState::Duplicate {
+            let _ = app.emit("download-duplicate", DuplicateEvent {
+                task_id: item.id.clone(),
+                found,
+            });
+        }
+
+        TaskRegistry::get().remove(&item.id);
+        self.update(&item.id, |queued| {
+            queued.state = state;
+            queued.finished_at = Some(now());
+        });
+        true
+    }
+
+    fn state_of(&self, id: &str) -> Option<QueueState> {
+        self.items
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|item| item.id == id)
+            .map(|item| item.state)
+    }
+
+    fn update<F: FnOnce(&mut QueueItem)>(&self, id: &str, f: F) {
+        if let Some(item) = self.items.lock().unwrap().iter_mut().find(|item| item.id == id) {
+            f(item);
+        }
+        self.persist();
+    }
+
+    /// 通知前端队列已变化
+    pub fn emit_changed(&self, app: &AppHandle) {
+        let _ = app.emit("download-queue-changed", self.list());
+    }
+}
+
+/// 任务的视频信息：每个任务只获取一次（加入队列时已获取的直接使用），失败时返回 None，
+/// 由 yt-dlp 渲染文件名并跳过已下载检查
+async fn video_info(item: &QueueItem, app: &AppHandle) -> Option<VideoInfo> {
+    let registry = TaskRegistry::get();
+    if let Some(info) = registry.info(&item.id) {
+        return Some(info);
+    }
+    let timeout = AppConfig::load(app).unwrap_or_default().info_timeout();
+    match ytdlp::get_video_info(&item.config.url, timeout).await {
+        Ok(info) => {
+            registry.set_info(&item.id, info.clone());
+            Some(info)
+        }
+        Err(e) => {
+            crate::logger::AppLogger::get().warn(&format!("获取任务 {} 的视频信息失败: {}", item.id, e));
+            None
+        }
+    }
+}
+
+/// 队列持久化文件（与日志位于同一应用数据目录）
+fn queue_file() -> Option<PathBuf> {
+    crate::config::app_data_dir().map(|dir| dir.join("queue.json"))
+}
+
+/// 需要持久化的未完成任务
+fn pending_items(items: &[QueueItem]) -> Vec<QueueItem> {
+    items
+        .iter()
+        .filter(|item| {
+            matches!(
+                item.state,
+                QueueState::Queued | QueueState::Running | QueueState::Paused | QueueState::Interrupted
+            )
+        })
+        .cloned()
+        .collect()
+}
+
+/// 将上次保存的任务标记为已中断，等待用户确认后再恢复
+fn restore_items(saved: Vec<QueueItem>) -> Vec<QueueItem> {
+    saved
+        .into_iter()
+        .filter(|item| {
+            !matches!(
+                item.state,
+                QueueState::Finished | QueueState::Failed | QueueState::Cancelled | QueueState::Skipped | QueueState::Duplicate
+            )
+        })
+        .map(|item| QueueItem {
+            state: QueueState::Interrupted,
+            started_at: None,
+            finished_at: None,
+            ..item
+        })
+        .collect()
+}
+
+/// 计算下一批可以启动的任务 ID（按队列顺序）
+fn next_to_start(items: &[QueueItem], max_concurrent: usize) -> Vec<String> {
+    let running = items.iter().filter(|item| item.state == QueueState::Running).count();
+    let slots = max_concurrent.max(1).saturating_sub(running);
+
+    items
+        .iter()
+        .filter(|item| item.state == QueueState::Queued)
+        .take(slots)
+        .map(|item| item.id.clone())
+        .collect()
+}
+
+/// 按 ID 顺序重排任务列表
+fn reorder_items(items: &mut Vec<QueueItem>, order: &[String]) -> Result<(), String> {
+    if let Some(unknown) = order.iter().find(|id| !items.iter().any(|item| &item.id == *id)) {
+        return Err(format!("Queue item not found: {}", unknown));
+    }
+
+    let mut reordered = Vec::with_capacity(items.len());
+    for id in order {
+        if let Some(pos) = items.iter().position(|item| &item.id == id) {
+            reordered.push(items.remove(pos));
+        }
+    }
+    reordered.append(items);
+    *items = reordered;
+    Ok(())
+}
+
+fn now() -> String {
+    chrono::Local::now().to_rfc3339()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(id: &str, state: QueueState) -> QueueItem {
+        QueueItem {
+            id: id.to_string(),
+            config: DownloadConfig {
+                url: format!("https://www.youtube.com/watch?v={}", id),
+                format_id: "best".to_string(),
+                output_path: "/tmp".to_string(),
+                ..Default::default()
+            },
+            state,
+            error: None,
+            added_at: now(),
+            started_at: None,
+            finished_at: None,
+            attempts: Vec::new(),
+        }
+    }
+
+    fn ids(items: &[QueueItem]) -> Vec<&str> {
+        items.iter().map(|item| item.id.as_str()).collect()
+    }
+
+    #[test]
+    fn test_next_to_start_respects_limit() {
+        let items = vec![
+            item("a", QueueState::Running),
+            item("b", QueueState::Finished),
+            item("c", QueueState::Queued),
+            item("d", QueueState::Queued),
+            item("e", QueueState::Queued),
+        ];
+
+        assert_eq!(next_to_start(&items, 3), vec!["c", "d"]);
+        assert!(next_to_start(&items, 1).is_empty());
+        // 并发数为 0 时至少运行一个任务
+        assert_eq!(next_to_start(&[item("x", QueueState::Queued)], 0), vec!["x"]);
+    }
+
+    #[test]
+    fn test_reorder_items() {
+        let mut items = vec![
+            item("a", QueueState::Queued),
+            item("b", QueueState::Queued),
+            item("c", QueueState::Queued),
+        ];
+
+        reorder_items(&mut items, &["c".to_string(), "a".to_string()]).unwrap();
+        assert_eq!(ids(&items), vec!["c", "a", "b"]);
+
+        assert!(reorder_items(&mut items, &["missing".to_string()]).is_err());
+        assert_eq!(ids(&items), vec!["c", "a", "b"]);
+    }
+
+    #[test]
+    fn test_persisted_items_restore_as_interrupted() {
+        let items = vec![
+            item("a", QueueState::Running),
+            item("b", QueueState::Finished),
+            item("c", QueueState::Paused),
+            item("d", QueueState::Queued),
+            item("e", QueueState::Failed),
+        ];
+
+        let pending = pending_items(&items);
+        assert_eq!(ids(&pending), vec!["a", "c", "d"]);
+
+        let json = serde_json::to_string(&pending).unwrap();
+        let restored = restore_items(serde_json::from_str(&json).unwrap());
+        assert_eq!(ids(&restored), vec!["a", "c", "d"]);
+        assert!(restored.iter().all(|item| item.state == QueueState::Interrupted));
+        assert!(restored.iter().all(|item| item.started_at.is_none()));
+    }
+}
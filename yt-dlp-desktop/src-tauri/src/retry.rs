@@ -0,0 +1,103 @@
+use std::time::Duration;
+use serde::{Deserialize, Serialize};
+
+/// 下载重试策略
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RetryPolicy {
+    /// yt-dlp --retries
+    pub retries: u32,
+    /// yt-dlp --fragment-retries
+    pub fragment_retries: u32,
+    /// yt-dlp --retry-sleep 表达式（如 linear=1:5:10、exp=1:20）
+    pub retry_sleep: String,
+    /// yt-dlp --socket-timeout（秒）
+    pub socket_timeout_secs: u64,
+    /// yt-dlp 以失败退出后，应用重新启动下载的次数
+    pub job_retries: u32,
+    /// 重新启动前的初始等待时间（秒），之后每次翻倍
+    pub backoff_base_secs: u64,
+    /// 等待时间上限（秒）
+    pub backoff_max_secs: u64,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            retries: 10,
+            fragment_retries: 10,
+            retry_sleep: "linear=1:5:10".to_string(),
+            socket_timeout_secs: 30,
+            job_retries: 2,
+            backoff_base_secs: 5,
+            backoff_max_secs: 300,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// 转换为 yt-dlp 命令行参数
+    pub fn ytdlp_args(&self) -> Vec<String> {
+        let mut args = vec![
+            "--retries".to_string(),
+            self.retries.to_string(),
+            "--fragment-retries".to_string(),
+            self.fragment_retries.to_string(),
+        ];
+        if !self.retry_sleep.trim().is_empty() {
+            args.push("--retry-sleep".to_string());
+            args.push(self.retry_sleep.trim().to_string());
+        }
+        args.push("--socket-timeout".to_string());
+        args.push(self.socket_timeout_secs.to_string());
+        args
+    }
+
+    /// 第 `retry` 次重新启动（从 1 开始）前的等待时间，指数增长
+    pub fn backoff(&self, retry: u32) -> Duration {
+        let factor = 2u64.saturating_pow(retry.saturating_sub(1));
+        let secs = self.backoff_base_secs.saturating_mul(factor).min(self.backoff_max_secs);
+        Duration::from_secs(secs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_args_match_previous_flags() {
+        assert_eq!(
+            RetryPolicy::default().ytdlp_args(),
+            vec![
+                "--retries", "10",
+                "--fragment-retries", "10",
+                "--retry-sleep", "linear=1:5:10",
+                "--socket-timeout", "30",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_empty_retry_sleep_is_omitted() {
+        let policy = RetryPolicy {
+            retry_sleep: String::new(),
+            ..Default::default()
+        };
+        assert!(!policy.ytdlp_args().contains(&"--retry-sleep".to_string()));
+    }
+
+    #[test]
+    fn test_exponential_backoff() {
+        let policy = RetryPolicy {
+            backoff_base_secs: 5,
+            backoff_max_secs: 60,
+            ..Default::default()
+        };
+        assert_eq!(policy.backoff(1), Duration::from_secs(5));
+        assert_eq!(policy.backoff(2), Duration::from_secs(10));
+        assert_eq!(policy.backoff(3), Duration::from_secs(20));
+        assert_eq!(policy.backoff(5), Duration::from_secs(60));
+        assert_eq!(policy.backoff(100), Duration::from_secs(60));
+    }
+}
@@ -0,0 +1,250 @@
+use std::path::{Path, PathBuf};
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+use crate::filename::FilenameOptions;
+use crate::types::{DownloadConfig, VideoInfo};
+
+/// 短视频的最大时长（秒），与 YouTube 搜索的时长筛选一致
+const SHORT_MAX_SECS: u32 = 4 * 60;
+/// 中等长度视频的最大时长（秒）
+const MEDIUM_MAX_SECS: u32 = 20 * 60;
+
+/// 时长分段
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DurationBucket {
+    /// 4 分钟以内
+    Short,
+    /// 4 到 20 分钟
+    Medium,
+    /// 20 分钟以上
+    Long,
+}
+
+impl DurationBucket {
+    /// 时长所在的分段，时长未知时为 None
+    fn of(duration: u32) -> Option<DurationBucket> {
+        match duration {
+            0 => None,
+            d if d < SHORT_MAX_SECS => Some(DurationBucket::Short),
+            d if d <= MEDIUM_MAX_SECS => Some(DurationBucket::Medium),
+            _ => Some(DurationBucket::Long),
+        }
+    }
+}
+
+/// 下载内容类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MediaKind {
+    /// 仅音频
+    Audio,
+    Video,
+}
+
+/// 整理规则：设置的条件全部满足时保存到 destination（未设置条件的规则匹配所有下载）
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OrganizeRule {
+    pub name: String,
+    /// 上传者（不区分大小写）
+    pub uploader: Option<String>,
+    /// 上传年份
+    pub year: Option<i32>,
+    /// 上传月份（1-12）
+    pub month: Option<u32>,
+    pub duration: Option<DurationBucket>,
+    pub media: Option<MediaKind>,
+    /// 标题正则表达式（不区分大小写）
+    pub title_pattern: Option<String>,
+    /// 保存目录，可以使用文件名模板字段（如 ~/Music/%(uploader)s）；相对路径相对于下载的保存路径
+    pub destination: String,
+}
+
+/// 匹配到的规则
+#[derive(Debug, Clone, PartialEq)]
+pub struct RuleMatch {
+    pub name: String,
+    /// 渲染后的保存目录
+    pub destination: String,
+}
+
+impl OrganizeRule {
+    /// 校验目标目录、月份和标题正则表达式
+    pub fn validate(&self) -> Result<(), String> {
+        if self.destination.trim().is_empty() {
+            return Err(format!("Rule '{}' has no destination folder", self.name));
+        }
+        if self.month.is_some_and(|month| !(1..=12).contains(&month)) {
+            return Err(format!("Rule '{}' has an invalid month", self.name));
+        }
+        self.title_regex()?;
+        crate::filename::validate_directory(&self.destination).map_err(|e| format!("Rule '{}': {}", self.name, e))
+    }
+
+    fn title_regex(&self) -> Result<Option<Regex>, String> {
+        self.title_pattern
+            .as_deref()
+            .filter(|pattern| !pattern.is_empty())
+            .map(|pattern| {
+                RegexBuilder::new(pattern)
+                    .case_insensitive(true)
+                    .build()
+                    .map_err(|e| format!("Rule '{}' has an invalid title pattern: {}", self.name, e))
+            })
+            .transpose()
+    }
+
+    /// 视频是否满足规则的全部条件
+    pub fn matches(&self, info: &VideoInfo, audio_only: bool) -> bool {
+        // upload_date 为 YYYYMMDD
+        let year = info.upload_date.get(..4).and_then(|year| year.parse::<i32>().ok());
+        let month = info.upload_date.get(4..6).and_then(|month| month.parse::<u32>().ok());
+        let media = if audio_only { MediaKind::Audio } else { MediaKind::Video };
+
+        self.uploader.as_ref().is_none_or(|uploader| uploader.to_lowercase() == info.uploader.to_lowercase())
+            && self.year.is_none_or(|expected| year == Some(expected))
+            && self.month.is_none_or(|expected| month == Some(expected))
+            && self.duration.is_none_or(|bucket| DurationBucket::of(info.duration) == Some(bucket))
+            && self.media.is_none_or(|kind| kind == media)
+            && match self.title_regex() {
+                Ok(regex) => regex.is_none_or(|regex| regex.is_match(&info.title)),
+                Err(_) => false,
+            }
+    }
+}
+
+/// 校验所有规则（保存设置时调用）
+pub fn validate_rules(rules: &[OrganizeRule]) -> Result<(), String> {
+    rules.iter().try_for_each(OrganizeRule::validate)
+}
+
+/// 按顺序找到第一个匹配的规则，并渲染其保存目录
+pub fn resolve(
+    rules: &[OrganizeRule],
+    info: &VideoInfo,
+    config: &DownloadConfig,
+    options: &FilenameOptions,
+) -> Result<Option<RuleMatch>, String> {
+    let Some(rule) = rules.iter().find(|rule| rule.matches(info, config.audio_only)) else {
+        return Ok(None);
+    };
+    let rendered = crate::filename::render_directory(&rule.destination, info, options)?;
+    let destination = match rendered.strip_prefix('~') {
+        Some(rest) => dirs::home_dir()
+            .ok_or("Failed to find home directory")?
+            .join(rest.trim_start_matches(['/', '\\'])),
+        None => PathBuf::from(&rendered),
+    };
+    let destination = if destination.is_absolute() {
+        destination
+    } else {
+        Path::new(&config.output_path).join(destination)
+    };
+    Ok(Some(RuleMatch {
+        name: rule.name.clone(),
+        destination: destination.to_string_lossy().to_string(),
+    }))
+}
+
+/// 在构建输出模板前应用整理规则，失败时使用下载原本的保存路径
+pub fn apply(config: &DownloadConfig, rules: &[OrganizeRule], info: &VideoInfo, options: &FilenameOptions) -> Option<RuleMatch> {
+    let logger = crate::logger::AppLogger::get();
+    match resolve(rules, info, config, options) {
+        Ok(found) => {
+            if let Some(found) = &found {
+                logger.info(&format!("匹配整理规则 {}: 保存到 {}", found.name, found.destination));
+            }
+            found
+        }
+        Err(e) => {
+            logger.warn(&format!("应用整理规则失败: {}", e));
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::test_support::video_info;
+
+    fn info(title: &str, uploader: &str, upload_date: &str, duration: u32) -> VideoInfo {
+        VideoInfo {
+            duration,
+            uploader: uploader.to_string(),
+            upload_date: upload_date.to_string(),
+            ..video_info("abc", title)
+        }
+    }
+
+    fn rules() -> Vec<OrganizeRule> {
+        vec![
+            OrganizeRule {
+                name: "music".to_string(),
+                media: Some(MediaKind::Audio),
+                destination: "/home/me/Music/%(uploader)s".to_string(),
+                ..Default::default()
+            },
+            OrganizeRule {
+                name: "lectures".to_string(),
+                duration: Some(DurationBucket::Long),
+                title_pattern: Some("lecture|talk".to_string()),
+                destination: "Lectures".to_string(),
+                ..Default::default()
+            },
+            OrganizeRule {
+                name: "2023".to_string(),
+                uploader: Some("someone".to_string()),
+                year: Some(2023),
+                month: Some(5),
+                destination: "%(upload_date)s".to_string(),
+                ..Default::default()
+            },
+        ]
+    }
+
+    fn config(audio_only: bool) -> DownloadConfig {
+        DownloadConfig {
+            output_path: "/dl".to_string(),
+            audio_only,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_first_matching_rule_wins() {
+        let song = info("Song", "AC/DC", "20230501", 200);
+        let found = resolve(&rules(), &song, &config(true), &FilenameOptions::default()).unwrap().unwrap();
+        assert_eq!(found.name, "music");
+        assert_eq!(found.destination, "/home/me/Music/AC⧸DC");
+
+        let lecture = info("Lecture 1: Intro", "Uni", "20230501", 3600);
+        let found = resolve(&rules(), &lecture, &config(false), &FilenameOptions::default()).unwrap().unwrap();
+        assert_eq!(found.destination, "/dl/Lectures");
+
+        let clip = info("Clip", "Someone", "20230515", 60);
+        assert_eq!(resolve(&rules(), &clip, &config(false), &FilenameOptions::default()).unwrap().unwrap().destination, "/dl/20230515");
+
+        let other = info("Clip", "Someone", "20230615", 60);
+        assert_eq!(resolve(&rules(), &other, &config(false), &FilenameOptions::default()).unwrap(), None);
+    }
+
+    #[test]
+    fn test_duration_bucket() {
+        assert_eq!(DurationBucket::of(0), None);
+        assert_eq!(DurationBucket::of(239), Some(DurationBucket::Short));
+        assert_eq!(DurationBucket::of(1200), Some(DurationBucket::Medium));
+        assert_eq!(DurationBucket::of(1201), Some(DurationBucket::Long));
+    }
+
+    #[test]
+    fn test_validate_rules() {
+        assert!(validate_rules(&rules()).is_ok());
+        let invalid = |rule: OrganizeRule| validate_rules(&[OrganizeRule { name: "bad".to_string(), ..rule }]).is_err();
+        assert!(invalid(OrganizeRule::default()));
+        assert!(invalid(OrganizeRule { destination: "x".to_string(), month: Some(13), ..Default::default() }));
+        assert!(invalid(OrganizeRule { destination: "x".to_string(), title_pattern: Some("(".to_string()), ..Default::default() }));
+        assert!(invalid(OrganizeRule { destination: "%(channel_id)s".to_string(), ..Default::default() }));
+    }
+}
@@ -0,0 +1,386 @@
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use serde::{Deserialize, Serialize};
+use crate::filename::{self, CollisionPolicy, FilenameOptions};
+use crate::types::{ArtifactKind, DownloadArtifact};
+
+/// 字幕文件的扩展名
+const SUBTITLE_EXTENSIONS: &[&str] = &["srt", "vtt", "ass", "lrc", "ttml", "srv1", "srv2", "srv3", "json3"];
+
+/// 缩略图文件的扩展名
+const THUMBNAIL_EXTENSIONS: &[&str] = &["jpg", "jpeg", "png", "webp"];
+
+/// 下载暂存设置：下载中的 .part 文件、分片和未合并的音视频流写入暂存目录，完成后再移动到保存目录，
+/// 避免同步文件夹（Nextcloud、Syncthing 等）上传未完成的文件
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StagingConfig {
+    /// 关闭时 yt-dlp 直接写入保存目录
+    pub enabled: bool,
+    /// 暂存目录，为空时使用应用数据目录下的 staging
+    pub dir: Option<String>,
+}
+
+impl Default for StagingConfig {
+    fn default() -> Self {
+        Self { enabled: true, dir: None }
+    }
+}
+
+impl StagingConfig {
+    /// 暂存根目录，每个任务使用其中以任务 ID 命名的子目录
+    fn root(&self) -> Option<PathBuf> {
+        if !self.enabled {
+            return None;
+        }
+        match self.dir.as_deref().map(str::trim).filter(|dir| !dir.is_empty()) {
+            Some(dir) => Some(PathBuf::from(dir)),
+            None => crate::config::app_data_dir().map(|dir| dir.join("staging")),
+        }
+    }
+
+    /// 任务的暂存目录，关闭暂存时为 None
+    pub fn task_dir(&self, task_id: &str) -> Option<PathBuf> {
+        self.root().map(|root| root.join(task_id))
+    }
+}
+
+/// 把输出模板中的保存目录替换为暂存目录（两者中的 % 均已转义），模板不在保存目录下时返回 None
+pub fn stage_template(template: &str, output_path: &str, task_dir: &Path) -> Option<String> {
+    let relative = Path::new(template).strip_prefix(output_path.replace('%', "%%")).ok()?;
+    let staged = PathBuf::from(task_dir.to_string_lossy().replace('%', "%%")).join(relative);
+    Some(staged.to_string_lossy().to_string())
+}
+
+/// 移动暂存文件的结果
+#[derive(Debug)]
+pub enum Finalized {
+    /// 更新路径后的文件列表
+    Moved(Vec<DownloadArtifact>),
+    /// 保存目录已有同名文件，按同名处理方式跳过（暂存目录保留，由调用方删除）
+    Skipped(PathBuf),
+}
+
+/// 同时只有一个任务把文件移动到保存目录，避免两个任务渲染出同名文件时互相覆盖
+static FINALIZE_LOCK: Mutex<()> = Mutex::new(());
+
+/// 下载完成后把暂存目录中的文件移动到保存目录中的对应位置，全部移动后删除暂存目录
+///
+/// 除 yt-dlp 报告的文件外，暂存目录中其余的完整文件（文件列表写入或解析失败时）也一并移动；
+/// 目标文件已存在时按同名处理方式跳过、覆盖或添加数字后缀。
+/// 跳过或出错时暂存目录保留，返回的错误包含未移动的文件
+pub fn finalize(
+    task_dir: &Path,
+    output_path: &str,
+    artifacts: Vec<DownloadArtifact>,
+    options: &FilenameOptions,
+) -> Result<Finalized, String> {
+    let _guard = FINALIZE_LOCK.lock().unwrap();
+    let logger = crate::logger::AppLogger::get();
+
+    let (mut staged, mut result): (Vec<_>, Vec<_>) =
+        artifacts.into_iter().partition(|artifact| Path::new(&artifact.path).starts_with(task_dir));
+    let mut files = Vec::new();
+    collect_files(task_dir, &mut files).map_err(|e| format!("Failed to read staging folder {}: {}", task_dir.display(), e))?;
+    for path in files {
+        let path_str = path.to_string_lossy().to_string();
+        if staged.iter().any(|artifact| artifact.path == path_str) {
+            continue;
+        }
+        logger.warn(&format!("yt-dlp 未报告的文件，一并移动: {}", path_str));
+        let size = std::fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
+        staged.push(DownloadArtifact { kind: guess_kind(&path), path: path_str, size });
+    }
+
+    let mut dests: Vec<PathBuf> = staged
+        .iter()
+        .map(|artifact| Path::new(output_path).join(Path::new(&artifact.path).strip_prefix(task_dir).unwrap_or(Path::new(""))))
+        .collect();
+    if let Some(existing) = dests.iter().find(|dest| dest.exists()) {
+        match options.collision {
+            CollisionPolicy::Overwrite => logger.info(&format!("覆盖已有文件: {}", existing.display())),
+            CollisionPolicy::Skip => return Ok(Finalized::Skipped(existing.clone())),
+            CollisionPolicy::Number | CollisionPolicy::Id => {
+                dests = renumber(&staged, dests, options);
+                logger.info(&format!("已存在同名文件，改为保存到: {}", dests[0].display()));
+            }
+        }
+    }
+
+    let overwrite = options.collision == CollisionPolicy::Overwrite;
+    let mut failed = Vec::new();
+    for (artifact, dest) in staged.into_iter().zip(dests) {
+        match move_file(Path::new(&artifact.path), &dest, overwrite) {
+            Ok(()) => result.push(DownloadArtifact {
+                path: dest.to_string_lossy().to_string(),
+                ..artifact
+            }),
+            Err(e) => {
+                logger.error(&format!("移动文件失败: {} -> {}: {}", artifact.path, dest.display(), e));
+                failed.push(artifact.path);
+            }
+        }
+    }
+
+    if !failed.is_empty() {
+        return Err(format!("Failed to move downloaded files to {}: {}", output_path, failed.join(", ")));
+    }
+    // 此时暂存目录中只剩下载残留的临时文件
+    if let Err(e) = std::fs::remove_dir_all(task_dir) {
+        logger.warn(&format!("删除暂存目录失败: {}: {}", task_dir.display(), e));
+    }
+    Ok(Finalized::Moved(result))
+}
+
+/// 递归列出暂存目录中的完整文件，跳过 .part/.ytdl/分片和合并前的单独流
+fn collect_files(dir: &Path, files: &mut Vec<PathBuf>) -> io::Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let kind = entry.file_type()?;
+        let path = entry.path();
+        if kind.is_dir() {
+            collect_files(&path, files)?;
+        } else if kind.is_file() && !is_leftover(&entry.file_name().to_string_lossy()) {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// 下载残留的临时文件
+fn is_leftover(name: &str) -> bool {
+    name.ends_with(".part") || name.ends_with(".ytdl") || name.contains(".part-Frag") || crate::tasks::is_intermediate_stream(name)
+}
+
+/// 按扩展名推断 yt-dlp 未报告的文件类型
+fn guess_kind(path: &Path) -> ArtifactKind {
+    let name = path.file_name().map(|name| name.to_string_lossy().to_lowercase()).unwrap_or_default();
+    let ext = name.rsplit('.').next().unwrap_or_default();
+    if name.ends_with(".info.json") {
+        ArtifactKind::InfoJson
+    } else if SUBTITLE_EXTENSIONS.contains(&ext) {
+        ArtifactKind::Subtitle
+    } else if THUMBNAIL_EXTENSIONS.contains(&ext) {
+        ArtifactKind::Thumbnail
+    } else {
+        ArtifactKind::Media
+    }
+}
+
+/// 给与主文件同名的所有文件（字幕、缩略图等）添加相同的数字后缀，直到没有目标文件已存在
+fn renumber(staged: &[DownloadArtifact], dests: Vec<PathBuf>, options: &FilenameOptions) -> Vec<PathBuf> {
+    let main = staged.iter().position(|artifact| artifact.kind == ArtifactKind::Media).unwrap_or(0);
+    let Some(stem) = dests[main].file_stem().map(|stem| stem.to_string_lossy().to_string()) else {
+        return dests;
+    };
+    let rename = |dest: &Path, new_stem: &str| {
+        let name = dest.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default();
+        match name.strip_prefix(&stem).filter(|rest| rest.starts_with('.')) {
+            Some(rest) => dest.with_file_name(format!("{}{}", new_stem, rest)),
+            None => dest.to_path_buf(),
+        }
+    };
+    (1..)
+        .map(|n| {
+            let new_stem = filename::numbered(Path::new(&stem), n, options).to_string_lossy().to_string();
+            dests.iter().map(|dest| rename(dest, &new_stem)).collect::<Vec<_>>()
+        })
+        .find(|candidate| candidate.iter().all(|dest| !dest.exists()))
+        .unwrap_or(dests)
+}
+
+/// 移动文件：同一文件系统内直接重命名；否则先复制为目标目录中的临时文件，写入磁盘后再重命名，
+/// 保证保存目录中只会出现完整的文件。overwrite 为 false 时不会替换已存在的目标文件
+fn move_file(src: &Path, dest: &Path, overwrite: bool) -> io::Result<()> {
+    let check_dest = || {
+        if !overwrite && dest.exists() {
+            return Err(io::Error::new(io::ErrorKind::AlreadyExists, "destination already exists"));
+        }
+        Ok(())
+    };
+    if let Some(parent) = dest.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    check_dest()?;
+    if std::fs::rename(src, dest).is_ok() {
+        return Ok(());
+    }
+
+    let name = dest.file_name().map(|name| name.to_string_lossy()).unwrap_or_default();
+    let temp = dest.with_file_name(format!(".{}.{}.tmp", name, uuid::Uuid::new_v4().simple()));
+    let copied = std::fs::copy(src, &temp)
+        .and_then(|_| std::fs::File::open(&temp)?.sync_all())
+        // 复制大文件期间目标可能已被创建
+        .and_then(|_| check_dest())
+        .and_then(|_| std::fs::rename(&temp, dest));
+    if let Err(e) = copied {
+        let _ = std::fs::remove_file(&temp);
+        return Err(e);
+    }
+    std::fs::remove_file(src)
+}
+
+/// 删除任务的暂存目录（下载失败或取消时）
+pub fn discard(task_dir: &Path) {
+    if task_dir.exists() {
+        if let Err(e) = std::fs::remove_dir_all(task_dir) {
+            crate::logger::AppLogger::get().warn(&format!("删除暂存目录失败: {}: {}", task_dir.display(), e));
+        }
+    }
+}
+
+/// 启动时清理残留的暂存内容：删除不属于 keep 中任务（恢复的未完成任务）的任务目录，
+/// 只删除以任务 ID 命名的目录，不影响用户指定的暂存目录中的其他文件
+pub fn cleanup(config: &StagingConfig, keep: &[String]) -> usize {
+    let Some(root) = config.root() else {
+        return 0;
+    };
+    let Ok(entries) = std::fs::read_dir(&root) else {
+        return 0;
+    };
+    let logger = crate::logger::AppLogger::get();
+    let mut removed = 0;
+    for entry in entries.flatten() {
+        let name = entry.file_name().to_string_lossy().to_string();
+        let is_task_dir = entry.file_type().is_ok_and(|kind| kind.is_dir()) && uuid::Uuid::parse_str(&name).is_ok();
+        if !is_task_dir || keep.contains(&name) {
+            continue;
+        }
+        match std::fs::remove_dir_all(entry.path()) {
+            Ok(()) => removed += 1,
+            Err(e) => logger.warn(&format!("清理暂存目录失败: {}: {}", entry.path().display(), e)),
+        }
+    }
+    if removed > 0 {
+        logger.info(&format!("已清理 {} 个残留的暂存目录: {}", removed, root.display()));
+    }
+    removed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("staging-test-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn moved(finalized: Result<Finalized, String>) -> Vec<DownloadArtifact> {
+        match finalized.unwrap() {
+            Finalized::Moved(artifacts) => artifacts,
+            Finalized::Skipped(existing) => panic!("unexpected skip: {}", existing.display()),
+        }
+    }
+
+    #[test]
+    fn test_stage_template() {
+        let task_dir = Path::new("/data/staging/abc");
+        assert_eq!(
+            stage_template("/dl/NA/NA - %(title).200B.%(ext)s", "/dl", task_dir).as_deref(),
+            Some("/data/staging/abc/NA/NA - %(title).200B.%(ext)s")
+        );
+        assert_eq!(
+            stage_template("/dl/100%%/Someone/Title.%(ext)s", "/dl/100%", task_dir).as_deref(),
+            Some("/data/staging/abc/Someone/Title.%(ext)s")
+        );
+        assert_eq!(stage_template("/other/Title.%(ext)s", "/dl", task_dir), None);
+    }
+
+    #[test]
+    fn test_finalize_moves_artifacts() {
+        let root = temp_dir();
+        let task_dir = root.join("task");
+        let output = root.join("out");
+        std::fs::create_dir_all(task_dir.join("Someone")).unwrap();
+        std::fs::write(task_dir.join("Someone/Title.mp4"), b"video").unwrap();
+        std::fs::write(task_dir.join("Someone/Title.mp4.part"), b"").unwrap();
+
+        let artifacts = vec![DownloadArtifact {
+            kind: ArtifactKind::Media,
+            path: task_dir.join("Someone/Title.mp4").to_string_lossy().to_string(),
+            size: 5,
+        }];
+        let moved = moved(finalize(&task_dir, &output.to_string_lossy(), artifacts, &FilenameOptions::default()));
+        let dest = output.join("Someone/Title.mp4");
+        assert_eq!(moved[0].path, dest.to_string_lossy());
+        assert_eq!(std::fs::read(&dest).unwrap(), b"video");
+        assert!(!output.join("Someone/Title.mp4.part").exists());
+        assert!(!task_dir.exists());
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_finalize_moves_unreported_files() {
+        let root = temp_dir();
+        let task_dir = root.join("task");
+        let output = root.join("out");
+        std::fs::create_dir_all(&task_dir).unwrap();
+        std::fs::write(task_dir.join("Title.mp4"), b"video").unwrap();
+        std::fs::write(task_dir.join("Title.en.srt"), b"subs").unwrap();
+        std::fs::write(task_dir.join("Title.f137.mp4.part"), b"").unwrap();
+
+        // 文件列表为空（写入或解析失败）时仍然移动完整的文件
+        let mut moved = moved(finalize(&task_dir, &output.to_string_lossy(), Vec::new(), &FilenameOptions::default()));
+        moved.sort_by(|a, b| a.path.cmp(&b.path));
+        let kinds: Vec<_> = moved.iter().map(|artifact| artifact.kind).collect();
+        assert_eq!(kinds, vec![ArtifactKind::Subtitle, ArtifactKind::Media]);
+        assert_eq!(std::fs::read(output.join("Title.mp4")).unwrap(), b"video");
+        assert!(!task_dir.exists());
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_finalize_applies_collision_policy() {
+        let root = temp_dir();
+        let output = root.join("out");
+        let out = output.to_string_lossy().to_string();
+        // 两个任务渲染出同一个文件名
+        let stage = |task: &str, content: &[u8]| {
+            let task_dir = root.join(task);
+            std::fs::create_dir_all(&task_dir).unwrap();
+            std::fs::write(task_dir.join("Title.mp4"), content).unwrap();
+            std::fs::write(task_dir.join("Title.en.srt"), content).unwrap();
+            task_dir
+        };
+        let options = |collision| FilenameOptions { collision, ..Default::default() };
+
+        moved(finalize(&stage("first", b"first"), &out, Vec::new(), &options(CollisionPolicy::Skip)));
+        let second = stage("second", b"second");
+        let skipped = finalize(&second, &out, Vec::new(), &options(CollisionPolicy::Skip)).unwrap();
+        assert!(matches!(skipped, Finalized::Skipped(existing) if existing.starts_with(&output)));
+        assert_eq!(std::fs::read(output.join("Title.mp4")).unwrap(), b"first");
+        assert!(second.join("Title.mp4").exists());
+
+        moved(finalize(&second, &out, Vec::new(), &options(CollisionPolicy::Number)));
+        assert_eq!(std::fs::read(output.join("Title.mp4")).unwrap(), b"first");
+        assert_eq!(std::fs::read(output.join("Title (1).mp4")).unwrap(), b"second");
+        assert_eq!(std::fs::read(output.join("Title (1).en.srt")).unwrap(), b"second");
+
+        moved(finalize(&stage("third", b"third"), &out, Vec::new(), &options(CollisionPolicy::Overwrite)));
+        assert_eq!(std::fs::read(output.join("Title.mp4")).unwrap(), b"third");
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_cleanup_keeps_unfinished_tasks() {
+        let root = temp_dir();
+        let (stale, kept) = (uuid::Uuid::new_v4().to_string(), uuid::Uuid::new_v4().to_string());
+        for dir in [&stale, &kept, &"not-a-task".to_string()] {
+            std::fs::create_dir_all(root.join(dir)).unwrap();
+        }
+        let config = StagingConfig { enabled: true, dir: Some(root.to_string_lossy().to_string()) };
+
+        assert_eq!(cleanup(&config, std::slice::from_ref(&kept)), 1);
+        assert!(!root.join(&stale).exists());
+        assert!(root.join(&kept).exists());
+        assert!(root.join("not-a-task").exists());
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
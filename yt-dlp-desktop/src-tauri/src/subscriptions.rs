@@ -0,0 +1,616 @@
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::Duration;
+use once_cell::sync::Lazy;
+use quick_xml::events::Event;
+use quick_xml::Reader;
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+use crate::archive;
+use crate::config::AppConfig;
+use crate::playlist;
+use crate::queue::DownloadQueue;
+use crate::tasks::TaskRegistry;
+use crate::types::DownloadConfig;
+use crate::ytdlp;
+
+/// 默认检查间隔（分钟）
+pub const DEFAULT_INTERVAL_MINUTES: u64 = 60;
+
+/// 最短检查间隔（分钟），避免过于频繁地请求
+const MIN_INTERVAL_MINUTES: u64 = 5;
+
+/// 每个订阅最多保留的已处理条目数
+const MAX_SEEN_ENTRIES: usize = 1000;
+
+/// 调度器检查到期订阅的间隔
+const SCHEDULER_TICK: Duration = Duration::from_secs(60);
+
+/// 订阅来源类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SubscriptionKind {
+    Channel,
+    Playlist,
+    /// RSS/Atom 订阅源
+    Feed,
+}
+
+/// 订阅
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Subscription {
+    pub id: String,
+    pub url: String,
+    pub kind: SubscriptionKind,
+    /// 下载预设（格式、保存路径、仅音频等），url 字段不使用
+    pub preset: DownloadConfig,
+    pub interval_minutes: u64,
+    /// 首次检查时是否下载已有的视频（否则只下载之后的新视频）
+    pub backfill: bool,
+    pub created_at: String,
+    pub last_checked_at: Option<String>,
+    pub last_error: Option<String>,
+    /// 首次检查已成功完成（失败的检查不算，之后仍按首次检查处理）
+    #[serde(default)]
+    pub initialized: bool,
+    /// 已处理过的条目 URL（最近的在后）
+    #[serde(default)]
+    pub seen: Vec<String>,
+}
+
+/// 一次检查的结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SubscriptionCheckResult {
+    pub subscription_id: String,
+    /// 发现的条目数
+    pub discovered: usize,
+    /// 本次加入队列的任务 ID
+    pub task_ids: Vec<String>,
+    pub error: Option<String>,
+}
+
+/// 从订阅源发现的条目
+#[derive(Debug, Clone, PartialEq)]
+pub struct FeedEntry {
+    pub id: Option<String>,
+    pub url: String,
+    pub title: String,
+}
+
+/// 订阅文件写入锁
+static SUBSCRIPTIONS_LOCK: Lazy<Mutex<()>> = Lazy::new(|| Mutex::new(()));
+
+/// 正在检查的订阅，避免调度器和手动检查同时处理同一个订阅
+static CHECKING: Lazy<Mutex<HashSet<String>>> = Lazy::new(|| Mutex::new(HashSet::new()));
+
+fn subscriptions_file() -> Option<PathBuf> {
+    crate::config::app_data_dir().map(|dir| dir.join("subscriptions.json"))
+}
+
+/// 读取所有订阅
+pub fn list_subscriptions() -> Result<Vec<Subscription>, String> {
+    let Some(path) = subscriptions_file() else {
+        return Ok(Vec::new());
+    };
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let content = std::fs::read_to_string(&path).map_err(|e| format!("Failed to read subscriptions: {}", e))?;
+    serde_json::from_str(&content).map_err(|e| format!("Failed to parse subscriptions: {}", e))
+}
+
+fn save_subscriptions(subscriptions: &[Subscription]) -> Result<(), String> {
+    let path = subscriptions_file().ok_or("Failed to get app data dir")?;
+    let content = serde_json::to_string_pretty(subscriptions)
+        .map_err(|e| format!("Failed to serialize subscriptions: {}", e))?;
+    crate::config::write_file_atomic(&path, &content).map_err(|e| format!("Failed to write subscriptions: {}", e))
+}
+
+/// 修改单个订阅并保存（订阅已被删除时不做任何事）
+fn update_subscription<F: FnOnce(&mut Subscription)>(id: &str, f: F) -> Result<(), String> {
+    let _guard = SUBSCRIPTIONS_LOCK.lock().unwrap();
+    let mut subscriptions = list_subscriptions()?;
+    if let Some(subscription) = subscriptions.iter_mut().find(|s| s.id == id) {
+        f(subscription);
+        save_subscriptions(&subscriptions)?;
+    }
+    Ok(())
+}
+
+/// 添加订阅
+pub fn add_subscription(
+    url: String,
+    preset: DownloadConfig,
+    interval_minutes: Option<u64>,
+    backfill: bool,
+) -> Result<Subscription, String> {
+    let url = url.trim().to_string();
+    if !url.starts_with("http://") && !url.starts_with("https://") {
+        return Err(format!("Invalid subscription URL: {}", url));
+    }
+
+    let _guard = SUBSCRIPTIONS_LOCK.lock().unwrap();
+    let mut subscriptions = list_subscriptions()?;
+    if subscriptions.iter().any(|s| s.url == url) {
+        return Err(format!("Already subscribed: {}", url));
+    }
+
+    let subscription = Subscription {
+        id: uuid::Uuid::new_v4().to_string(),
+        kind: detect_kind(&url),
+        url,
+        preset,
+        interval_minutes: interval_minutes.unwrap_or(DEFAULT_INTERVAL_MINUTES).max(MIN_INTERVAL_MINUTES),
+        backfill,
+        created_at: chrono::Local::now().to_rfc3339(),
+        last_checked_at: None,
+        last_error: None,
+        initialized: false,
+        seen: Vec::new(),
+    };
+    subscriptions.push(subscription.clone());
+    save_subscriptions(&subscriptions)?;
+
+    crate::logger::AppLogger::get().info(&format!(
+        "添加订阅: {} ({:?}, 每 {} 分钟检查)",
+        subscription.url, subscription.kind, subscription.interval_minutes
+    ));
+    Ok(subscription)
+}
+
+/// 删除订阅
+pub fn remove_subscription(id: &str) -> Result<(), String> {
+    let _guard = SUBSCRIPTIONS_LOCK.lock().unwrap();
+    let mut subscriptions = list_subscriptions()?;
+    let len = subscriptions.len();
+    subscriptions.retain(|s| s.id != id);
+    if subscriptions.len() == len {
+        return Err(format!("Subscription not found: {}", id));
+    }
+    save_subscriptions(&subscriptions)
+}
+
+/// 立即检查订阅（id 为空时检查全部）
+pub async fn check_now(id: Option<&str>, app: &AppHandle) -> Result<Vec<SubscriptionCheckResult>, String> {
+    let subscriptions: Vec<Subscription> = list_subscriptions()?
+        .into_iter()
+        .filter(|s| id.is_none_or(|id| s.id == id))
+        .collect();
+    if let (Some(id), true) = (id, subscriptions.is_empty()) {
+        return Err(format!("Subscription not found: {}", id));
+    }
+
+    let mut results = Vec::new();
+    for subscription in subscriptions {
+        results.push(check_subscription(&subscription, app).await);
+    }
+    Ok(results)
+}
+
+/// 启动后台调度器，定期检查到期的订阅
+pub fn start_scheduler(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            tokio::time::sleep(SCHEDULER_TICK).await;
+
+            let subscriptions = match list_subscriptions() {
+                Ok(subscriptions) => subscriptions,
+                Err(e) => {
+                    crate::logger::AppLogger::get().error(&format!("读取订阅失败: {}", e));
+                    continue;
+                }
+            };
+
+            let now = chrono::Local::now();
+            for subscription in subscriptions.iter().filter(|s| is_due(s, now)) {
+                check_subscription(subscription, &app).await;
+            }
+        }
+    });
+}
+
+/// 检查一个订阅，将新条目加入下载队列
+async fn check_subscription(subscription: &Subscription, app: &AppHandle) -> SubscriptionCheckResult {
+    let logger = crate::logger::AppLogger::get();
+    let mut result = SubscriptionCheckResult {
+        subscription_id: subscription.id.clone(),
+        discovered: 0,
+        task_ids: Vec::new(),
+        error: None,
+    };
+
+    if !CHECKING.lock().unwrap().insert(subscription.id.clone()) {
+        result.error = Some("Subscription is already being checked".to_string());
+        return result;
+    }
+
+    let checked = check_entries(subscription, app).await;
+    CHECKING.lock().unwrap().remove(&subscription.id);
+
+    let new_seen = match checked {
+        Ok((discovered, task_ids, new_seen)) => {
+            result.discovered = discovered;
+            result.task_ids = task_ids;
+            new_seen
+        }
+        Err(e) => {
+            logger.error(&format!("检查订阅失败: {} - {}", subscription.url, e));
+            result.error = Some(e);
+            Vec::new()
+        }
+    };
+
+    let checked_at = chrono::Local::now().to_rfc3339();
+    let error = result.error.clone();
+    if let Err(e) = update_subscription(&subscription.id, |s| {
+        s.last_checked_at = Some(checked_at);
+        s.initialized |= error.is_none();
+        s.last_error = error;
+        s.seen.extend(new_seen);
+        let overflow = s.seen.len().saturating_sub(MAX_SEEN_ENTRIES);
+        s.seen.drain(..overflow);
+    }) {
+        logger.error(&format!("保存订阅状态失败: {}", e));
+    }
+
+    result
+}
+
+/// 发现条目并加入队列，返回 (发现数, 任务 ID, 新处理的条目 URL)
+async fn check_entries(subscription: &Subscription, app: &AppHandle) -> Result<(usize, Vec<String>, Vec<String>), String> {
+    let logger = crate::logger::AppLogger::get();
+    let timeout = AppConfig::load(app).unwrap_or_default().info_timeout();
+    let archive_path = archive::archive_path(&format!("subscription-{}", subscription.id))
+        .ok_or("Failed to get app data dir")?;
+
+    let entries = discover_entries(subscription, timeout).await?;
+    let discovered = entries.len();
+
+    let seen: HashSet<String> = subscription.seen.iter().cloned().collect();
+    let archived = archive::load_ids(&archive_path);
+    let queued = DownloadQueue::get().pending_urls();
+    let mut new_entries = select_new_entries(entries, &seen, &archived, &queued);
+
+    // 首次检查只记录已有条目，之后发布的视频才会下载
+    if !subscription.initialized && !subscription.backfill {
+        logger.info(&format!("订阅 {} 首次检查，记录 {} 个已有条目", subscription.url, new_entries.len()));
+        return Ok((discovered, Vec::new(), new_entries.into_iter().map(|entry| entry.url).collect()));
+    }
+
+    // 订阅源按从新到旧排列，从最旧的开始下载
+    new_entries.reverse();
+
+    let mut configs = Vec::new();
+    let mut infos = Vec::new();
+    let mut new_seen = Vec::new();
+    for entry in new_entries {
+        // 订阅源已提供视频 ID（如 yt:videoId）时直接使用，否则解析视频信息，确认条目可以下载并取得规范的视频 ID
+        let (video_id, info) = match entry.id.clone() {
+            Some(id) => (id, None),
+            None => match ytdlp::get_video_info(&entry.url, timeout).await {
+                Ok(info) => (info.id.clone(), Some(info)),
+                Err(e) => {
+                    // 不标记为已处理，下次检查时重试（如尚未开播的首映）
+                    logger.warn(&format!("订阅条目暂不可用: {} - {}", entry.url, e));
+                    continue;
+                }
+            },
+        };
+        new_seen.push(entry.url.clone());
+        if archived.contains(&video_id) {
+            continue;
+        }
+
+        logger.info(&format!("订阅 {} 发现新视频: {} ({})", subscription.url, entry.title, video_id));
+        configs.push(DownloadConfig {
+            url: entry.url,
+            playlist: None,
+            archive: Some(archive_path.to_string_lossy().to_string()),
+            ..subscription.preset.clone()
+        });
+        infos.push(info);
+    }
+
+    let queue = DownloadQueue::get();
+    let task_ids = queue.enqueue_all(configs);
+    // 已获取的视频信息交给任务，下载时不再重复获取
+    for (id, info) in task_ids.iter().zip(infos) {
+        if let Some(info) = info {
+            TaskRegistry::get().set_info(id, info);
+        }
+    }
+    if !task_ids.is_empty() {
+        queue.schedule(app);
+    }
+    Ok((discovered, task_ids, new_seen))
+}
+
+/// 获取订阅中的条目
+async fn discover_entries(subscription: &Subscription, timeout: Duration) -> Result<Vec<FeedEntry>, String> {
+    match subscription.kind {
+        SubscriptionKind::Feed => {
+            let xml = fetch_feed(&subscription.url, timeout).await?;
+            parse_feed(&xml)
+        }
+        SubscriptionKind::Channel | SubscriptionKind::Playlist => {
+            let info = playlist::get_playlist_info(&subscription.url, timeout).await?;
+            Ok(info
+                .entries
+                .into_iter()
+                .map(|entry| FeedEntry {
+                    id: Some(entry.id),
+                    url: entry.url,
+                    title: entry.title,
+                })
+                .collect())
+        }
+    }
+}
+
+/// 下载 RSS/Atom 订阅源
+async fn fetch_feed(url: &str, timeout: Duration) -> Result<String, String> {
+    let client = reqwest::Client::builder()
+        .timeout(timeout)
+        .build()
+        .map_err(|e| format!("Failed to create HTTP client: {}", e))?;
+    client
+        .get(url)
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|e| format!("Failed to fetch feed: {}", e))?
+        .text()
+        .await
+        .map_err(|e| format!("Failed to read feed: {}", e))
+}
+
+/// 解析 RSS（item/link）和 Atom（entry/link href，包括 YouTube 的 yt:videoId）订阅源
+pub fn parse_feed(xml: &str) -> Result<Vec<FeedEntry>, String> {
+    let mut reader = Reader::from_str(xml);
+    reader.config_mut().trim_text(true);
+
+    let mut entries = Vec::new();
+    let mut current: Option<FeedEntry> = None;
+    let mut field: Option<String> = None;
+
+    loop {
+        let event = reader
+            .read_event()
+            .map_err(|e| format!("Failed to parse feed at {}: {}", reader.buffer_position(), e))?;
+        match &event {
+            Event::Start(e) | Event::Empty(e) => {
+                let name = String::from_utf8_lossy(e.local_name().as_ref()).to_string();
+                match (name.as_str(), current.as_mut()) {
+                    ("entry" | "item", _) => {
+                        current = Some(FeedEntry { id: None, url: String::new(), title: String::new() });
+                    }
+                    ("link", Some(entry)) => {
+                        // Atom: <link rel="alternate" href="..."/>
+                        let mut href = None;
+                        let mut rel = None;
+                        for attr in e.attributes().flatten() {
+                            let value = attr.unescape_value().map(|v| v.to_string()).unwrap_or_default();
+                            match attr.key.local_name().as_ref() {
+                                b"href" => href = Some(value),
+                                b"rel" => rel = Some(value),
+                                _ => {}
+                            }
+                        }
+                        if let Some(href) = href.filter(|_| rel.as_deref().is_none_or(|rel| rel == "alternate")) {
+                            entry.url = href;
+                        }
+                    }
+                    _ => {}
+                }
+                // 自闭合元素没有文本内容
+                field = matches!(event, Event::Start(_)).then_some(name);
+            }
+            Event::Text(e) => {
+                let text = e.unescape().map(|t| t.to_string()).unwrap_or_default();
+                set_field(current.as_mut(), field.as_deref(), text);
+            }
+            Event::CData(e) => {
+                let text = String::from_utf8_lossy(e).to_string();
+                set_field(current.as_mut(), field.as_deref(), text);
+            }
+            Event::End(e) => {
+                if matches!(e.local_name().as_ref(), b"entry" | b"item") {
+                    if let Some(mut entry) = current.take() {
+                        if entry.url.is_empty() {
+                            if let Some(id) = &entry.id {
+                                entry.url = format!("https://www.youtube.com/watch?v={}", id);
+                            }
+                        }
+                        if !entry.url.is_empty() {
+                            entries.push(entry);
+                        }
+                    }
+                }
+                field = None;
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+
+    Ok(entries)
+}
+
+fn set_field(entry: Option<&mut FeedEntry>, field: Option<&str>, text: String) {
+    let Some(entry) = entry else {
+        return;
+    };
+    match field {
+        Some("title") => entry.title = text,
+        Some("videoId") => entry.id = Some(text),
+        // RSS: <link>...</link>
+        Some("link") if entry.url.is_empty() => entry.url = text,
+        _ => {}
+    }
+}
+
+/// 过滤出尚未处理、未下载且不在队列中的条目
+fn select_new_entries(
+    entries: Vec<FeedEntry>,
+    seen: &HashSet<String>,
+    archived: &HashSet<String>,
+    queued: &HashSet<String>,
+) -> Vec<FeedEntry> {
+    let mut urls = HashSet::new();
+    entries
+        .into_iter()
+        .filter(|entry| !seen.contains(&entry.url) && !queued.contains(&entry.url))
+        .filter(|entry| entry.id.as_ref().is_none_or(|id| !archived.contains(id)))
+        .filter(|entry| urls.insert(entry.url.clone()))
+        .collect()
+}
+
+/// 根据 URL 判断订阅类型
+fn detect_kind(url: &str) -> SubscriptionKind {
+    let lower = url.to_lowercase();
+    if lower.contains("/feeds/") || lower.contains("rss") || lower.contains("atom") || lower.ends_with(".xml") {
+        SubscriptionKind::Feed
+    } else if lower.contains("list=") || lower.contains("/playlist") {
+        SubscriptionKind::Playlist
+    } else {
+        SubscriptionKind::Channel
+    }
+}
+
+/// 订阅是否到了检查时间
+fn is_due(subscription: &Subscription, now: chrono::DateTime<chrono::Local>) -> bool {
+    let Some(last) = subscription
+        .last_checked_at
+        .as_deref()
+        .and_then(|last| chrono::DateTime::parse_from_rfc3339(last).ok())
+    else {
+        return true;
+    };
+    now.signed_duration_since(last) >= chrono::Duration::minutes(subscription.interval_minutes as i64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    const YOUTUBE_FEED: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<feed xmlns:yt="http://www.youtube.com/xml/schemas/2015" xmlns="http://www.w3.org/2005/Atom">
+  <title>Lectures</title>
+  <link rel="alternate" href="https://www.youtube.com/channel/UC123"/>
+  <entry>
+    <id>yt:video:abc</id>
+    <yt:videoId>abc</yt:videoId>
+    <title>Lecture 2 &amp; notes</title>
+    <link rel="alternate" href="https://www.youtube.com/watch?v=abc"/>
+  </entry>
+  <entry>
+    <yt:videoId>def</yt:videoId>
+    <title>Lecture 1</title>
+  </entry>
+</feed>"#;
+
+    const RSS_FEED: &str = r#"<?xml version="1.0"?>
+<rss version="2.0"><channel><title>Podcast</title>
+  <item><title><![CDATA[Episode 1]]></title><link>https://example.com/ep1</link></item>
+  <item><title>No link</title></item>
+</channel></rss>"#;
+
+    fn subscription(last_checked_at: Option<String>, interval_minutes: u64) -> Subscription {
+        Subscription {
+            id: "sub".to_string(),
+            url: "https://www.youtube.com/@lectures".to_string(),
+            kind: SubscriptionKind::Channel,
+            preset: DownloadConfig::default(),
+            interval_minutes,
+            backfill: false,
+            created_at: chrono::Local::now().to_rfc3339(),
+            last_checked_at,
+            last_error: None,
+            initialized: false,
+            seen: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_parse_youtube_atom_feed() {
+        let entries = parse_feed(YOUTUBE_FEED).unwrap();
+        assert_eq!(
+            entries,
+            vec![
+                FeedEntry {
+                    id: Some("abc".to_string()),
+                    url: "https://www.youtube.com/watch?v=abc".to_string(),
+                    title: "Lecture 2 & notes".to_string(),
+                },
+                FeedEntry {
+                    id: Some("def".to_string()),
+                    url: "https://www.youtube.com/watch?v=def".to_string(),
+                    title: "Lecture 1".to_string(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_rss_feed() {
+        let entries = parse_feed(RSS_FEED).unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].url, "https://example.com/ep1");
+        assert_eq!(entries[0].title, "Episode 1");
+    }
+
+    #[test]
+    fn test_select_new_entries() {
+        let entries = parse_feed(YOUTUBE_FEED).unwrap();
+        let archived = HashSet::from(["abc".to_string()]);
+        let selected = select_new_entries(entries.clone(), &HashSet::new(), &archived, &HashSet::new());
+        assert_eq!(selected.len(), 1);
+        assert_eq!(selected[0].id.as_deref(), Some("def"));
+
+        let seen = HashSet::from(["https://www.youtube.com/watch?v=def".to_string()]);
+        assert!(select_new_entries(selected, &seen, &HashSet::new(), &HashSet::new()).is_empty());
+    }
+
+    #[test]
+    fn test_detect_kind() {
+        assert_eq!(
+            detect_kind("https://www.youtube.com/feeds/videos.xml?channel_id=UC123"),
+            SubscriptionKind::Feed
+        );
+        assert_eq!(detect_kind("https://www.youtube.com/playlist?list=PL1"), SubscriptionKind::Playlist);
+        assert_eq!(detect_kind("https://www.youtube.com/@lectures"), SubscriptionKind::Channel);
+    }
+
+    #[test]
+    fn test_is_due() {
+        let now = chrono::Local::now();
+        assert!(is_due(&subscription(None, 60), now));
+        let recent = (now - chrono::Duration::minutes(10)).to_rfc3339();
+        assert!(!is_due(&subscription(Some(recent.clone()), 60), now));
+        assert!(is_due(&subscription(Some(recent), 5), now));
+    }
+
+    #[tokio::test]
+    async fn test_fetch_feed_from_local_server() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut buf = [0u8; 1024];
+            let _ = socket.read(&mut buf).await;
+            let response = format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: application/atom+xml\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                YOUTUBE_FEED.len(),
+                YOUTUBE_FEED
+            );
+            socket.write_all(response.as_bytes()).await.unwrap();
+        });
+
+        let xml = fetch_feed(&format!("http://{}/feeds/videos.xml", addr), Duration::from_secs(5))
+            .await
+            .unwrap();
+        assert_eq!(parse_feed(&xml).unwrap().len(), 2);
+    }
+}
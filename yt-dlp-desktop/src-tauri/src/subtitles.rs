@@ -0,0 +1,149 @@
+use std::collections::HashMap;
+use crate::types::{SubtitleFormat, SubtitleOptions, SubtitleTrack, YtDlpSubtitle};
+
+/// 未设置语言时下载的字幕语言
+const DEFAULT_LANGUAGE: &str = "en";
+
+impl SubtitleFormat {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SubtitleFormat::Srt => "srt",
+            SubtitleFormat::Vtt => "vtt",
+            SubtitleFormat::Ass => "ass",
+        }
+    }
+}
+
+impl SubtitleOptions {
+    /// 下载、转换和嵌入字幕的 yt-dlp 参数
+    pub fn ytdlp_args(&self) -> Vec<String> {
+        let languages: Vec<&str> = self
+            .languages
+            .iter()
+            .map(|language| language.trim())
+            .filter(|language| !language.is_empty())
+            .collect();
+        let languages = if languages.is_empty() {
+            DEFAULT_LANGUAGE.to_string()
+        } else {
+            languages.join(",")
+        };
+
+        let mut args = vec!["--write-subs".to_string()];
+        if self.include_automatic {
+            args.push("--write-auto-subs".to_string());
+        }
+        args.extend([
+            "--sub-langs".to_string(),
+            languages,
+            "--convert-subs".to_string(),
+            self.format.as_str().to_string(),
+        ]);
+        if self.embed {
+            // 嵌入后 yt-dlp 会删除字幕文件
+            args.push("--embed-subs".to_string());
+        }
+        args
+    }
+}
+
+/// 下载使用的字幕选项：下载未指定时使用应用设置，未选择语言时使用应用设置中的语言
+pub fn resolve_options(requested: Option<&SubtitleOptions>, defaults: &SubtitleOptions) -> SubtitleOptions {
+    match requested {
+        Some(options) if options.languages.is_empty() => SubtitleOptions {
+            languages: defaults.languages.clone(),
+            ..options.clone()
+        },
+        Some(options) => options.clone(),
+        None => defaults.clone(),
+    }
+}
+
+/// 整理 yt-dlp 返回的字幕列表：人工字幕在前，按语言代码排序，忽略直播聊天记录
+pub fn list_tracks(
+    manual: Option<HashMap<String, Vec<YtDlpSubtitle>>>,
+    automatic: Option<HashMap<String, Vec<YtDlpSubtitle>>>,
+) -> Vec<SubtitleTrack> {
+    let tracks = |subtitles: Option<HashMap<String, Vec<YtDlpSubtitle>>>, automatic: bool| {
+        let mut tracks: Vec<SubtitleTrack> = subtitles
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|(language, entries)| language != "live_chat" && !entries.is_empty())
+            .map(|(language, entries)| SubtitleTrack {
+                language,
+                name: entries.iter().find_map(|entry| entry.name.clone()),
+                automatic,
+                formats: entries.into_iter().filter_map(|entry| entry.ext).collect(),
+            })
+            .collect();
+        tracks.sort_by(|a, b| a.language.cmp(&b.language));
+        tracks
+    };
+
+    let mut result = tracks(manual, false);
+    result.extend(tracks(automatic, true));
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ytdlp_args() {
+        let options = SubtitleOptions {
+            languages: vec!["en".to_string(), " zh-Hans ".to_string(), String::new()],
+            include_automatic: true,
+            format: SubtitleFormat::Ass,
+            embed: true,
+        };
+        assert_eq!(
+            options.ytdlp_args(),
+            vec!["--write-subs", "--write-auto-subs", "--sub-langs", "en,zh-Hans", "--convert-subs", "ass", "--embed-subs"]
+        );
+        assert_eq!(
+            SubtitleOptions::default().ytdlp_args(),
+            vec!["--write-subs", "--sub-langs", "en", "--convert-subs", "srt"]
+        );
+    }
+
+    #[test]
+    fn test_resolve_options_falls_back_to_defaults() {
+        let defaults = SubtitleOptions {
+            languages: vec!["de".to_string()],
+            ..Default::default()
+        };
+        let requested = SubtitleOptions {
+            format: SubtitleFormat::Vtt,
+            ..Default::default()
+        };
+        let resolved = resolve_options(Some(&requested), &defaults);
+        assert_eq!(resolved.languages, vec!["de"]);
+        assert_eq!(resolved.format, SubtitleFormat::Vtt);
+        assert_eq!(resolve_options(None, &defaults), defaults);
+    }
+
+    #[test]
+    fn test_list_tracks() {
+        let json = r#"{
+            "subtitles": {
+                "live_chat": [{"ext": "json"}],
+                "fr": [{"ext": "vtt", "name": "French"}, {"ext": "srv3", "name": "French"}],
+                "en": [{"ext": "vtt", "name": "English"}]
+            },
+            "automatic_captions": {"en": [{"ext": "vtt", "name": "English (auto)"}], "xx": []}
+        }"#;
+        #[derive(serde::Deserialize)]
+        struct Output {
+            subtitles: Option<HashMap<String, Vec<YtDlpSubtitle>>>,
+            automatic_captions: Option<HashMap<String, Vec<YtDlpSubtitle>>>,
+        }
+        let output: Output = serde_json::from_str(json).unwrap();
+        let tracks = list_tracks(output.subtitles, output.automatic_captions);
+
+        let summary: Vec<(&str, bool)> = tracks.iter().map(|t| (t.language.as_str(), t.automatic)).collect();
+        assert_eq!(summary, vec![("en", false), ("fr", false), ("en", true)]);
+        assert_eq!(tracks[1].formats, vec!["vtt", "srv3"]);
+        assert_eq!(tracks[1].name.as_deref(), Some("French"));
+    }
+}
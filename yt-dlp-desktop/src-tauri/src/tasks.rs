@@ -0,0 +1,412 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use once_cell::sync::Lazy;
+use tokio::process::Child;
+use tokio::task::JoinHandle;
+use crate::types::{DownloadAttempt, DownloadConfig, DownloadProgress, PlaylistPosition, VideoInfo};
+
+/// 单个下载任务
+struct DownloadTask {
+    config: DownloadConfig,
+    /// yt-dlp 进程（进程启动后才有）
+    child: Option<Arc<Mutex<Child>>>,
+    /// stdout/stderr 读取任务
+    handles: Vec<JoinHandle<()>>,
+    /// yt-dlp 报告的目标文件（用于清理残留的 .part/.ytdl 文件）
+    destinations: Vec<PathBuf>,
+    /// 最近一次发送的进度（恢复下载时从这里继续显示）
+    last_progress: Option<DownloadProgress>,
+    /// 尚未写入队列记录的下载尝试
+    attempts: Vec<DownloadAttempt>,
+    /// 已获取的视频信息（重复检查、文件名渲染和画质降级共用，暂停后恢复时不再重新获取）
+    info: Option<VideoInfo>,
+    /// 当前流已下载完成或进入后处理（没有新字节不算停滞）
+    post_processing: bool,
+    cancelled: Arc<AtomicBool>,
+    paused: Arc<AtomicBool>,
+}
+
+/// 下载任务注册表（单例模式）
+pub struct TaskRegistry {
+    tasks: Mutex<HashMap<String, DownloadTask>>,
+}
+
+/// 全局任务注册表
+static REGISTRY: Lazy<TaskRegistry> = Lazy::new(|| TaskRegistry {
+    tasks: Mutex::new(HashMap::new()),
+});
+
+impl TaskRegistry {
+    /// 获取全局任务注册表
+    pub fn get() -> &'static TaskRegistry {
+        &REGISTRY
+    }
+
+    /// 注册新任务，返回唯一任务 ID
+    pub fn register(&self, config: DownloadConfig) -> String {
+        let id = uuid::Uuid::new_v4().to_string();
+        self.register_with_id(id.clone(), config);
+        id
+    }
+
+    /// 使用已有 ID 注册任务（从持久化的队列恢复时使用）
+    pub fn register_with_id(&self, id: String, config: DownloadConfig) {
+        let task = DownloadTask {
+            config,
+            child: None,
+            handles: Vec::new(),
+            destinations: Vec::new(),
+            last_progress: None,
+            attempts: Vec::new(),
+            info: None,
+            post_processing: false,
+            cancelled: Arc::new(AtomicBool::new(false)),
+            paused: Arc::new(AtomicBool::new(false)),
+        };
+        self.tasks.lock().unwrap().insert(id, task);
+    }
+
+    /// 关联已启动的 yt-dlp 进程
+    pub fn attach_process(&self, id: &str, child: Arc<Mutex<Child>>) {
+        if let Some(task) = self.tasks.lock().unwrap().get_mut(id) {
+            // 进程启动期间任务已被取消或暂停，立即终止
+            if task.cancelled.load(Ordering::SeqCst) || task.paused.load(Ordering::SeqCst) {
+                terminate(&mut child.lock().unwrap());
+            }
+            task.child = Some(child);
+        }
+    }
+
+    /// 关联输出读取任务
+    pub fn attach_handle(&self, id: &str, handle: JoinHandle<()>) {
+        if let Some(task) = self.tasks.lock().unwrap().get_mut(id) {
+            task.handles.push(handle);
+        }
+    }
+
+    /// 记录 yt-dlp 报告的目标文件
+    pub fn add_destination(&self, id: &str, path: PathBuf) {
+        if let Some(task) = self.tasks.lock().unwrap().get_mut(id) {
+            if !task.destinations.contains(&path) {
+                task.destinations.push(path);
+            }
+        }
+    }
+
+    /// 记录最近一次进度
+    pub fn set_progress(&self, id: &str, progress: &DownloadProgress) {
+        if let Some(task) = self.tasks.lock().unwrap().get_mut(id) {
+            task.last_progress = Some(progress.clone());
+        }
+    }
+
+    /// 获取最近一次进度
+    pub fn last_progress(&self, id: &str) -> Option<DownloadProgress> {
+        self.tasks
+            .lock()
+            .unwrap()
+            .get(id)
+            .and_then(|task| task.last_progress.clone())
+    }
+
+    /// 标记是否处于后处理阶段
+    pub fn set_post_processing(&self, id: &str, post_processing: bool) {
+        if let Some(task) = self.tasks.lock().unwrap().get_mut(id) {
+            task.post_processing = post_processing;
+        }
+    }
+
+    /// 是否处于后处理阶段
+    pub fn is_post_processing(&self, id: &str) -> bool {
+        self.tasks
+            .lock()
+            .unwrap()
+            .get(id)
+            .is_some_and(|task| task.post_processing)
+    }
+
+    /// 任务在播放列表中的位置
+    pub fn playlist_position(&self, id: &str) -> Option<PlaylistPosition> {
+        self.tasks
+            .lock()
+            .unwrap()
+            .get(id)
+            .and_then(|task| task.config.playlist.clone())
+    }
+
+    /// 任务是否只下载音频（下载后提取音频）
+    pub fn is_audio_only(&self, id: &str) -> bool {
+        self.tasks
+            .lock()
+            .unwrap()
+            .get(id)
+            .is_some_and(|task| task.config.audio_only)
+    }
+
+    /// 保存已获取的视频信息
+    pub fn set_info(&self, id: &str, info: VideoInfo) {
+        if let Some(task) = self.tasks.lock().unwrap().get_mut(id) {
+            task.info = Some(info);
+        }
+    }
+
+    /// 获取已保存的视频信息
+    pub fn info(&self, id: &str) -> Option<VideoInfo> {
+        self.tasks
+            .lock()
+            .unwrap()
+            .get(id)
+            .and_then(|task| task.info.clone())
+    }
+
+    /// 记录一次下载尝试
+    pub fn record_attempt(&self, id: &str, attempt: DownloadAttempt) {
+        if let Some(task) = self.tasks.lock().unwrap().get_mut(id) {
+            task.attempts.push(attempt);
+        }
+    }
+
+    /// 取出已记录的下载尝试
+    pub fn take_attempts(&self, id: &str) -> Vec<DownloadAttempt> {
+        self.tasks
+            .lock()
+            .unwrap()
+            .get_mut(id)
+            .map(|task| std::mem::take(&mut task.attempts))
+            .unwrap_or_default()
+    }
+
+    /// 任务是否已被取消
+    pub fn is_cancelled(&self, id: &str) -> bool {
+        self.tasks
+            .lock()
+            .unwrap()
+            .get(id)
+            .map(|task| task.cancelled.load(Ordering::SeqCst))
+            .unwrap_or(false)
+    }
+
+    /// 任务是否已被暂停
+    pub fn is_paused(&self, id: &str) -> bool {
+        self.tasks
+            .lock()
+            .unwrap()
+            .get(id)
+            .map(|task| task.paused.load(Ordering::SeqCst))
+            .unwrap_or(false)
+    }
+
+    /// 暂停任务：终止进程树但保留已下载的分片和 .part 文件
+    pub fn pause(&self, id: &str) -> Result<(), String> {
+        let (child, handles) = {
+            let mut tasks = self.tasks.lock().unwrap();
+            let task = tasks
+                .get_mut(id)
+                .ok_or_else(|| format!("Download task not found: {}", id))?;
+            task.paused.store(true, Ordering::SeqCst);
+            (task.child.take(), std::mem::take(&mut task.handles))
+        };
+
+        crate::logger::AppLogger::get().info(&format!("暂停下载任务: {}", id));
+
+        if let Some(child) = child {
+            terminate(&mut child.lock().unwrap());
+        }
+
+        for handle in handles {
+            handle.abort();
+        }
+
+        Ok(())
+    }
+
+    /// 仅终止当前进程树（停滞重启时使用，不改变任务状态也不清理文件）
+    pub fn kill_process(&self, id: &str) {
+        let child = self
+            .tasks
+            .lock()
+            .unwrap()
+            .get(id)
+            .and_then(|task| task.child.clone());
+        if let Some(child) = child {
+            terminate(&mut child.lock().unwrap());
+        }
+    }
+
+    /// 清理当前已下载的残留文件并重置进度（更换格式重新下载时使用）
+    pub fn discard_partial_files(&self, id: &str) {
+        let destinations = {
+            let mut tasks = self.tasks.lock().unwrap();
+            let Some(task) = tasks.get_mut(id) else {
+                return;
+            };
+            task.last_progress = None;
+            std::mem::take(&mut task.destinations)
+        };
+
+        let removed = cleanup_partial_files(&destinations);
+        crate::logger::AppLogger::get().info(&format!("任务 {} 已清理 {} 个残留文件", id, removed));
+    }
+
+    /// 恢复已暂停的任务，清除暂停标记以便重新启动进程
+    pub fn resume(&self, id: &str) -> Result<(), String> {
+        let mut tasks = self.tasks.lock().unwrap();
+        let task = tasks
+            .get_mut(id)
+            .ok_or_else(|| format!("Download task not found: {}", id))?;
+        task.paused.store(false, Ordering::SeqCst);
+        task.child = None;
+        crate::logger::AppLogger::get().info(&format!("恢复下载任务: {}", id));
+        Ok(())
+    }
+
+    /// 取消任务：终止进程树、停止读取任务并清理残留文件
+    pub fn cancel(&self, id: &str) -> Result<(), String> {
+        let logger = crate::logger::AppLogger::get();
+
+        let (child, handles, destinations, output_path) = {
+            let mut tasks = self.tasks.lock().unwrap();
+            let task = tasks
+                .get_mut(id)
+                .ok_or_else(|| format!("Download task not found: {}", id))?;
+            task.cancelled.store(true, Ordering::SeqCst);
+            (
+                task.child.clone(),
+                std::mem::take(&mut task.handles),
+                task.destinations.clone(),
+                task.config.output_path.clone(),
+            )
+        };
+
+        logger.info(&format!("取消下载任务: {}", id));
+
+        if let Some(child) = child {
+            terminate(&mut child.lock().unwrap());
+        }
+
+        for handle in handles {
+            handle.abort();
+        }
+
+        let removed = cleanup_partial_files(&destinations);
+        logger.info(&format!(
+            "已清理 {} 个残留文件（输出目录: {}）",
+            removed, output_path
+        ));
+
+        Ok(())
+    }
+
+    /// 移除已结束的任务
+    pub fn remove(&self, id: &str) {
+        self.tasks.lock().unwrap().remove(id);
+    }
+}
+
+/// 终止 yt-dlp 进程树（进程退出由下载任务的等待循环回收）
+fn terminate(child: &mut Child) {
+    if let Some(pid) = child.id() {
+        kill_process_tree(pid);
+    }
+    // 确保主进程一定被终止
+    let _ = child.start_kill();
+}
+
+/// 终止进程及其子进程（如 ffmpeg 合并进程）
+fn kill_process_tree(pid: u32) {
+    use std::process::Command;
+
+    #[cfg(unix)]
+    {
+        // yt-dlp 以独立进程组启动，向整个进程组发送信号
+        let _ = Command::new("kill")
+            .arg("-KILL")
+            .arg(format!("-{}", pid))
+            .status();
+    }
+
+    #[cfg(windows)]
+    {
+        let _ = Command::new("taskkill")
+            .arg("/PID")
+            .arg(pid.to_string())
+            .arg("/T")
+            .arg("/F")
+            .status();
+    }
+}
+
+/// 删除下载残留的 .part/.ytdl/分片文件以及未合并的单独流文件
+fn cleanup_partial_files(destinations: &[PathBuf]) -> usize {
+    let mut removed = 0;
+
+    for destination in destinations {
+        let Some(file_name) = destination.file_name().map(|n| n.to_string_lossy().to_string()) else {
+            continue;
+        };
+        let dir = destination.parent().unwrap_or(Path::new("."));
+
+        if let Ok(entries) = std::fs::read_dir(dir) {
+            for entry in entries.flatten() {
+                let name = entry.file_name().to_string_lossy().to_string();
+                if is_partial_file(&file_name, &name) && std::fs::remove_file(entry.path()).is_ok() {
+                    removed += 1;
+                }
+            }
+        }
+
+        // DASH 下载中已完成但尚未合并的单独流（如 title.f137.mp4）
+        if is_intermediate_stream(&file_name) && std::fs::remove_file(destination).is_ok() {
+            removed += 1;
+        }
+    }
+
+    removed
+}
+
+/// 判断文件是否是目标文件的下载残留
+fn is_partial_file(destination_name: &str, name: &str) -> bool {
+    name.strip_prefix(destination_name)
+        .map(|suffix| suffix == ".part" || suffix == ".ytdl" || suffix.starts_with(".part-Frag"))
+        .unwrap_or(false)
+}
+
+/// 判断是否是 yt-dlp 合并前的单独流文件（文件名包含 .f<格式ID>.）
+pub(crate) fn is_intermediate_stream(name: &str) -> bool {
+    let mut parts = name.rsplit('.');
+    parts.next(); // 扩展名
+    parts
+        .next()
+        .and_then(|part| part.strip_prefix('f'))
+        .map(|id| id.starts_with(|c: char| c.is_ascii_digit()))
+        .unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_partial_file() {
+        assert!(is_partial_file("video.f137.mp4", "video.f137.mp4.part"));
+        assert!(is_partial_file("video.f137.mp4", "video.f137.mp4.ytdl"));
+        assert!(is_partial_file("video.f137.mp4", "video.f137.mp4.part-Frag12"));
+        assert!(!is_partial_file("video.f137.mp4", "video.f137.mp4"));
+        assert!(!is_partial_file("video.f137.mp4", "video.mp4.part"));
+    }
+
+    #[test]
+    fn test_is_intermediate_stream() {
+        assert!(is_intermediate_stream("video.f137.mp4"));
+        assert!(is_intermediate_stream("video.f251.webm"));
+        assert!(!is_intermediate_stream("video.mp4"));
+        assert!(!is_intermediate_stream("my.file.mp4"));
+    }
+
+    #[test]
+    fn test_cancel_unknown_task() {
+        assert!(TaskRegistry::get().cancel("missing-task").is_err());
+    }
+}
@@ -1,3 +1,4 @@
+use std::collections::HashMap;
 use serde::{Deserialize, Serialize};
 
 /// 视频信息
@@ -12,6 +13,82 @@ pub struct VideoInfo {
     pub description: String,
     pub view_count: u64,
     pub formats: Vec<VideoFormat>,
+    /// 可用的字幕（人工字幕在前，其后是自动生成的字幕）
+    #[serde(default)]
+    pub subtitles: Vec<SubtitleTrack>,
+}
+
+/// 可用的字幕语言
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SubtitleTrack {
+    /// 语言代码（如 en、zh-Hans），即 --sub-langs 的取值
+    pub language: String,
+    /// 语言名称（如 English）
+    pub name: Option<String>,
+    /// 是否为自动生成的字幕
+    pub automatic: bool,
+    /// 可用的字幕格式（如 vtt、srv3）
+    pub formats: Vec<String>,
+}
+
+/// 字幕转换格式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SubtitleFormat {
+    #[default]
+    Srt,
+    Vtt,
+    Ass,
+}
+
+/// 字幕下载选项
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SubtitleOptions {
+    /// 语言代码，支持 yt-dlp 的正则写法（如 en.*）
+    pub languages: Vec<String>,
+    /// 同时下载自动生成的字幕
+    pub include_automatic: bool,
+    pub format: SubtitleFormat,
+    /// 嵌入视频容器；否则写入与视频同名的字幕文件
+    pub embed: bool,
+}
+
+/// 音频提取的目标格式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AudioCodec {
+    #[default]
+    Mp3,
+    /// AAC 编码的 M4A
+    M4a,
+    Opus,
+    Flac,
+    Wav,
+}
+
+/// 音频提取选项
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AudioOptions {
+    pub codec: AudioCodec,
+    /// 目标码率（kbit/s），为空时使用最高质量的可变码率；无损格式忽略
+    pub bitrate: Option<u32>,
+    /// 嵌入封面
+    pub embed_thumbnail: bool,
+    /// 写入标签（MP3 为 ID3，Opus/FLAC 为 Vorbis 注释）
+    pub embed_metadata: bool,
+}
+
+impl Default for AudioOptions {
+    fn default() -> Self {
+        Self {
+            codec: AudioCodec::default(),
+            bitrate: None,
+            embed_thumbnail: true,
+            embed_metadata: true,
+        }
+    }
 }
 
 /// 视频格式
@@ -28,11 +105,43 @@ pub struct VideoFormat {
     pub tbr: Option<f64>,  // tbr 也可能是 null
     pub format_note: String,
     pub hdr: bool,         // 是否支持 HDR
+    pub dynamic_range: DynamicRange,
     pub is_dash: bool,     // 是否是 DASH 格式
 }
 
+/// 视频动态范围
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DynamicRange {
+    #[default]
+    Sdr,
+    /// HDR10（包括 HDR10+ 和 HDR12）
+    Hdr10,
+    Hlg,
+    DolbyVision,
+}
+
+impl DynamicRange {
+    /// 根据 yt-dlp 的 dynamic_range 字段判断；旧版 yt-dlp 没有该字段时根据编码和格式说明推断
+    pub fn detect(dynamic_range: Option<&str>, vcodec: &str, format_note: &str) -> DynamicRange {
+        match dynamic_range.map(str::to_ascii_uppercase).as_deref() {
+            Some("SDR") => DynamicRange::Sdr,
+            Some("HLG") => DynamicRange::Hlg,
+            Some("DV") => DynamicRange::DolbyVision,
+            Some(range) if range.starts_with("HDR") => DynamicRange::Hdr10,
+            _ if vcodec.starts_with("dvh") || vcodec.starts_with("dav1") => DynamicRange::DolbyVision,
+            _ if format_note.contains("HDR") => DynamicRange::Hdr10,
+            _ => DynamicRange::Sdr,
+        }
+    }
+
+    pub fn is_hdr(&self) -> bool {
+        *self != DynamicRange::Sdr
+    }
+}
+
 /// 下载配置
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct DownloadConfig {
     pub url: String,
     pub format_id: String,
@@ -40,30 +149,222 @@ pub struct DownloadConfig {
     pub audio_only: bool,
     pub include_subtitles: bool,
     pub prefer_hdr: bool,
+    /// 字幕选项，为空时使用应用设置中的默认选项
+    #[serde(default)]
+    pub subtitles: Option<SubtitleOptions>,
+    /// 仅音频时的音频提取选项，为空时使用应用设置中的默认选项
+    #[serde(default)]
+    pub audio: Option<AudioOptions>,
+    /// 文件名模板（yt-dlp 输出模板，相对于保存路径），为空时使用应用设置中的模板
+    #[serde(default)]
+    pub filename_template: Option<String>,
+    /// 来自播放列表时记录所在位置
+    pub playlist: Option<PlaylistPosition>,
+    /// 下载存档文件（yt-dlp --download-archive），成功下载后记录视频 ID
+    pub archive: Option<String>,
+    /// 跳过“已下载”检查，强制重新下载
+    #[serde(default)]
+    pub force: bool,
 }
 
-/// 下载进度
+/// 播放列表条目在本次下载中的位置（第 index 个，共 count 个）
 #[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlaylistPosition {
+    pub playlist_id: String,
+    pub playlist_title: String,
+    pub index: u32,
+    pub count: u32,
+}
+
+/// 下载状态
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DownloadStatus {
+    #[default]
+    Queued,
+    Downloading,
+    Processing,
+    Paused,
+    Finished,
+    Error,
+    Cancelled,
+    /// 视频已下载过，未重新下载
+    Skipped,
+}
+
+/// 下载阶段
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DownloadPhase {
+    #[default]
+    Preparing,
+    /// 写入缩略图
+    Thumbnail,
+    Downloading,
+    Merging,
+    /// 提取音频（转换格式）
+    Extracting,
+    Metadata,
+    Cleanup,
+    Completed,
+}
+
+/// 下载产生的文件类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ArtifactKind {
+    Media,
+    Thumbnail,
+    Subtitle,
+    InfoJson,
+}
+
+/// 下载产生的文件
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DownloadArtifact {
+    pub kind: ArtifactKind,
+    pub path: String,
+    /// 文件大小（字节）
+    pub size: u64,
+}
+
+/// 下载进度
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct DownloadProgress {
-    pub status: String,
+    pub task_id: String,
+    pub status: DownloadStatus,
+    pub phase: DownloadPhase,
     pub percent: f64,
     pub speed: f64,
     pub eta: f64,
     pub downloaded: u64,
     pub total: u64,
     pub filename: String,
+    /// 下载完成后的主文件（合并后的视频或音频）
     pub file_path: Option<String>,
+    /// 下载完成后 yt-dlp 报告的全部文件（主文件、缩略图、字幕等）
+    #[serde(default)]
+    pub artifacts: Vec<DownloadArtifact>,
+    /// 实际下载的格式（画质降级后与请求的格式不同）
+    pub format_id: Option<String>,
+    /// 实际下载的画质（如 720p）
+    pub quality: Option<String>,
+    /// 播放列表下载时的位置（第几个，共几个）
+    pub playlist_index: Option<u32>,
+    pub playlist_count: Option<u32>,
+    /// 分片下载（HLS/DASH）时的当前分片和分片总数
+    pub fragment_index: Option<u32>,
+    pub fragment_count: Option<u32>,
 }
 
-/// 下载历史记录
+/// 一次下载尝试（每次启动 yt-dlp 下载进程记录一条）
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DownloadAttempt {
+    /// 第几次尝试（从 1 开始）
+    pub attempt: u32,
+    pub format_id: String,
+    pub started_at: String,
+    pub finished_at: String,
+    pub exit_code: Option<i32>,
+    pub error: Option<String>,
+}
+
+/// 播放列表/频道信息（平铺提取，不包含各视频的格式）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlaylistInfo {
+    pub id: String,
+    pub title: String,
+    pub uploader: String,
+    pub entries: Vec<PlaylistEntry>,
+}
+
+/// 播放列表条目
 #[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlaylistEntry {
+    pub id: String,
+    pub title: String,
+    pub url: String,
+    pub duration: Option<f64>,
+    /// 上传日期（YYYYMMDD，可能是近似值或未知）
+    pub upload_date: Option<String>,
+    /// 在播放列表中的序号（从 1 开始）
+    pub index: u32,
+}
+
+/// 下载历史记录的结果
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HistoryStatus {
+    #[default]
+    Finished,
+    Failed,
+    /// 保存目录已有同名文件，按文件名冲突策略跳过
+    Skipped,
+}
+
+impl HistoryStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            HistoryStatus::Finished => "finished",
+            HistoryStatus::Failed => "failed",
+            HistoryStatus::Skipped => "skipped",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<HistoryStatus> {
+        match value {
+            "finished" => Some(HistoryStatus::Finished),
+            "failed" => Some(HistoryStatus::Failed),
+            "skipped" => Some(HistoryStatus::Skipped),
+            _ => None,
+        }
+    }
+}
+
+/// 下载历史记录
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct DownloadHistoryItem {
+    /// 下载任务 ID
     pub id: String,
+    pub video_id: String,
     pub title: String,
+    /// 上传者/频道名
+    pub uploader: Option<String>,
+    /// yt-dlp 提取器名（小写，如 youtube），用于导出下载存档
+    pub extractor: Option<String>,
     pub url: String,
+    /// 下载失败时为空
     pub file_path: String,
-    pub download_date: String,
+    /// 下载产生的全部文件
+    pub artifacts: Vec<DownloadArtifact>,
+    pub format_id: String,
+    /// 实际下载的画质（如 720p）
+    pub quality: Option<String>,
+    /// 决定保存目录的整理规则
+    pub rule: Option<String>,
+    /// 文件大小（字节）
+    pub filesize: Option<u64>,
+    /// 视频时长（秒）
+    pub duration: Option<f64>,
     pub thumbnail: String,
+    pub started_at: String,
+    /// 下载完成（或失败）时间
+    pub download_date: String,
+    pub status: HistoryStatus,
+    /// 失败原因
+    pub error: Option<String>,
+    /// 每次启动下载的记录（包括自动重试和暂停后恢复）
+    pub attempts: Vec<DownloadAttempt>,
+}
+
+/// 分页的下载历史
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DownloadHistoryPage {
+    pub items: Vec<DownloadHistoryItem>,
+    pub total: u64,
+    /// 页码（从 1 开始）
+    pub page: u32,
+    pub page_size: u32,
 }
 
 /// yt-dlp JSON 输出格式（部分字段）
@@ -78,6 +379,15 @@ pub struct YtDlpOutput {
     pub description: Option<String>,
     pub view_count: Option<u64>,
     pub formats: Option<Vec<YtDlpFormat>>,
+    pub subtitles: Option<HashMap<String, Vec<YtDlpSubtitle>>>,
+    pub automatic_captions: Option<HashMap<String, Vec<YtDlpSubtitle>>>,
+}
+
+/// yt-dlp 字幕信息（每种格式一项）
+#[derive(Debug, Deserialize)]
+pub struct YtDlpSubtitle {
+    pub ext: Option<String>,
+    pub name: Option<String>,
 }
 
 /// yt-dlp 格式信息
@@ -93,7 +403,7 @@ pub struct YtDlpFormat {
     pub acodec: Option<String>,
     pub tbr: Option<f64>,
     pub format_note: Option<String>,
-    pub hdr: Option<String>,  // HDR 信息
+    pub dynamic_range: Option<String>,  // 动态范围（SDR、HDR10、HDR10+、HDR12、HLG、DV）
 }
 
 impl From<YtDlpOutput> for VideoInfo {
@@ -126,9 +436,11 @@ impl From<YtDlpOutput> for VideoInfo {
                     return None;
                 }
 
-                // 检查是否是 HDR
-                let is_hdr = f.hdr.as_deref() == Some("10") || 
-                            f.format_note.as_deref().unwrap_or("").contains("HDR");
+                let dynamic_range = DynamicRange::detect(
+                    f.dynamic_range.as_deref(),
+                    f.vcodec.as_deref().unwrap_or(""),
+                    f.format_note.as_deref().unwrap_or(""),
+                );
                 
                 // 检查是否是 DASH 格式
                 let is_dash = f.format_note.as_deref().unwrap_or("").contains("dash") ||
@@ -160,7 +472,8 @@ impl From<YtDlpOutput> for VideoInfo {
                     acodec: f.acodec.unwrap_or_else(|| "unknown".to_string()),
                     tbr: f.tbr,
                     format_note: f.format_note.unwrap_or_default(),
-                    hdr: is_hdr,
+                    hdr: dynamic_range.is_hdr(),
+                    dynamic_range,
                     is_dash,
                 })
             })
@@ -176,6 +489,67 @@ impl From<YtDlpOutput> for VideoInfo {
             description: output.description.unwrap_or_default(),
             view_count: output.view_count.unwrap_or(0),
             formats,
+            subtitles: crate::subtitles::list_tracks(output.subtitles, output.automatic_captions),
+        }
+    }
+}
+
+/// 测试用的视频信息、格式和播放列表
+#[cfg(test)]
+pub(crate) mod test_support {
+    use super::*;
+
+    pub fn video_info(id: &str, title: &str) -> VideoInfo {
+        VideoInfo {
+            id: id.to_string(),
+            title: title.to_string(),
+            duration: 300,
+            thumbnail: String::new(),
+            uploader: "Someone".to_string(),
+            upload_date: "20240102".to_string(),
+            description: String::new(),
+            view_count: 42,
+            formats: Vec::new(),
+            subtitles: Vec::new(),
+        }
+    }
+
+    /// 音频流或视频流的 vcodec/acodec 为 "none"
+    pub fn video_format(format_id: &str, quality_label: &str, ext: &str, vcodec: &str, acodec: &str) -> VideoFormat {
+        VideoFormat {
+            format_id: format_id.to_string(),
+            ext: ext.to_string(),
+            resolution: String::new(),
+            quality_label: quality_label.to_string(),
+            filesize: None,
+            fps: None,
+            vcodec: vcodec.to_string(),
+            acodec: acodec.to_string(),
+            tbr: None,
+            format_note: String::new(),
+            hdr: false,
+            dynamic_range: DynamicRange::Sdr,
+            is_dash: acodec == "none" || vcodec == "none",
+        }
+    }
+
+    pub fn playlist_entry(id: &str, index: u32) -> PlaylistEntry {
+        PlaylistEntry {
+            id: id.to_string(),
+            title: id.to_string(),
+            url: format!("https://www.youtube.com/watch?v={}", id),
+            duration: None,
+            upload_date: None,
+            index,
+        }
+    }
+
+    pub fn playlist_info(id: &str, entries: Vec<PlaylistEntry>) -> PlaylistInfo {
+        PlaylistInfo {
+            id: id.to_string(),
+            title: "Lectures".to_string(),
+            uploader: "Someone".to_string(),
+            entries,
         }
     }
 }
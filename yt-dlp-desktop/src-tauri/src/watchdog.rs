@@ -0,0 +1,217 @@
+use std::time::{Duration, Instant};
+use serde::{Deserialize, Serialize};
+
+/// 停滞检测配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WatchdogConfig {
+    pub enabled: bool,
+    /// 启动后超过该秒数仍未收到第一个字节即视为停滞（包括解析视频信息的时间，因此比 stall_timeout_secs 长）
+    pub first_byte_timeout_secs: u64,
+    /// 超过该秒数没有收到任何新字节即视为停滞
+    pub stall_timeout_secs: u64,
+    /// 速度低于该值（字节/秒）视为被限速
+    pub min_speed_bytes_per_sec: u64,
+    /// 速度持续低于阈值超过该秒数即视为停滞
+    pub low_speed_timeout_secs: u64,
+    /// 单个任务最多自动重启次数
+    pub max_restarts: u32,
+}
+
+impl Default for WatchdogConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            first_byte_timeout_secs: 180,
+            stall_timeout_secs: 60,
+            min_speed_bytes_per_sec: 50 * 1024,
+            low_speed_timeout_secs: 120,
+            max_restarts: 3,
+        }
+    }
+}
+
+/// 停滞原因
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StallReason {
+    /// 启动后长时间没有开始接收数据
+    NoFirstByte(u64),
+    /// 长时间没有收到新数据
+    NoProgress(u64),
+    /// 速度长时间低于阈值
+    LowSpeed(u64),
+}
+
+impl std::fmt::Display for StallReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StallReason::NoFirstByte(secs) => write!(f, "{} 秒内没有开始接收数据", secs),
+            StallReason::NoProgress(secs) => write!(f, "{} 秒内没有收到新数据", secs),
+            StallReason::LowSpeed(secs) => write!(f, "下载速度持续 {} 秒低于阈值", secs),
+        }
+    }
+}
+
+/// 根据解析到的进度判断下载是否停滞
+pub struct StallWatchdog {
+    config: WatchdogConfig,
+    /// 首次观察到的已下载字节数（恢复下载时为上次的进度）
+    last_downloaded: Option<u64>,
+    /// 收到第一次字节进度后按 stall_timeout_secs 判断，之前按 first_byte_timeout_secs 判断
+    armed: bool,
+    last_change: Instant,
+    slow_since: Option<Instant>,
+}
+
+impl StallWatchdog {
+    pub fn new(config: WatchdogConfig, now: Instant) -> Self {
+        Self {
+            config,
+            last_downloaded: None,
+            armed: false,
+            last_change: now,
+            slow_since: None,
+        }
+    }
+
+    /// 记录一次进度观察，`active` 为 false 时（如合并、写入元数据）不做判断
+    pub fn observe(&mut self, now: Instant, downloaded: u64, speed: f64, active: bool) -> Option<StallReason> {
+        match self.last_downloaded {
+            Some(last) if last == downloaded => {}
+            Some(_) => {
+                self.armed = true;
+                self.last_downloaded = Some(downloaded);
+                self.last_change = now;
+            }
+            None => {
+                self.last_downloaded = Some(downloaded);
+                self.last_change = now;
+            }
+        }
+
+        if !self.config.enabled || !active {
+            self.last_change = now;
+            self.slow_since = None;
+            return None;
+        }
+
+        // 连接在第一个字节之前就卡住
+        if !self.armed {
+            let first_byte_timeout = Duration::from_secs(self.config.first_byte_timeout_secs);
+            if now.duration_since(self.last_change) >= first_byte_timeout {
+                return Some(StallReason::NoFirstByte(self.config.first_byte_timeout_secs));
+            }
+            return None;
+        }
+
+        let stall_timeout = Duration::from_secs(self.config.stall_timeout_secs);
+        if now.duration_since(self.last_change) >= stall_timeout {
+            return Some(StallReason::NoProgress(self.config.stall_timeout_secs));
+        }
+
+        // 速度为 0 时由无进度检测负责
+        if speed > 0.0 && speed < self.config.min_speed_bytes_per_sec as f64 {
+            let since = *self.slow_since.get_or_insert(now);
+            if now.duration_since(since) >= Duration::from_secs(self.config.low_speed_timeout_secs) {
+                return Some(StallReason::LowSpeed(self.config.low_speed_timeout_secs));
+            }
+        } else if speed > 0.0 {
+            self.slow_since = None;
+        }
+
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> WatchdogConfig {
+        WatchdogConfig {
+            enabled: true,
+            first_byte_timeout_secs: 30,
+            stall_timeout_secs: 10,
+            min_speed_bytes_per_sec: 1000,
+            low_speed_timeout_secs: 30,
+            max_restarts: 3,
+        }
+    }
+
+    #[test]
+    fn test_detects_no_progress() {
+        let start = Instant::now();
+        let mut watchdog = StallWatchdog::new(config(), start);
+
+        watchdog.observe(start, 0, 0.0, true);
+        assert_eq!(watchdog.observe(start + Duration::from_secs(5), 100, 5000.0, true), None);
+        assert_eq!(watchdog.observe(start + Duration::from_secs(14), 100, 5000.0, true), None);
+        assert_eq!(
+            watchdog.observe(start + Duration::from_secs(15), 100, 5000.0, true),
+            Some(StallReason::NoProgress(10))
+        );
+    }
+
+    #[test]
+    fn test_detects_low_speed() {
+        let start = Instant::now();
+        let mut watchdog = StallWatchdog::new(config(), start);
+
+        watchdog.observe(start, 0, 0.0, true);
+        for secs in 1..30 {
+            let now = start + Duration::from_secs(secs);
+            assert_eq!(watchdog.observe(now, secs * 100, 100.0, true), None);
+        }
+        assert_eq!(
+            watchdog.observe(start + Duration::from_secs(31), 3100, 100.0, true),
+            Some(StallReason::LowSpeed(30))
+        );
+    }
+
+    #[test]
+    fn test_speed_recovery_resets_low_speed_timer() {
+        let start = Instant::now();
+        let mut watchdog = StallWatchdog::new(config(), start);
+
+        watchdog.observe(start, 0, 0.0, true);
+        watchdog.observe(start + Duration::from_secs(1), 100, 100.0, true);
+        watchdog.observe(start + Duration::from_secs(20), 200, 5000.0, true);
+        assert_eq!(watchdog.observe(start + Duration::from_secs(40), 300, 100.0, true), None);
+    }
+
+    #[test]
+    fn test_inactive_phases_are_ignored() {
+        let start = Instant::now();
+        let mut watchdog = StallWatchdog::new(config(), start);
+
+        watchdog.observe(start, 0, 0.0, true);
+        watchdog.observe(start + Duration::from_secs(1), 100, 5000.0, true);
+        // 合并阶段长时间没有新字节不算停滞
+        assert_eq!(watchdog.observe(start + Duration::from_secs(100), 100, 0.0, false), None);
+        assert_eq!(watchdog.observe(start + Duration::from_secs(105), 100, 0.0, true), None);
+    }
+
+    #[test]
+    fn test_first_byte_timeout_before_progress() {
+        let start = Instant::now();
+        let mut watchdog = StallWatchdog::new(config(), start);
+
+        // 解析视频信息、等待服务器响应期间按首字节超时判断
+        assert_eq!(watchdog.observe(start, 0, 0.0, true), None);
+        assert_eq!(watchdog.observe(start + Duration::from_secs(29), 0, 0.0, true), None);
+        assert_eq!(
+            watchdog.observe(start + Duration::from_secs(30), 0, 0.0, true),
+            Some(StallReason::NoFirstByte(30))
+        );
+
+        // 恢复下载时初始进度来自上次，同样要等到新的字节
+        let mut resumed = StallWatchdog::new(config(), start);
+        assert_eq!(resumed.observe(start, 5000, 0.0, true), None);
+        assert_eq!(resumed.observe(start + Duration::from_secs(20), 5000, 0.0, true), None);
+        assert_eq!(resumed.observe(start + Duration::from_secs(21), 6000, 2000.0, true), None);
+        assert_eq!(
+            resumed.observe(start + Duration::from_secs(31), 6000, 0.0, true),
+            Some(StallReason::NoProgress(10))
+        );
+    }
+}
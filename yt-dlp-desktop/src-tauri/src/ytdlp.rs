@@ -1,9 +1,24 @@
-use std::process::Command;
-use crate::types::{VideoInfo, YtDlpOutput};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use once_cell::sync::Lazy;
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::process::Command;
+use tokio::sync::Semaphore;
+use crate::config::{AppConfig, QualityFallbackConfig};
+use crate::filename::CollisionPolicy;
+use crate::retry::RetryPolicy;
+use crate::types::{DownloadAttempt, DownloadPhase, DownloadProgress, DownloadStatus, HistoryStatus, VideoInfo, YtDlpOutput};
+use crate::watchdog::{StallReason, StallWatchdog, WatchdogConfig};
 use tauri::Emitter;
 
+/// 同时进行的视频信息获取数量上限，避免大量并发 yt-dlp 进程拖垮系统
+const MAX_CONCURRENT_INFO_FETCHES: usize = 4;
+
+static INFO_FETCH_PERMITS: Lazy<Arc<Semaphore>> =
+    Lazy::new(|| Arc::new(Semaphore::new(MAX_CONCURRENT_INFO_FETCHES)));
+
 /// 获取 yt-dlp 可执行文件路径
-fn get_ytdlp_path() -> String {
+pub(crate) fn get_ytdlp_path() -> String {
     // 在调试模式下使用开发路径
     #[cfg(debug_assertions)]
     {
@@ -142,8 +157,8 @@ fn get_ffmpeg_path() -> String {
     fallback.to_string()
 }
 
-/// 获取视频信息
-pub async fn get_video_info(url: &str) -> Result<VideoInfo, String> {
+/// 获取视频信息（超时后终止 yt-dlp 进程）
+pub async fn get_video_info(url: &str, timeout: Duration) -> Result<VideoInfo, String> {
     let ytdlp_path = get_ytdlp_path();
     let ffmpeg_path = get_ffmpeg_path();
 
@@ -163,9 +178,12 @@ pub async fn get_video_info(url: &str) -> Result<VideoInfo, String> {
         println!("[DEBUG] Executing yt-dlp command...");
     }
 
+    // 限制并发的信息获取进程数
+    let _permit = acquire_info_permit().await?;
+
     // 执行 yt-dlp 获取 JSON，包含所有格式
-    let output = Command::new(&ytdlp_path)
-        .arg("-J")  // 等同于 --dump-single-json
+    let mut cmd = Command::new(&ytdlp_path);
+    cmd.arg("-J")  // 等同于 --dump-single-json
         .arg("--no-playlist")
         .arg("--all-formats")  // 获取所有可用格式
         .arg("--format-sort")
@@ -173,12 +191,11 @@ pub async fn get_video_info(url: &str) -> Result<VideoInfo, String> {
         .arg("--ffmpeg-location")
         .arg(&ffmpeg_path)  // 指定 ffmpeg 位置
         .arg(url)
-        .output()
-        .map_err(|e| {
-            let error_msg = format!("Failed to execute yt-dlp: {}", e);
-            logger.error(&error_msg);
-            error_msg
-        })?;
+        .kill_on_drop(true);  // 超时后丢弃 future 时终止进程
+
+    let output = run_with_timeout(&mut cmd, timeout)
+        .await
+        .inspect_err(|e| logger.error(e))?;
 
     #[cfg(debug_assertions)]
     println!("[DEBUG] yt-dlp execution completed");
@@ -215,17 +232,56 @@ pub async fn get_video_info(url: &str) -> Result<VideoInfo, String> {
     Ok(video_info)
 }
 
+/// 获取信息获取进程的并发许可
+pub(crate) async fn acquire_info_permit() -> Result<tokio::sync::OwnedSemaphorePermit, String> {
+    INFO_FETCH_PERMITS
+        .clone()
+        .acquire_owned()
+        .await
+        .map_err(|e| format!("Failed to acquire fetch permit: {}", e))
+}
+
+/// 执行命令并等待输出，超时则终止进程
+pub(crate) async fn run_with_timeout(cmd: &mut Command, timeout: Duration) -> Result<std::process::Output, String> {
+    match tokio::time::timeout(timeout, cmd.output()).await {
+        Ok(result) => result.map_err(|e| format!("Failed to execute yt-dlp: {}", e)),
+        Err(_) => Err(format!("yt-dlp timed out after {} seconds", timeout.as_secs())),
+    }
+}
+
+/// 下载结束的结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DownloadOutcome {
+    Finished,
+    /// 保存目录已有同名文件，按文件名冲突策略跳过
+    Skipped,
+}
+
 /// 下载视频（支持自动合并 DASH 格式和实时进度）
 pub async fn download_video(
-    url: &str,
+    task_id: &str,
+    config: &crate::types::DownloadConfig,
     format_id: &str,
-    output_path: &str,
-    window: tauri::Window,
-) -> Result<(), String> {
+    info: Option<VideoInfo>,
+    app: tauri::AppHandle,
+) -> Result<DownloadOutcome, String> {
+    let url = config.url.as_str();
+    let started_at = chrono::Local::now().to_rfc3339();
     let ytdlp_path = get_ytdlp_path();
     let ffmpeg_path = get_ffmpeg_path();
 
     let logger = crate::logger::AppLogger::get();
+    let registry = crate::tasks::TaskRegistry::get();
+    let app_config = AppConfig::load(&app).unwrap_or_default();
+
+    // 有视频信息时由应用渲染文件名并按整理规则选择保存目录；否则字段交给 yt-dlp 渲染
+    if info.is_none() {
+        logger.warn("没有视频信息，文件名由 yt-dlp 渲染且不应用整理规则");
+    }
+    let rule = info
+        .as_ref()
+        .and_then(|info| crate::rules::apply(config, &app_config.organize_rules, info, &app_config.filename));
+    let output_path = rule.as_ref().map_or(config.output_path.as_str(), |rule| rule.destination.as_str());
 
     // 记录下载开始
     logger.info(&format!("开始下载: URL={}, 格式={}, 输出路径={}", url, format_id, output_path));
@@ -251,38 +307,371 @@ pub async fn download_video(
         }
     }
 
-    // 构建输出模板：output_path/%(title)s.%(ext)s
-    let output_template = format!("{}/%(title)s.%(ext)s", output_path);
+    // 构建输出模板：output_path/<文件名模板>，下载未指定模板时使用应用设置
+    let filename_template = config.filename_template.as_deref().unwrap_or(&app_config.filename_template);
+    let filename_options = &app_config.filename;
+    let output = crate::filename::output_template(output_path, filename_template, config.playlist.as_ref(), info.as_ref(), filename_options)
+        .inspect_err(|e| logger.error(e))?;
+    match filename_options.collision {
+        CollisionPolicy::Overwrite => {
+            for path in &output.existing {
+                logger.info(&format!("覆盖已有文件: {}", path.display()));
+                if let Err(e) = std::fs::remove_file(path) {
+                    logger.warn(&format!("删除已有文件失败: {}: {}", path.display(), e));
+                }
+            }
+        }
+        CollisionPolicy::Skip if !output.existing.is_empty() => {
+            let existing = output.existing[0].to_string_lossy().to_string();
+            logger.info(&format!("已存在同名文件，跳过下载: {}", existing));
+            record_history(crate::history::DownloadRecord {
+                task_id: task_id.to_string(),
+                url: url.to_string(),
+                file_path: Some(existing.clone()),
+                artifacts: Vec::new(),
+                format_id: format_id.to_string(),
+                quality: None,
+                rule: rule.as_ref().map(|rule| rule.name.clone()),
+                started_at: started_at.clone(),
+                status: HistoryStatus::Skipped,
+                error: Some(format!("File already exists: {}", existing)),
+                info: info.clone(),
+            })
+            .await;
+            emit_skipped(&app, task_id, existing);
+            return Ok(DownloadOutcome::Skipped);
+        }
+        _ => {}
+    }
+    // 下载中的文件写入任务的暂存目录，完成后再移动到保存目录
+    let staging_dir = app_config.staging.task_dir(task_id);
+    let staged_template = staging_dir
+        .as_deref()
+        .and_then(|dir| crate::staging::stage_template(&output.template, output_path, dir));
+    let staging_dir = staging_dir.filter(|_| staged_template.is_some());
+    let download_template = staged_template.as_deref().unwrap_or(&output.template);
+    let retry_policy = &app_config.retry;
+    let prefer_hdr = config.prefer_hdr || app_config.prefer_hdr;
+    let mut fallback = QualityFallback::new(app_config.quality_fallback.clone(), prefer_hdr, info.clone());
+    // 仅音频时由 yt-dlp 在下载后提取并转换音频
+    let audio_args = if config.audio_only {
+        crate::audio::resolve_options(config.audio.as_ref(), &app_config.audio).ytdlp_args()
+    } else {
+        Vec::new()
+    };
+    let subtitle_args = if config.include_subtitles || app_config.include_subtitles {
+        crate::subtitles::resolve_options(config.subtitles.as_ref(), &app_config.subtitles).ytdlp_args()
+    } else {
+        Vec::new()
+    };
+
+    // 下载失败时先按画质降级阶梯重试（仅网络错误），再按重试策略退避后重新启动
+    let mut current_format = format_id.to_string();
+    let mut attempt = 0;
+    let mut job_retries = 0;
+    let (status, error_output) = loop {
+        attempt += 1;
+        let started_at = chrono::Local::now().to_rfc3339();
+        let mut cmd = build_download_command(&ytdlp_path, &ffmpeg_path, url, &current_format, download_template, retry_policy, prefer_hdr);
+        if let Some(template) = crate::history::metadata_template(task_id) {
+            // 视频元数据写入应用数据目录，下载完成后用于记录历史
+            cmd.arg("--write-info-json").arg("-o").arg(format!("infojson:{}", template));
+        }
+        // 由 yt-dlp 报告后处理完成后的实际文件，而不是在输出目录中猜测
+        crate::artifacts::add_print_args(&mut cmd, task_id);
+        // 字幕：下载选定语言、转换格式，并按设置嵌入或保存为独立文件
+        cmd.args(&subtitle_args);
+        cmd.args(&audio_args);
+        cmd.args(filename_options.ytdlp_args());
+        if let Some(archive) = &config.archive {
+            // 成功下载后由 yt-dlp 记录视频 ID，已记录的视频会被跳过
+            cmd.arg("--download-archive").arg(archive);
+        }
+        let run = run_with_restarts(task_id, &mut cmd, &app, &app_config.watchdog).await;
 
-    // 构建 yt-dlp 命令
-    let mut cmd = Command::new(&ytdlp_path);
+        registry.record_attempt(task_id, DownloadAttempt {
+            attempt,
+            format_id: current_format.clone(),
+            started_at,
+            finished_at: chrono::Local::now().to_rfc3339(),
+            exit_code: run.as_ref().ok().and_then(|(status, _)| status.code()),
+            error: match &run {
+                Ok((status, _)) if status.success() => None,
+                Ok((_, error_output)) => Some(summarize_error(error_output)),
+                Err(e) => Some(e.clone()),
+            },
+        });
+
+        let (status, error_output) = run?;
+        if status.success() || registry.is_cancelled(task_id) || registry.is_paused(task_id) {
+            break (status, error_output);
+        }
+
+        if fallback.config.enabled && is_network_error(&error_output) {
+            if let Some(next) = fallback.next_format(url, &current_format, app_config.info_timeout()).await {
+                let quality = fallback.quality(&next);
+                logger.warn(&format!(
+                    "任务 {} 格式 {} 下载失败（网络错误），降级为 {}（{}）重试",
+                    task_id, current_format, next, quality.as_deref().unwrap_or("未知画质")
+                ));
+
+                // 不同格式的分片无法续传，清理上一次尝试的残留文件
+                registry.discard_partial_files(task_id);
+                emit_progress(&app, task_id, DownloadProgress {
+                    status: DownloadStatus::Downloading,
+                    phase: DownloadPhase::Preparing,
+                    filename: format!("Network error, retrying at {}...", quality.as_deref().unwrap_or(&next)),
+                    format_id: Some(next.clone()),
+                    quality,
+                    ..Default::default()
+                });
+                current_format = next;
+                continue;
+            }
+        }
+
+        if job_retries < retry_policy.job_retries {
+            job_retries += 1;
+            let delay = retry_policy.backoff(job_retries);
+            logger.warn(&format!(
+                "任务 {} 下载失败（退出码 {:?}），{} 秒后第 {}/{} 次重试",
+                task_id, status.code(), delay.as_secs(), job_retries, retry_policy.job_retries
+            ));
+            emit_progress(&app, task_id, DownloadProgress {
+                status: DownloadStatus::Downloading,
+                phase: DownloadPhase::Preparing,
+                speed: 0.0,
+                filename: format!(
+                    "Download failed, retrying in {}s ({}/{})...",
+                    delay.as_secs(), job_retries, retry_policy.job_retries
+                ),
+                ..registry.last_progress(task_id).unwrap_or_default()
+            });
+            wait_for_retry(task_id, delay).await;
+            continue;
+        }
+
+        break (status, error_output);
+    };
+    let final_quality = fallback.quality(&current_format);
+
+    if registry.is_cancelled(task_id) {
+        #[cfg(debug_assertions)]
+        println!("[DEBUG] Download cancelled");
+
+        if let Some(dir) = &staging_dir {
+            crate::staging::discard(dir);
+        }
+        emit_cancelled(&app, task_id);
+        return Err("Download cancelled".to_string());
+    }
+
+    if registry.is_paused(task_id) {
+        #[cfg(debug_assertions)]
+        println!("[DEBUG] Download paused");
+
+        emit_paused(&app, task_id);
+        return Err("Download paused".to_string());
+    }
+
+    // 给进度解析一点时间完成
+    tokio::time::sleep(Duration::from_millis(500)).await;
     
-    // 基本参数
+    if status.success() {
+        let artifacts = crate::artifacts::take_artifacts(task_id);
+        let artifacts = match &staging_dir {
+            Some(dir) => {
+                // 跨文件系统时需要复制文件，在阻塞线程中执行
+                let (task_dir, destination, options) = (dir.clone(), output_path.to_string(), filename_options.clone());
+                let finalized = tokio::task::spawn_blocking(move || crate::staging::finalize(&task_dir, &destination, artifacts, &options))
+                    .await
+                    .unwrap_or_else(|e| Err(format!("Failed to move downloaded files: {}", e)));
+                match finalized {
+                    Ok(crate::staging::Finalized::Moved(artifacts)) => artifacts,
+                    // 下载期间保存目录中出现了同名文件
+                    Ok(crate::staging::Finalized::Skipped(existing)) => {
+                        let existing = existing.to_string_lossy().to_string();
+                        logger.info(&format!("已存在同名文件，丢弃下载的文件: {}", existing));
+                        crate::staging::discard(dir);
+                        record_history(crate::history::DownloadRecord {
+                            task_id: task_id.to_string(),
+                            url: url.to_string(),
+                            file_path: Some(existing.clone()),
+                            artifacts: Vec::new(),
+                            format_id: current_format.clone(),
+                            quality: final_quality.clone(),
+                            rule: rule.as_ref().map(|rule| rule.name.clone()),
+                            started_at: started_at.clone(),
+                            status: HistoryStatus::Skipped,
+                            error: Some(format!("File already exists: {}", existing)),
+                            info: info.clone(),
+                        })
+                        .await;
+                        emit_skipped(&app, task_id, existing);
+                        return Ok(DownloadOutcome::Skipped);
+                    }
+                    Err(e) => {
+                        logger.error(&e);
+                        crate::staging::discard(dir);
+                        record_history(crate::history::DownloadRecord {
+                            task_id: task_id.to_string(),
+                            url: url.to_string(),
+                            file_path: None,
+                            artifacts: Vec::new(),
+                            format_id: current_format.clone(),
+                            quality: final_quality.clone(),
+                            rule: rule.as_ref().map(|rule| rule.name.clone()),
+                            started_at: started_at.clone(),
+                            status: HistoryStatus::Failed,
+                            error: Some(e.clone()),
+                            info: info.clone(),
+                        })
+                        .await;
+                        emit_progress(&app, task_id, DownloadProgress {
+                            status: DownloadStatus::Error,
+                            filename: e.clone(),
+                            ..Default::default()
+                        });
+                        return Err(e);
+                    }
+                }
+            }
+            None => artifacts,
+        };
+        let main_file = crate::artifacts::main_file(&artifacts);
+        let file_path = main_file.map(|artifact| artifact.path.clone());
+        if file_path.is_none() {
+            logger.warn(&format!("任务 {} 未报告下载文件（可能已在下载存档中）", task_id));
+        }
+
+        // 没有主文件时沿用最后一次进度中的大小
+        let (downloaded_size, total_size) = match main_file {
+            Some(artifact) => (artifact.size, artifact.size),
+            None => registry
+                .last_progress(task_id)
+                .map(|progress| (progress.downloaded, progress.total))
+                .unwrap_or_default(),
+        };
+
+        record_history(crate::history::DownloadRecord {
+            task_id: task_id.to_string(),
+            url: url.to_string(),
+            file_path: file_path.clone(),
+            artifacts: artifacts.clone(),
+            format_id: current_format.clone(),
+            quality: final_quality.clone(),
+            rule: rule.as_ref().map(|rule| rule.name.clone()),
+            started_at: started_at.clone(),
+            status: HistoryStatus::Finished,
+            error: None,
+            info: info.clone(),
+        })
+        .await;
+
+        // 发送完成事件
+        emit_progress(&app, task_id, DownloadProgress {
+            status: DownloadStatus::Finished,
+            phase: DownloadPhase::Completed,
+            percent: 100.0,
+            downloaded: downloaded_size,
+            total: total_size,
+            filename: "Download completed!".to_string(),
+            file_path,
+            artifacts,
+            format_id: Some(current_format),
+            quality: final_quality,
+            ..Default::default()
+        });
+
+        #[cfg(debug_assertions)]
+        println!("[DEBUG] Download completed successfully");
+
+        Ok(DownloadOutcome::Finished)
+    } else {
+        let error_msg = if !error_output.is_empty() {
+            format!("Download failed with exit code: {:?}\nError details: {}", status.code(), error_output)
+        } else {
+            format!("Download failed with exit code: {:?}", status.code())
+        };
+        
+        // 记录详细错误信息
+        logger.error(&format!("下载失败: {}", error_msg));
+        if let Some(dir) = &staging_dir {
+            crate::staging::discard(dir);
+        }
+
+        record_history(crate::history::DownloadRecord {
+            task_id: task_id.to_string(),
+            url: url.to_string(),
+            file_path: None,
+            artifacts: Vec::new(),
+            format_id: current_format.clone(),
+            quality: final_quality,
+            rule: rule.as_ref().map(|rule| rule.name.clone()),
+            started_at: started_at.clone(),
+            status: HistoryStatus::Failed,
+            error: Some(summarize_error(&error_output)),
+            info,
+        })
+        .await;
+
+        // 检查是否是SSL错误，如果是，尝试备用策略
+        if error_output.contains("SSL") || error_output.contains("ssl") {
+            logger.info("检测到SSL错误，建议尝试以下解决方案：");
+            logger.info("1. 检查网络连接");
+            logger.info("2. 尝试选择较低分辨率的格式");
+            logger.info("3. 稍后重试");
+        }
+        
+        emit_progress(&app, task_id, DownloadProgress {
+            status: DownloadStatus::Error,
+            phase: registry.last_progress(task_id).map(|p| p.phase).unwrap_or_default(),
+            filename: error_msg.clone(),
+            ..Default::default()
+        });
+        Err(error_msg)
+    }
+}
+
+/// 构建 yt-dlp 下载命令
+fn build_download_command(
+    ytdlp_path: &str,
+    ffmpeg_path: &str,
+    url: &str,
+    format_id: &str,
+    output_template: &str,
+    retry_policy: &RetryPolicy,
+    prefer_hdr: bool,
+) -> Command {
+    // 构建 yt-dlp 命令
+    let mut cmd = Command::new(ytdlp_path);
+
+    // 基本参数（--continue 使暂停后恢复时复用已下载的 .part 文件和分片）
     cmd.arg("-f").arg(format_id)
-       .arg("-o").arg(&output_template)
-       .arg("--no-playlist");
+       .arg("-o").arg(output_template)
+       .arg("--no-playlist")
+       .arg("--continue");
 
     // 对于 DASH 格式，确保启用合并
     if format_id.contains('+') {
         #[cfg(debug_assertions)]
         println!("[DEBUG] DASH format detected, enabling merge");
-        cmd.arg("--merge-output-format").arg("mp4");
+        // HDR 视频流（如 VP9.2）无法放入 MP4 时由 yt-dlp 改用 MKV
+        cmd.arg("--merge-output-format").arg(if prefer_hdr { "mp4/mkv" } else { "mp4" });
     }
 
     // 添加网络和重试相关参数
-    cmd.arg("--retries").arg("10")  // 重试10次
-       .arg("--fragment-retries").arg("10")  // 片段重试10次
-       .arg("--retry-sleep").arg("linear=1:5:10")  // 重试间隔：线性增长1-5-10秒
-       .arg("--socket-timeout").arg("30")  // Socket 超时30秒
+    cmd.args(retry_policy.ytdlp_args())  // 重试次数、重试间隔和 Socket 超时
        .arg("--no-check-certificates")  // 跳过SSL证书检查（临时解决方案）
        .arg("--user-agent").arg("Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/537.36")  // 设置用户代理
-       .arg("--ffmpeg-location").arg(&ffmpeg_path)  // 指定 ffmpeg 位置
+       .arg("--ffmpeg-location").arg(ffmpeg_path)  // 指定 ffmpeg 位置
        .arg("--embed-metadata")  // 嵌入元数据
        .arg("--write-thumbnail")  // 下载缩略图
        .arg("--convert-thumbnails").arg("jpg") // 转换缩略图为 JPG
        .arg("--newline")  // 每行输出进度
        .arg("--no-colors") // 禁用颜色输出
        .arg("--progress") // 启用进度输出
+       .arg("--progress-template").arg(crate::progress::DOWNLOAD_TEMPLATE) // 以 JSON 输出进度，便于准确解析
        .arg(url); // 添加 URL 参数
 
     println!("[DEBUG] Executing command: {:?}", cmd);
@@ -291,6 +680,152 @@ pub async fn download_video(
     cmd.stdout(std::process::Stdio::piped())
        .stderr(std::process::Stdio::piped());
 
+    // 以独立进程组启动，取消时可以连同 ffmpeg 子进程一起终止
+    #[cfg(unix)]
+    cmd.process_group(0);
+    cmd.kill_on_drop(true);
+
+    cmd
+}
+
+/// 运行 yt-dlp，停滞时终止并以断点续传方式重启，返回最终退出状态和错误输出
+async fn run_with_restarts(
+    task_id: &str,
+    cmd: &mut Command,
+    app: &tauri::AppHandle,
+    watchdog_config: &WatchdogConfig,
+) -> Result<(std::process::ExitStatus, String), String> {
+    let logger = crate::logger::AppLogger::get();
+    let registry = crate::tasks::TaskRegistry::get();
+    let mut restarts = 0;
+    loop {
+        // 任务在进程启动前已被取消或暂停
+        if registry.is_cancelled(task_id) {
+            emit_cancelled(app, task_id);
+            return Err("Download cancelled".to_string());
+        }
+        if registry.is_paused(task_id) {
+            emit_paused(app, task_id);
+            return Err("Download paused".to_string());
+        }
+
+        let run = run_download_process(task_id, cmd, app, watchdog_config).await?;
+
+        match run.stall {
+            Some(reason) if restarts < watchdog_config.max_restarts
+                && !registry.is_cancelled(task_id)
+                && !registry.is_paused(task_id) =>
+            {
+                restarts += 1;
+                logger.warn(&format!(
+                    "任务 {} 下载停滞（{}），第 {}/{} 次重启",
+                    task_id, reason, restarts, watchdog_config.max_restarts
+                ));
+                emit_progress(app, task_id, DownloadProgress {
+                    status: DownloadStatus::Downloading,
+                    phase: DownloadPhase::Preparing,
+                    speed: 0.0,
+                    filename: format!("Download stalled, restarting ({}/{})...", restarts, watchdog_config.max_restarts),
+                    ..registry.last_progress(task_id).unwrap_or_default()
+                });
+            }
+            Some(reason) => {
+                logger.error(&format!("任务 {} 下载停滞（{}），已达到最大重启次数", task_id, reason));
+                return Ok((run.status, format!("Download stalled: {}\n{}", reason, run.error_output)));
+            }
+            None => return Ok((run.status, run.error_output)),
+        }
+    }
+}
+
+/// 画质降级状态：首次降级时才构建降级阶梯，没有已获取的视频信息时再获取视频格式
+struct QualityFallback {
+    config: QualityFallbackConfig,
+    prefer_hdr: bool,
+    info: Option<VideoInfo>,
+    ladder: Option<Vec<String>>,
+}
+
+impl QualityFallback {
+    fn new(config: QualityFallbackConfig, prefer_hdr: bool, info: Option<VideoInfo>) -> Self {
+        Self { config, prefer_hdr, info, ladder: None }
+    }
+
+    /// 取出下一个降级格式，阶梯用尽时返回 None
+    async fn next_format(&mut self, url: &str, current_format: &str, timeout: Duration) -> Option<String> {
+        let logger = crate::logger::AppLogger::get();
+
+        if self.ladder.is_none() {
+            match diagnose_network_issue(url, timeout).await {
+                Ok(diagnosis) => logger.info(&format!("网络诊断结果: {}", diagnosis)),
+                Err(e) => logger.warn(&format!("网络诊断失败: {}", e)),
+            }
+
+            if self.info.is_none() {
+                match get_video_info(url, timeout).await {
+                    Ok(info) => self.info = Some(info),
+                    Err(e) => logger.warn(&format!("获取格式列表失败，无法降级画质: {}", e)),
+                }
+            }
+            let ladder = self.info.as_ref().map_or_else(Vec::new, |info| {
+                get_fallback_formats(current_format, &info.formats, self.config.min_height, self.prefer_hdr)
+            });
+            logger.info(&format!("画质降级阶梯: {:?}", ladder));
+            self.ladder = Some(ladder.into_iter().take(self.config.max_attempts as usize).collect());
+        }
+
+        let next = self.ladder.as_mut().filter(|ladder| !ladder.is_empty()).map(|ladder| ladder.remove(0));
+        if next.is_none() {
+            logger.error("画质降级阶梯已用尽");
+        }
+        next
+    }
+
+    /// 格式对应的画质（仅在获取过格式列表后可知）
+    fn quality(&self, format_id: &str) -> Option<String> {
+        self.info.as_ref().and_then(|info| describe_format(format_id, &info.formats))
+    }
+}
+
+/// 重试前等待，期间任务被取消或暂停时提前返回
+async fn wait_for_retry(task_id: &str, delay: Duration) {
+    let registry = crate::tasks::TaskRegistry::get();
+    let deadline = Instant::now() + delay;
+    while Instant::now() < deadline {
+        if registry.is_cancelled(task_id) || registry.is_paused(task_id) {
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(500)).await;
+    }
+}
+
+/// 提取 yt-dlp 错误输出中最有用的一行，用于记录下载尝试
+fn summarize_error(error_output: &str) -> String {
+    let lines = || error_output.lines().map(str::trim).filter(|line| !line.is_empty());
+    lines()
+        .rfind(|line| line.starts_with("ERROR"))
+        .or_else(|| lines().next_back())
+        .unwrap_or("Unknown error")
+        .to_string()
+}
+
+/// 单次 yt-dlp 进程运行结果
+struct ProcessRun {
+    status: std::process::ExitStatus,
+    error_output: String,
+    /// 被停滞检测终止时的原因
+    stall: Option<StallReason>,
+}
+
+/// 启动一次 yt-dlp 进程并等待其结束，期间由停滞检测监控下载进度
+async fn run_download_process(
+    task_id: &str,
+    cmd: &mut Command,
+    app: &tauri::AppHandle,
+    watchdog_config: &WatchdogConfig,
+) -> Result<ProcessRun, String> {
+    let registry = crate::tasks::TaskRegistry::get();
+
     // 启动进程
     let mut child = cmd
         .spawn()
@@ -298,54 +833,66 @@ pub async fn download_video(
 
     println!("[DEBUG] Download process started successfully");
 
-    // 发送初始进度状态
-    let _ = window.emit("download-progress", crate::types::DownloadProgress {
-        status: "downloading".to_string(),
-        percent: 0.0,
-        speed: 0.0,
-        eta: 0.0,
-        downloaded: 0,
-        total: 0,
-        filename: "Initializing download...".to_string(),
-        file_path: None,
-    });
+    // 发送初始进度状态（恢复下载时从上次的进度继续显示）
+    let initial_progress = match registry.last_progress(task_id) {
+        Some(last) => DownloadProgress {
+            status: DownloadStatus::Downloading,
+            phase: DownloadPhase::Preparing,
+            speed: 0.0,
+            filename: "Resuming download...".to_string(),
+            ..last
+        },
+        None => DownloadProgress {
+            status: DownloadStatus::Downloading,
+            phase: DownloadPhase::Preparing,
+            filename: "Initializing download...".to_string(),
+            ..Default::default()
+        },
+    };
+    emit_progress(app, task_id, initial_progress);
+
+    let stdout = child.stdout.take();
+    let stderr = child.stderr.take();
+    let child = Arc::new(std::sync::Mutex::new(child));
+    registry.attach_process(task_id, child.clone());
 
     // 在后台任务中处理进度输出
-    let window_clone = window.clone();
-    let progress_handle = if let Some(stdout) = child.stdout.take() {
+    let app_clone = app.clone();
+    let progress_task_id = task_id.to_string();
+    let (progress_done_tx, progress_done_rx) = tokio::sync::oneshot::channel::<()>();
+    let progress_handle = if let Some(stdout) = stdout {
         Some(tokio::spawn(async move {
-            parse_download_progress(stdout, window_clone).await;
+            parse_download_progress(&progress_task_id, stdout, app_clone).await;
+            let _ = progress_done_tx.send(());
         }))
     } else {
         None
     };
 
     // 捕获 stderr 输出用于错误诊断
-    let stderr_handle = if let Some(stderr) = child.stderr.take() {
+    let (stderr_tx, stderr_rx) = tokio::sync::oneshot::channel::<String>();
+    let stderr_handle = if let Some(stderr) = stderr {
         Some(tokio::spawn(async move {
-            use std::io::{BufRead, BufReader};
-            let reader = BufReader::new(stderr);
+            let mut lines = BufReader::new(stderr).lines();
             let mut error_output = String::new();
             let mut error_lines = Vec::new();
 
-            for line in reader.lines() {
-                if let Ok(line) = line {
-                    // 只在调试模式下打印到控制台
-                    #[cfg(debug_assertions)]
-                    println!("[STDERR] {}", line);
-
-                    error_output.push_str(&line);
-                    error_output.push('\n');
-
-                    // 只记录重要的错误信息，过滤进度和调试信息
-                    if line.contains("ERROR") ||
-                       line.contains("error") ||
-                       line.contains("failed") ||
-                       line.contains("SSL") ||
-                       line.contains("certificate") ||
-                       line.contains("timeout") {
-                        error_lines.push(line.clone());
-                    }
+            while let Ok(Some(line)) = lines.next_line().await {
+                // 只在调试模式下打印到控制台
+                #[cfg(debug_assertions)]
+                println!("[STDERR] {}", line);
+
+                error_output.push_str(&line);
+                error_output.push('\n');
+
+                // 只记录重要的错误信息，过滤进度和调试信息
+                if line.contains("ERROR") ||
+                   line.contains("error") ||
+                   line.contains("failed") ||
+                   line.contains("SSL") ||
+                   line.contains("certificate") ||
+                   line.contains("timeout") {
+                    error_lines.push(line.clone());
                 }
             }
 
@@ -363,341 +910,273 @@ pub async fn download_video(
                 logger.error(&error_summary);
             }
 
-            error_output
+            let _ = stderr_tx.send(error_output);
         }))
     } else {
         None
     };
 
-    // 等待进程完成
-    let status = child.wait().map_err(|e| format!("Failed to wait for process: {}", e))?;
-    
-    // 等待进度解析任务完成
-    if let Some(handle) = progress_handle {
-        let _ = handle.await;
+    // 读取任务交给注册表管理，取消时一并终止
+    for handle in progress_handle.into_iter().chain(stderr_handle) {
+        registry.attach_handle(task_id, handle);
     }
-    
+
+    // 等待进程完成（轮询，避免持有进程锁导致无法取消），同时检测下载是否停滞
+    let mut watchdog = StallWatchdog::new(watchdog_config.clone(), Instant::now());
+    let mut stall = None;
+    let status = loop {
+        let exited = child
+            .lock()
+            .unwrap()
+            .try_wait()
+            .map_err(|e| format!("Failed to wait for process: {}", e))?;
+        if let Some(status) = exited {
+            break status;
+        }
+
+        if stall.is_none() {
+            let progress = registry.last_progress(task_id).unwrap_or_default();
+            // 合并、写入元数据等后处理阶段没有下载字节，不做停滞判断
+            let active = matches!(progress.phase, DownloadPhase::Preparing | DownloadPhase::Downloading)
+                && !registry.is_post_processing(task_id);
+            if let Some(reason) = watchdog.observe(Instant::now(), progress.downloaded, progress.speed, active) {
+                stall = Some(reason);
+                registry.kill_process(task_id);
+            }
+        }
+
+        tokio::time::sleep(Duration::from_millis(200)).await;
+    };
+
+    // 等待进度解析任务完成
+    let _ = progress_done_rx.await;
+
     // 获取错误输出
-    let error_output = if let Some(handle) = stderr_handle {
-        handle.await.unwrap_or_default()
-    } else {
-        String::new()
+    let error_output = stderr_rx.await.unwrap_or_default();
+
+    Ok(ProcessRun {
+        status,
+        error_output,
+        stall,
+    })
+}
+
+/// 发送进度事件并记录到任务注册表
+///
+/// 同时发送到全局频道 `download-progress` 和任务专属频道 `download-progress:<任务ID>`
+pub(crate) fn emit_progress(app: &tauri::AppHandle, task_id: &str, progress: DownloadProgress) {
+    let registry = crate::tasks::TaskRegistry::get();
+    let position = registry.playlist_position(task_id);
+    let progress = DownloadProgress {
+        task_id: task_id.to_string(),
+        playlist_index: position.as_ref().map(|position| position.index),
+        playlist_count: position.as_ref().map(|position| position.count),
+        ..progress
     };
-    
-    // 给进度解析一点时间完成
-    tokio::time::sleep(tokio::time::Duration::from_millis(500)).await;
-    
-    if status.success() {
-        // 尝试找到下载的文件
-        let file_path = find_downloaded_file(&output_path);
+    registry.set_progress(task_id, &progress);
+    let _ = app.emit(&format!("download-progress:{}", task_id), progress.clone());
+    let _ = app.emit("download-progress", progress);
+}
+
+/// 发送暂停事件（保留已下载的进度）
+fn emit_paused(app: &tauri::AppHandle, task_id: &str) {
+    let last = crate::tasks::TaskRegistry::get().last_progress(task_id);
+    emit_progress(app, task_id, DownloadProgress {
+        status: DownloadStatus::Paused,
+        speed: 0.0,
+        eta: 0.0,
+        filename: "Download paused".to_string(),
+        file_path: None,
+        ..last.unwrap_or_default()
+    });
+}
+
+/// 发送跳过事件（保存目录已有同名文件）
+fn emit_skipped(app: &tauri::AppHandle, task_id: &str, existing: String) {
+    emit_progress(app, task_id, DownloadProgress {
+        status: DownloadStatus::Skipped,
+        filename: format!("File already exists: {}", existing),
+        file_path: Some(existing),
+        ..Default::default()
+    });
+}
+
+/// 发送取消事件
+fn emit_cancelled(app: &tauri::AppHandle, task_id: &str) {
+    emit_progress(app, task_id, DownloadProgress {
+        status: DownloadStatus::Cancelled,
+        filename: "Download cancelled".to_string(),
+        ..Default::default()
+    });
+}
+
+/// 解析 yt-dlp 的进度输出（优先使用 --progress-template 的 JSON 行，兼容默认的文本格式）
+async fn parse_download_progress(task_id: &str, stdout: tokio::process::ChildStdout, app: tauri::AppHandle) {
+    use crate::progress::{JobProgress, JobStep};
+
+    let registry = crate::tasks::TaskRegistry::get();
+    let mut lines = BufReader::new(stdout).lines();
+
+    // 合并各个流和后处理步骤的总进度，恢复下载时从上次的进度开始
+    let mut job = JobProgress::new(registry.last_progress(task_id).map(|p| p.percent).unwrap_or(0.0));
+    let mut current_filename = "Downloading...".to_string();
+    if registry.is_audio_only(task_id) {
+        job.expect_extraction();
+    }
+    registry.set_post_processing(task_id, false);
+
+    while let Ok(Some(line)) = lines.next_line().await {
+        println!("[PROGRESS] {}", line);
         
-        // 获取文件大小
-        let (downloaded_size, total_size) = if let Some(ref path) = file_path {
-            match std::fs::metadata(path) {
-                Ok(metadata) => {
-                    let size = metadata.len();
-                    (size, size)
-                },
-                Err(_) => {
-                    // 如果无法获取文件大小，尝试使用一个合理的默认值
-                    // 对于 DASH 格式，通常视频 + 音频文件会比较大
-                    (100_000_000, 100_000_000) // 100MB 作为默认值
-                }
-            }
-        } else {
-            (100_000_000, 100_000_000) // 100MB 作为默认值
-        };
+        // 检测文件名变化（当开始下载新文件时）
+        if let Some(destination) = line.strip_prefix("[download] Destination:") {
+            registry.set_post_processing(task_id, false);
+            registry.add_destination(task_id, std::path::PathBuf::from(destination.trim()));
+            current_filename = file_name(destination.trim());
+        }
         
-        // 发送完成事件
-        let _ = window.emit("download-progress", crate::types::DownloadProgress {
-            status: "finished".to_string(),
-            percent: 100.0,
-            speed: 0.0,
-            eta: 0.0,
-            downloaded: downloaded_size,
-            total: total_size,
-            filename: "Download completed!".to_string(),
-            file_path,
-        });
+        // 检测断点续传
+        if line.contains("Resuming download at byte") {
+            crate::logger::AppLogger::get().info(&format!("任务 {} 断点续传: {}", task_id, line));
+        }
 
-        #[cfg(debug_assertions)]
-        println!("[DEBUG] Download completed successfully");
+        // 任何后处理器输出都表示下载已结束（包括未单独显示的 FixupM3u8、SponsorBlock 等）
+        if crate::progress::postprocessor_name(&line).is_some() {
+            registry.set_post_processing(task_id, true);
+        }
 
-        Ok(())
-    } else {
-        let error_msg = if !error_output.is_empty() {
-            format!("Download failed with exit code: {:?}\nError details: {}", status.code(), error_output)
+        // 后处理步骤（缩略图、合并、元数据、清理）
+        let step = if line.contains("Writing video thumbnail") || line.contains("[ThumbnailsConvertor]") {
+            Some((DownloadPhase::Thumbnail, "Writing thumbnail..."))
+        } else if line.contains("[Merger] Merging formats") {
+            job.enter(JobStep::Merge);
+            Some((DownloadPhase::Merging, "Merging video and audio..."))
+        } else if line.starts_with("[ExtractAudio]") {
+            job.enter(JobStep::Extract);
+            Some((DownloadPhase::Extracting, "Extracting audio..."))
+        } else if line.contains("[Metadata] Adding metadata") || line.starts_with("[EmbedThumbnail]") {
+            job.enter(JobStep::Metadata);
+            Some((DownloadPhase::Metadata, "Adding metadata..."))
+        } else if line.contains("Deleting original file") {
+            // 删除临时文件表示处理即将完成
+            Some((DownloadPhase::Cleanup, "Cleaning up temporary files..."))
         } else {
-            format!("Download failed with exit code: {:?}", status.code())
+            None
         };
-        
-        // 记录详细错误信息
-        logger.error(&format!("下载失败: {}", error_msg));
-
-        // 检查是否是SSL错误，如果是，尝试备用策略
-        if error_output.contains("SSL") || error_output.contains("ssl") {
-            logger.info("检测到SSL错误，建议尝试以下解决方案：");
-            logger.info("1. 检查网络连接");
-            logger.info("2. 尝试选择较低分辨率的格式");
-            logger.info("3. 稍后重试");
+        if let Some((phase, message)) = step {
+            let status = if phase == DownloadPhase::Thumbnail {
+                DownloadStatus::Downloading
+            } else {
+                DownloadStatus::Processing
+            };
+            emit_progress(&app, task_id, DownloadProgress {
+                status,
+                phase,
+                percent: job.percent(),
+                downloaded: job.downloaded(),
+                total: job.total(),
+                filename: message.to_string(),
+                ..Default::default()
+            });
+            continue;
         }
-        
-        let _ = window.emit("download-progress", crate::types::DownloadProgress {
-            status: "error".to_string(),
-            percent: 0.0,
-            speed: 0.0,
-            eta: 0.0,
-            downloaded: 0,
-            total: 0,
-            filename: error_msg.clone(),
-            file_path: None,
-        });
-        Err(error_msg)
-    }
-}
-
-/// 解析 yt-dlp 的进度输出
-async fn parse_download_progress(stdout: std::process::ChildStdout, window: tauri::Window) {
-    use std::io::{BufRead, BufReader};
-    use regex::Regex;
 
+        let Some(update) = crate::progress::parse_line(&line) else {
+            continue;
+        };
 
-    let reader = BufReader::new(stdout);
-    
-    // 正则表达式匹配下载进度行
-    // 例如: [download]   2.3% of   10.15GiB at   49.23MiB/s ETA 03:26
-    let progress_regex = Regex::new(r"\[download\]\s+(\d+\.?\d*)%\s+of\s+([\d.]+\w+)\s+at\s+([\d.]+\w+/s)\s+ETA\s+([\d:]+)").unwrap();
-    
-    // 匹配 100% 完成行
-    // 例如: [download] 100% of  364.32MiB in 00:00:12 at 28.36MiB/s
-    let complete_regex = Regex::new(r"\[download\]\s+100%\s+of\s+([\d.]+\w+)\s+in\s+([\d:]+)\s+at\s+([\d.]+\w+/s)").unwrap();
-    
-    let mut last_total_bytes = 0u64;
-    let mut current_filename = "Downloading...".to_string();
-    let mut _total_downloaded = 0u64; // 跟踪总下载量
-    
-    for line in reader.lines() {
-        if let Ok(line) = line {
-            println!("[PROGRESS] {}", line);
-            
-            // 检测文件名变化（当开始下载新文件时）
-            if line.contains("[download] Destination:") {
-                if let Some(filename_start) = line.rfind('/') {
-                    current_filename = line[filename_start + 1..].to_string();
-                }
-            }
-            
-            // 检测合并状态
-            if line.contains("[Merger] Merging formats") {
-                let _ = window.emit("download-progress", crate::types::DownloadProgress {
-                    status: "processing".to_string(),
-                    percent: 100.0,
-                    speed: 0.0,
-                    eta: 0.0,
-                    downloaded: last_total_bytes,
-                    total: last_total_bytes,
-                    filename: "Merging video and audio...".to_string(),
-                    file_path: None,
-                });
-                continue;
-            }
-            
-            // 检测元数据添加状态
-            if line.contains("[Metadata] Adding metadata") {
-                let _ = window.emit("download-progress", crate::types::DownloadProgress {
-                    status: "processing".to_string(),
-                    percent: 100.0,
-                    speed: 0.0,
-                    eta: 0.0,
-                    downloaded: last_total_bytes,
-                    total: last_total_bytes,
-                    filename: "Adding metadata...".to_string(),
-                    file_path: None,
-                });
-                continue;
-            }
-            
-            // 检测删除临时文件（表示处理即将完成）
-            if line.contains("Deleting original file") {
-                let _ = window.emit("download-progress", crate::types::DownloadProgress {
-                    status: "processing".to_string(),
-                    percent: 100.0,
-                    speed: 0.0,
-                    eta: 0.0,
-                    downloaded: last_total_bytes,
-                    total: last_total_bytes,
-                    filename: "Cleaning up temporary files...".to_string(),
-                    file_path: None,
-                });
-                continue;
-            }
-            
-            // 检测 100% 完成
-            if let Some(captures) = complete_regex.captures(&line) {
-                let total_str = captures.get(1).unwrap().as_str();
-                let total_bytes = parse_size_to_bytes(total_str);
-                last_total_bytes = total_bytes;
-                _total_downloaded += total_bytes; // 累加下载量
-                
-                let progress = crate::types::DownloadProgress {
-                    status: "downloading".to_string(),
-                    percent: 100.0,
-                    speed: 0.0,
-                    eta: 0.0,
-                    downloaded: total_bytes,
-                    total: total_bytes,
-                    filename: current_filename.clone(),
-                    file_path: None,
-                };
-                
-                let _ = window.emit("download-progress", &progress);
-                continue;
-            }
-            
-            // 检测常规进度
-            if let Some(captures) = progress_regex.captures(&line) {
-                let percent = captures.get(1).unwrap().as_str().parse::<f64>().unwrap_or(0.0);
-                let total_str = captures.get(2).unwrap().as_str();
-                let speed_str = captures.get(3).unwrap().as_str();
-                let eta_str = captures.get(4).unwrap().as_str();
-                
-                // 解析文件大小
-                let total_bytes = parse_size_to_bytes(total_str);
-                let downloaded_bytes = (total_bytes as f64 * percent / 100.0) as u64;
-                last_total_bytes = total_bytes;
-                
-                // 解析速度
-                let speed_bytes = parse_speed_to_bytes_per_sec(speed_str);
-                
-                // 解析 ETA
-                let eta_seconds = parse_eta_to_seconds(eta_str);
-                
-                let progress = crate::types::DownloadProgress {
-                    status: "downloading".to_string(),
-                    percent,
-                    speed: speed_bytes as f64,
-                    eta: eta_seconds as f64,
-                    downloaded: downloaded_bytes,
-                    total: total_bytes,
-                    filename: current_filename.clone(),
-                    file_path: None,
-                };
-                
-                // 发送进度事件到前端
-                let _ = window.emit("download-progress", &progress);
+        // info.json 在开始下载前写入，从中读取各个流的大小用于加权
+        if !job.has_streams() {
+            let content = crate::history::metadata_file(task_id).and_then(|path| std::fs::read_to_string(path).ok());
+            if let Some(Err(e)) = content.map(|content| job.load_info_json(&content)) {
+                crate::logger::AppLogger::get().warn(&format!("任务 {} 读取流大小失败，按流数量平均计算进度: {}", task_id, e));
             }
         }
-    }
-}
 
-/// 解析文件大小字符串为字节数
-fn parse_size_to_bytes(size_str: &str) -> u64 {
-    let size_str = size_str.trim();
-    if let Some(pos) = size_str.find(|c: char| c.is_alphabetic()) {
-        let (number_part, unit_part) = size_str.split_at(pos);
-        if let Ok(number) = number_part.parse::<f64>() {
-            let multiplier = match unit_part.to_lowercase().as_str() {
-                "b" => 1,
-                "kb" => 1_024,
-                "mb" => 1_024_u64.pow(2),
-                "gb" => 1_024_u64.pow(3),
-                "gib" => 1_073_741_824, // 1024^3
-                "mib" => 1_048_576,     // 1024^2
-                "kib" => 1_024,
-                _ => 1,
-            };
-            return (number * multiplier as f64) as u64;
+        if let Some(path) = &update.filename {
+            registry.add_destination(task_id, std::path::PathBuf::from(path));
+            current_filename = file_name(path);
         }
-    }
-    0
-}
+        job.update_stream(&update);
+        // 最后一个流下载到 100% 后到下一个流开始前，没有新字节不算停滞
+        registry.set_post_processing(task_id, update.finished);
 
-/// 解析速度字符串为每秒字节数
-fn parse_speed_to_bytes_per_sec(speed_str: &str) -> u64 {
-    let speed_str = speed_str.trim().trim_end_matches("/s");
-    parse_size_to_bytes(speed_str)
+        emit_progress(&app, task_id, DownloadProgress {
+            status: DownloadStatus::Downloading,
+            phase: DownloadPhase::Downloading,
+            percent: job.percent(),
+            speed: update.speed.unwrap_or(0.0),
+            eta: job.eta(update.speed, update.eta),
+            downloaded: job.downloaded(),
+            total: job.total(),
+            filename: current_filename.clone(),
+            fragment_index: update.fragment_index,
+            fragment_count: update.fragment_count,
+            ..Default::default()
+        });
+    }
 }
 
-/// 解析 ETA 字符串为秒数
-fn parse_eta_to_seconds(eta_str: &str) -> u32 {
-    let parts: Vec<&str> = eta_str.split(':').collect();
-    match parts.len() {
-        2 => {
-            // MM:SS 格式
-            let minutes = parts[0].parse::<u32>().unwrap_or(0);
-            let seconds = parts[1].parse::<u32>().unwrap_or(0);
-            minutes * 60 + seconds
-        }
-        3 => {
-            // HH:MM:SS 格式
-            let hours = parts[0].parse::<u32>().unwrap_or(0);
-            let minutes = parts[1].parse::<u32>().unwrap_or(0);
-            let seconds = parts[2].parse::<u32>().unwrap_or(0);
-            hours * 3600 + minutes * 60 + seconds
-        }
-        _ => 0,
+/// 写入下载历史（SQLite 为同步调用，放到阻塞线程中执行）
+async fn record_history(record: crate::history::DownloadRecord) {
+    let saved = tokio::task::spawn_blocking(move || crate::history::HistoryStore::get().record_download(record))
+        .await
+        .unwrap_or_else(|e| Err(e.to_string()));
+    if let Err(e) = saved {
+        crate::logger::AppLogger::get().warn(&format!("记录下载历史失败: {}", e));
     }
 }
 
-/// 查找下载的文件
-fn find_downloaded_file(output_path: &str) -> Option<String> {
-    use std::fs;
+/// 取路径中的文件名部分
+fn file_name(path: &str) -> String {
+    std::path::Path::new(path)
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| path.to_string())
+}
 
-    
-    // 尝试读取输出目录
-    if let Ok(entries) = fs::read_dir(output_path) {
-        let mut video_files = Vec::new();
-        
-        for entry in entries.flatten() {
-            let path = entry.path();
-            if path.is_file() {
-                if let Some(ext) = path.extension() {
-                    let ext_str = ext.to_string_lossy().to_lowercase();
-                    // 查找视频文件
-                    if matches!(ext_str.as_str(), "mp4" | "mkv" | "webm" | "avi" | "mov" | "m4a" | "mp3") {
-                        // 排除缩略图文件
-                        if let Some(name) = path.file_name() {
-                            let name_str = name.to_string_lossy();
-                            if !name_str.contains("thumbnail") && !name_str.contains("thumb") && !name_str.contains(".jpg") {
-                                if let Ok(metadata) = path.metadata() {
-                                    if let Ok(modified) = metadata.modified() {
-                                        video_files.push((path.to_string_lossy().to_string(), modified));
-                                    }
-                                }
-                            }
-                        }
-                    }
-                }
-            }
+/// 根据下载配置确定最终传给 yt-dlp 的格式
+pub fn resolve_format_id(config: &crate::types::DownloadConfig) -> String {
+    if config.audio_only {
+        // 对于仅音频，使用 bestaudio 或指定的音频格式
+        if config.format_id.contains("audio") {
+            config.format_id.clone()
+        } else {
+            "bestaudio/best".to_string()
         }
-        
-        // 返回最新修改的文件
-        video_files.sort_by(|a, b| b.1.cmp(&a.1));
-        video_files.first().map(|(path, _)| path.clone())
     } else {
-        None
+        config.format_id.clone()
     }
 }
 
 /// 获取最佳格式（自动选择需要合并的 DASH 格式）
-pub fn get_best_format_for_quality(formats: &[crate::types::VideoFormat], quality: &str, preferred_ext: &str) -> Option<String> {
+///
+/// 优先 HDR 且该清晰度有 HDR 视频流时只选择 HDR 视频流，否则只选择 SDR 视频流（没有时再退而求其次）
+pub fn get_best_format_for_quality(formats: &[crate::types::VideoFormat], quality: &str, preferred_ext: &str, prefer_hdr: bool) -> Option<String> {
+    let want_hdr = prefer_hdr && formats.iter().any(|f| f.quality_label == quality && f.vcodec != "none" && f.hdr);
+
     // 首先尝试找到完整的格式（不需要合并）
     if let Some(format) = formats.iter().find(|f| 
         f.quality_label == quality && 
         f.ext == preferred_ext && 
         f.vcodec != "none" && 
-        f.acodec != "none"
+        f.acodec != "none" &&
+        f.hdr == want_hdr
     ) {
         println!("[DEBUG] Found complete format: {}", format.format_id);
         return Some(format.format_id.clone());
     }
 
-    // 如果没有完整格式，寻找需要合并的 DASH 格式
-    let video_format = formats.iter().find(|f| 
+    // 如果没有完整格式，寻找需要合并的 DASH 格式（HDR 视频流通常只有 DASH 格式）
+    let video_formats = || formats.iter().filter(|f| 
         f.quality_label == quality && 
         f.vcodec != "none" && 
         f.acodec == "none"
     );
+    let video_format = video_formats()
+        .find(|f| f.hdr == want_hdr && f.ext == preferred_ext)
+        .or_else(|| video_formats().find(|f| f.hdr == want_hdr))
+        .or_else(|| video_formats().next());
 
     let audio_format = formats.iter().find(|f| 
         f.vcodec == "none" && 
@@ -722,12 +1201,13 @@ pub fn get_best_format_for_quality(formats: &[crate::types::VideoFormat], qualit
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::types::test_support::video_format;
 
     #[tokio::test]
     #[ignore] // 跳过需要网络的测试
     async fn test_get_video_info() {
         let url = "https://www.youtube.com/watch?v=dQw4w9WgXcQ";
-        let result = get_video_info(url).await;
+        let result = get_video_info(url, Duration::from_secs(60)).await;
 
         if let Ok(info) = result {
             assert_eq!(info.id, "dQw4w9WgXcQ");
@@ -735,21 +1215,122 @@ mod tests {
             println!("Video title: {}", info.title);
         }
     }
+
+    fn sample_formats() -> Vec<crate::types::VideoFormat> {
+        vec![
+            video_format("140", "audio", "m4a", "none", "mp4a.40.2"),
+            video_format("18", "360p", "mp4", "avc1", "mp4a.40.2"),
+            video_format("136", "720p", "mp4", "avc1", "none"),
+            video_format("137", "1080p", "mp4", "avc1", "none"),
+            video_format("248", "1080p", "webm", "vp9", "none"),
+            video_format("313", "2160p", "webm", "vp9", "none"),
+        ]
+    }
+
+    #[test]
+    fn test_fallback_ladder_from_available_formats() {
+        let ladder = get_fallback_formats("313+140", &sample_formats(), 360, false);
+        assert_eq!(ladder, vec!["137+140", "136+140", "18", "best"]);
+    }
+
+    #[test]
+    fn test_fallback_ladder_respects_min_height() {
+        let ladder = get_fallback_formats("313+140", &sample_formats(), 720, false);
+        assert_eq!(ladder, vec!["137+140", "136+140", "best"]);
+    }
+
+    #[test]
+    fn test_fallback_ladder_for_generic_format() {
+        // 无法识别的格式按最高画质处理
+        let ladder = get_fallback_formats("bestvideo+bestaudio", &sample_formats(), 360, false);
+        assert_eq!(ladder, vec!["137+140", "136+140", "18", "best"]);
+    }
+
+    fn hdr_format(format_id: &str, quality_label: &str, ext: &str, vcodec: &str, dynamic_range: crate::types::DynamicRange) -> crate::types::VideoFormat {
+        crate::types::VideoFormat {
+            hdr: dynamic_range.is_hdr(),
+            dynamic_range,
+            ..video_format(format_id, quality_label, ext, vcodec, "none")
+        }
+    }
+
+    #[test]
+    fn test_best_format_prefers_hdr_streams() {
+        use crate::types::DynamicRange;
+        let mut formats = sample_formats();
+        formats.push(hdr_format("337", "2160p", "webm", "vp09.02.51.10", DynamicRange::Hdr10));
+        formats.push(hdr_format("701", "2160p", "mp4", "av01.0.12M.10", DynamicRange::Hdr10));
+        formats.push(video_format("22", "2160p", "mp4", "avc1", "mp4a.40.2"));
+
+        assert_eq!(get_best_format_for_quality(&formats, "2160p", "mp4", true), Some("701+140".to_string()));
+        assert_eq!(get_best_format_for_quality(&formats, "2160p", "webm", true), Some("337+140".to_string()));
+        assert_eq!(get_best_format_for_quality(&formats, "2160p", "mp4", false), Some("22".to_string()));
+        assert_eq!(get_best_format_for_quality(&formats, "2160p", "webm", false), Some("313+140".to_string()));
+        // 该清晰度没有 HDR 视频流时照常选择
+        assert_eq!(get_best_format_for_quality(&formats, "1080p", "mp4", true), Some("137+140".to_string()));
+    }
+
+    #[test]
+    fn test_detect_dynamic_range() {
+        use crate::types::DynamicRange;
+        assert_eq!(DynamicRange::detect(Some("HDR10+"), "vp9", ""), DynamicRange::Hdr10);
+        assert_eq!(DynamicRange::detect(Some("HLG"), "vp9", ""), DynamicRange::Hlg);
+        assert_eq!(DynamicRange::detect(Some("DV"), "dvh1.05.06", ""), DynamicRange::DolbyVision);
+        assert_eq!(DynamicRange::detect(Some("SDR"), "avc1", "1080p HDR"), DynamicRange::Sdr);
+        assert_eq!(DynamicRange::detect(None, "vp09.02", "2160p60 HDR"), DynamicRange::Hdr10);
+        assert_eq!(DynamicRange::detect(None, "avc1", "1080p"), DynamicRange::Sdr);
+    }
+
+    #[test]
+    fn test_describe_format() {
+        assert_eq!(describe_format("136+140", &sample_formats()), Some("720p".to_string()));
+        assert_eq!(describe_format("best", &sample_formats()), None);
+    }
+
+    #[test]
+    fn test_high_fps_formats_keep_their_height() {
+        assert_eq!(parse_height("1080p60"), Some(1080));
+        assert_eq!(parse_height("audio"), None);
+
+        let mut formats = sample_formats();
+        formats.push(video_format("298", "720p60", "mp4", "avc1", "none"));
+        formats.push(video_format("299", "1440p60", "mp4", "avc1", "none"));
+        assert_eq!(describe_format("298+140", &formats), Some("720p60".to_string()));
+        let ladder = get_fallback_formats("313+140", &formats, 720, false);
+        assert_eq!(ladder, vec!["299+140", "137+140", "298+140", "best"]);
+    }
+
+    #[test]
+    fn test_summarize_error() {
+        let output = "WARNING: [youtube] retrying\nERROR: unable to download video data: HTTP Error 403\n\n";
+        assert_eq!(summarize_error(output), "ERROR: unable to download video data: HTTP Error 403");
+        assert_eq!(summarize_error("WARNING: something\n"), "WARNING: something");
+        assert_eq!(summarize_error(""), "Unknown error");
+    }
+
+    #[test]
+    fn test_is_network_error() {
+        assert!(is_network_error("ERROR: [SSL: UNEXPECTED_EOF_WHILE_READING] EOF occurred"));
+        assert!(is_network_error("ERROR: Read timed out."));
+        assert!(!is_network_error("ERROR: Requested format is not available"));
+    }
 }
 /// 网络连接诊断
-pub async fn diagnose_network_issue(url: &str) -> Result<String, String> {
+pub async fn diagnose_network_issue(url: &str, timeout: Duration) -> Result<String, String> {
     let ytdlp_path = get_ytdlp_path();
     let ffmpeg_path = get_ffmpeg_path();
 
     // 测试基本连接
-    let output = Command::new(&ytdlp_path)
-        .arg("--simulate")
+    let mut cmd = Command::new(&ytdlp_path);
+    cmd.arg("--simulate")
         .arg("--no-playlist")
         .arg("--verbose")
         .arg("--ffmpeg-location")
         .arg(&ffmpeg_path)
         .arg(url)
-        .output()
+        .kill_on_drop(true);
+    let output = run_with_timeout(&mut cmd, timeout)
+        .await
         .map_err(|e| format!("Failed to run diagnostic: {}", e))?;
     
     let _stdout = String::from_utf8_lossy(&output.stdout);
@@ -779,27 +1360,83 @@ pub async fn diagnose_network_issue(url: &str) -> Result<String, String> {
     Ok(diagnosis)
 }
 
-/// 获取推荐的备用格式
-pub fn get_fallback_formats(original_format: &str) -> Vec<String> {
+/// 根据视频实际可用的格式构建画质降级阶梯（从高到低，最后以 best 兜底）
+pub fn get_fallback_formats(original_format: &str, formats: &[crate::types::VideoFormat], min_height: u32, prefer_hdr: bool) -> Vec<String> {
+    let video_heights = || formats.iter().filter(|f| f.vcodec != "none").filter_map(format_height);
+
+    // 原格式的画质；无法从格式 ID 判断时（如 bestvideo+bestaudio）按最高画质处理
+    let original_height = original_format
+        .split(['+', '/'])
+        .filter_map(|id| formats.iter().find(|f| f.format_id == id))
+        .filter_map(format_height)
+        .max()
+        .or_else(|| video_heights().max());
+
+    let mut heights: Vec<u32> = video_heights()
+        .filter(|h| *h >= min_height && original_height.is_none_or(|original| *h < original))
+        .collect();
+    heights.sort_unstable_by(|a, b| b.cmp(a));
+    heights.dedup();
+
     let mut fallbacks = Vec::new();
-    
-    // 如果原格式是4K，提供降级选项
-    if original_format.contains("701") || original_format.contains("315") || original_format.contains("337") {
-        // 4K -> 1440p
-        fallbacks.push("308+258".to_string()); // 1440p60 webm + audio
-        fallbacks.push("299+258".to_string()); // 1080p60 mp4 + audio
-        fallbacks.push("136+258".to_string()); // 720p mp4 + audio
-        fallbacks.push("best[height<=1080]".to_string()); // 最佳1080p或以下
-    } else if original_format.contains("308") {
-        // 1440p -> 1080p
-        fallbacks.push("299+258".to_string());
-        fallbacks.push("136+258".to_string());
-        fallbacks.push("best[height<=720]".to_string());
+    for height in heights {
+        // 同一高度可能有多个画质标签（如 720p60 和 720p），帧率高的在前
+        let mut labels: Vec<&str> = formats
+            .iter()
+            .filter(|f| f.vcodec != "none" && format_height(f) == Some(height))
+            .map(|f| f.quality_label.as_str())
+            .collect();
+        labels.sort_unstable_by(|a, b| b.cmp(a));
+        labels.dedup();
+        let best = labels
+            .into_iter()
+            .find_map(|label| get_best_format_for_quality(formats, label, "mp4", prefer_hdr));
+        if let Some(format) = best {
+            if format != original_format && !fallbacks.contains(&format) {
+                fallbacks.push(format);
+            }
+        }
     }
-    
+
     // 通用备用选项
-    fallbacks.push("best".to_string());
-    fallbacks.push("worst".to_string());
-    
+    if original_format != "best" {
+        fallbacks.push("best".to_string());
+    }
+
     fallbacks
 }
+
+/// 从画质标签（如 1080p）解析视频高度
+pub(crate) fn format_height(format: &crate::types::VideoFormat) -> Option<u32> {
+    parse_height(&format.quality_label)
+}
+
+/// 解析画质标签开头的高度（如 "1080p60" -> 1080）
+pub(crate) fn parse_height(quality: &str) -> Option<u32> {
+    let digits: String = quality.chars().take_while(char::is_ascii_digit).collect();
+    digits.parse().ok()
+}
+
+/// 描述格式对应的画质（如 720p），用于告知界面最终下载的画质
+pub(crate) fn describe_format(format_id: &str, formats: &[crate::types::VideoFormat]) -> Option<String> {
+    format_id
+        .split(['+', '/'])
+        .filter_map(|id| formats.iter().find(|f| f.format_id == id))
+        .filter_map(|f| format_height(f).map(|height| (height, f.quality_label.clone())))
+        .max_by_key(|(height, _)| *height)
+        .map(|(_, label)| label)
+}
+
+/// 判断下载失败是否由网络问题（SSL、超时、连接中断）导致
+fn is_network_error(error_output: &str) -> bool {
+    const PATTERNS: [&str; 7] = [
+        "SSL",
+        "ssl",
+        "timed out",
+        "timeout",
+        "Timeout",
+        "Connection reset",
+        "IncompleteRead",
+    ];
+    PATTERNS.iter().any(|pattern| error_output.contains(pattern))
+}
@@ -1,12 +1,23 @@
 import { useState, useEffect } from 'react';
 import { useTranslation } from 'react-i18next';
-import type { VideoInfo, DownloadProgress as DownloadProgressType, DownloadConfig } from './types';
+import type { VideoInfo, DownloadProgress as DownloadProgressType, DownloadConfig, SubtitleOptions, AudioOptions } from './types';
 import { UrlInput } from './components/UrlInput';
 import { VideoInfo as VideoInfoComponent } from './components/VideoInfo';
 import { FormatSelector } from './components/FormatSelector';
 import { DownloadProgress } from './components/DownloadProgress';
 import { LanguageSwitcher } from './components/LanguageSwitcher';
-import { getVideoInfo, startDownload, selectSavePath, getDefaultSavePath } from './services/api';
+import {
+  getVideoInfo,
+  startDownload,
+  cancelDownload,
+  pauseDownload,
+  resumeDownload,
+  selectSavePath,
+  getDefaultSavePath,
+  listInterruptedDownloads,
+  resumeInterruptedDownloads,
+  removeFromQueue,
+} from './services/api';
 import './styles/index.css';
 
 function App() {
@@ -17,6 +28,7 @@ function App() {
   const [savePath, setSavePath] = useState<string>('');
   const [downloadProgress, setDownloadProgress] = useState<DownloadProgressType | null>(null);
   const [cleanupDownload, setCleanupDownload] = useState<(() => void) | null>(null);
+  const [downloadTaskId, setDownloadTaskId] = useState<string | null>(null);
   const [isDownloadStarting, setIsDownloadStarting] = useState(false);
 
   // 初始化默认保存路径
@@ -34,6 +46,26 @@ function App() {
     initDefaultPath();
   }, []);
 
+  // 询问是否恢复上次未完成的下载
+  useEffect(() => {
+    const checkInterrupted = async () => {
+      try {
+        const interrupted = await listInterruptedDownloads();
+        if (interrupted.length === 0) {
+          return;
+        }
+        if (window.confirm(t('queue.resumeInterrupted', { count: interrupted.length }))) {
+          await resumeInterruptedDownloads();
+        } else {
+          await Promise.all(interrupted.map((item) => removeFromQueue(item.id)));
+        }
+      } catch (error) {
+        console.error('Failed to check interrupted downloads:', error);
+      }
+    };
+    checkInterrupted();
+  }, []);
+
   // 获取视频信息
   const handleFetchVideo = async (url: string) => {
     setIsLoading(true);
@@ -61,7 +93,14 @@ function App() {
   };
 
   // 开始下载
-  const handleStartDownload = (formatId: string, audioOnly: boolean, includeSubtitles: boolean, preferHdr: boolean = false) => {
+  const handleStartDownload = (
+    formatId: string,
+    audioOnly: boolean,
+    includeSubtitles: boolean,
+    preferHdr: boolean = false,
+    subtitles?: SubtitleOptions,
+    audio?: AudioOptions,
+  ) => {
     if (!videoInfo || !savePath) {
       alert(t('errors.selectPathFirst'));
       return;
@@ -76,19 +115,27 @@ function App() {
       audio_only: audioOnly,
       include_subtitles: includeSubtitles,
       prefer_hdr: preferHdr,
+      subtitles: subtitles ?? null,
+      audio: audio ?? null,
     };
 
     // 开始下载并监听进度
     const cleanup = startDownload(config, (progress) => {
       setIsDownloadStarting(false);
       setDownloadProgress(progress);
-    });
+    }, setDownloadTaskId);
 
     setCleanupDownload(() => cleanup);
   };
 
   // 取消下载
   const handleCancelDownload = () => {
+    if (downloadTaskId) {
+      cancelDownload(downloadTaskId).catch((err) => {
+        console.error('Failed to cancel download:', err);
+      });
+      setDownloadTaskId(null);
+    }
     if (cleanupDownload) {
       cleanupDownload();
       setCleanupDownload(null);
@@ -97,6 +144,24 @@ function App() {
     setIsDownloadStarting(false);
   };
 
+  // 暂停下载
+  const handlePauseDownload = () => {
+    if (downloadTaskId) {
+      pauseDownload(downloadTaskId).catch((err) => {
+        console.error('Failed to pause download:', err);
+      });
+    }
+  };
+
+  // 恢复下载
+  const handleResumeDownload = () => {
+    if (downloadTaskId) {
+      resumeDownload(downloadTaskId).catch((err) => {
+        console.error('Failed to resume download:', err);
+      });
+    }
+  };
+
   // 选择保存路径
   const handleSelectPath = async () => {
     const path = await selectSavePath();
@@ -160,6 +225,7 @@ function App() {
             {!downloadProgress && !isDownloadStarting && (
               <FormatSelector 
                 formats={videoInfo.formats} 
+                subtitles={videoInfo.subtitles}
                 onSelect={handleStartDownload}
                 isDownloading={isDownloadStarting}
               />
@@ -179,7 +245,13 @@ function App() {
 
         {/* 下载进度 */}
         {downloadProgress && (
-          <DownloadProgress progress={downloadProgress} onCancel={handleCancelDownload} />
+          <DownloadProgress
+            progress={downloadProgress}
+            onCancel={handleCancelDownload}
+            onPause={handlePauseDownload}
+            onResume={handleResumeDownload}
+            isPaused={downloadProgress.status === 'paused'}
+          />
         )}
 
         {/* 页脚 */}
@@ -29,6 +29,8 @@ export function DownloadProgress({
         return `✅ ${t('progress.finished')}`;
       case 'error':
         return `❌ ${t('progress.error')}`;
+      case 'skipped':
+        return `⏭️ ${t('progress.skipped')}`;
       default:
         return t('progress.preparing');
     }
@@ -44,6 +46,8 @@ export function DownloadProgress({
         return 'text-success';
       case 'error':
         return 'text-error';
+      case 'skipped':
+        return 'text-success';
       default:
         return 'text-gray-600';
     }
@@ -60,6 +64,16 @@ export function DownloadProgress({
           <p className="text-sm text-gray-600 dark:text-gray-400 mt-1">
             {progress.filename}
           </p>
+          {progress.playlist_index != null && progress.playlist_count != null && (
+            <p className="text-xs text-gray-500 dark:text-gray-400 mt-1">
+              {t('progress.playlistItem', { index: progress.playlist_index, count: progress.playlist_count })}
+            </p>
+          )}
+          {progress.quality && (
+            <p className="text-xs text-gray-500 dark:text-gray-400 mt-1">
+              {t('progress.quality')}: {progress.quality}
+            </p>
+          )}
         </div>
 
         {/* 进度条 */}
@@ -100,7 +114,7 @@ export function DownloadProgress({
         )}
 
         {/* 控制按钮 */}
-        {progress.status === 'downloading' && progress.percent < 100 && (
+        {(progress.status === 'downloading' || progress.status === 'paused') && progress.percent < 100 && (
           <div className="flex gap-2">
             {isPaused ? (
               <button onClick={onResume} className="btn btn-primary flex-1">
@@ -1,20 +1,47 @@
 import { useState, useEffect } from 'react';
 import { useTranslation } from 'react-i18next';
-import type { VideoFormat, QualityOption, FormatOption } from '../types';
+import type { VideoFormat, QualityOption, FormatOption, SubtitleTrack, SubtitleFormat, SubtitleOptions, DynamicRange, AudioCodec, AudioOptions } from '../types';
 import { formatFileSize } from '../services/api';
 
+const DYNAMIC_RANGE_LABELS: Record<DynamicRange, string> = {
+  sdr: 'SDR',
+  hdr10: 'HDR10',
+  hlg: 'HLG',
+  dolbyvision: 'Dolby Vision',
+};
+
+const AUDIO_CODECS: AudioCodec[] = ['mp3', 'm4a', 'opus', 'flac', 'wav'];
+const AUDIO_BITRATES = [320, 256, 192, 128];
+
 interface FormatSelectorProps {
   formats: VideoFormat[];
-  onSelect: (formatId: string, audioOnly: boolean, includeSubtitles: boolean, preferHdr?: boolean) => void;
+  subtitles?: SubtitleTrack[];
+  onSelect: (
+    formatId: string,
+    audioOnly: boolean,
+    includeSubtitles: boolean,
+    preferHdr?: boolean,
+    subtitleOptions?: SubtitleOptions,
+    audioOptions?: AudioOptions,
+  ) => void;
   isDownloading?: boolean;
 }
 
-export function FormatSelector({ formats, onSelect, isDownloading = false }: FormatSelectorProps) {
+export function FormatSelector({ formats, subtitles = [], onSelect, isDownloading = false }: FormatSelectorProps) {
   const { t } = useTranslation();
   const [selectedQuality, setSelectedQuality] = useState<QualityOption>('1080p');
   const [selectedFormat, setSelectedFormat] = useState<FormatOption>('mp4');
   const [audioOnly, setAudioOnly] = useState(false);
   const [includeSubtitles, setIncludeSubtitles] = useState(false);
+  const [subtitleLanguages, setSubtitleLanguages] = useState<string[]>([]);
+  const [subtitleFormat, setSubtitleFormat] = useState<SubtitleFormat>('srt');
+  const [embedSubtitles, setEmbedSubtitles] = useState(false);
+  const [audioOptions, setAudioOptions] = useState<AudioOptions>({
+    codec: 'mp3',
+    bitrate: null,
+    embed_thumbnail: true,
+    embed_metadata: true,
+  });
   const [preferHdr, setPreferHdr] = useState(false);
   const [isInitialized, setIsInitialized] = useState(false);
   const [isClicked, setIsClicked] = useState(false);
@@ -117,6 +144,7 @@ export function FormatSelector({ formats, onSelect, isDownloading = false }: For
       tbr: (videoFormat.tbr || 0) + (audioFormat.tbr || 0),
       format_note: `DASH video + audio (${videoFormat.format_note}, ${audioFormat.format_note})`,
       hdr: videoFormat.hdr,
+      dynamic_range: videoFormat.dynamic_range,
       is_dash: true,
     };
   };
@@ -128,6 +156,9 @@ export function FormatSelector({ formats, onSelect, isDownloading = false }: For
 
   // 获取最佳格式（包括 DASH 合并和 HDR 支持）
   const getBestFormatForQuality = (quality: QualityOption, format: FormatOption, preferHdr: boolean = false): VideoFormat | null => {
+    // HDR 视频流通常只有 DASH 格式，偏好 HDR 时不能先选中 SDR 的完整格式
+    const wantHdr = preferHdr && hasHdrForQuality(quality);
+
     // 首先尝试找到完整的格式（包含视频和音频）
     let completeFormats = formats.filter(
       (f) =>
@@ -137,10 +168,8 @@ export function FormatSelector({ formats, onSelect, isDownloading = false }: For
         f.acodec !== 'none'
     );
 
-    // 如果偏好 HDR，优先选择 HDR 格式
-    if (preferHdr && completeFormats.some(f => f.hdr)) {
-      completeFormats = completeFormats.filter(f => f.hdr);
-    }
+    // 只保留符合 HDR 偏好的完整格式
+    completeFormats = completeFormats.filter(f => f.hdr === wantHdr);
 
     if (completeFormats.length > 0) {
       return completeFormats[0];
@@ -154,9 +183,9 @@ export function FormatSelector({ formats, onSelect, isDownloading = false }: For
         f.acodec === 'none' // 纯视频流
     );
 
-    // HDR 偏好处理
-    if (preferHdr && videoFormats.some(f => f.hdr)) {
-      videoFormats = videoFormats.filter(f => f.hdr);
+    // HDR 偏好处理（不偏好 HDR 时也优先选择 SDR 视频流）
+    if (videoFormats.some(f => f.hdr === wantHdr)) {
+      videoFormats = videoFormats.filter(f => f.hdr === wantHdr);
     }
 
     // 选择最佳视频格式（优先选择指定的容器格式）
@@ -203,7 +232,23 @@ export function FormatSelector({ formats, onSelect, isDownloading = false }: For
     const format = getSelectedFormat();
     if (format) {
       setIsClicked(true);
-      onSelect(format.format_id, audioOnly, includeSubtitles, preferHdr);
+      // 选中的语言中包含自动生成的字幕时才下载自动字幕
+      const subtitleOptions: SubtitleOptions | undefined = includeSubtitles
+        ? {
+            languages: subtitleLanguages,
+            include_automatic: subtitles.some(s => s.automatic && subtitleLanguages.includes(s.language)),
+            format: subtitleFormat,
+            embed: embedSubtitles,
+          }
+        : undefined;
+      onSelect(
+        format.format_id,
+        audioOnly,
+        includeSubtitles,
+        preferHdr,
+        subtitleOptions,
+        audioOnly ? audioOptions : undefined,
+      );
       
       // 重置点击状态（防止卡住）
       setTimeout(() => {
@@ -212,6 +257,17 @@ export function FormatSelector({ formats, onSelect, isDownloading = false }: For
     }
   };
 
+  const toggleSubtitleLanguage = (language: string) => {
+    setSubtitleLanguages(prev =>
+      prev.includes(language) ? prev.filter(l => l !== language) : [...prev, language]
+    );
+  };
+
+  // 同一语言可能同时有人工和自动字幕，只显示一次（优先人工字幕）
+  const subtitleChoices = subtitles.filter(
+    (track, index) => subtitles.findIndex(s => s.language === track.language) === index
+  );
+
   const selectedFormatInfo = getSelectedFormat();
 
   return (
@@ -291,6 +347,18 @@ export function FormatSelector({ formats, onSelect, isDownloading = false }: For
           <span className="text-sm">📝 {t('download.includeSubtitles')}</span>
         </label>
 
+        {includeSubtitles && (
+          <label className="flex items-center gap-2 cursor-pointer">
+            <input
+              type="checkbox"
+              checked={embedSubtitles}
+              onChange={(e) => setEmbedSubtitles(e.target.checked)}
+              className="w-4 h-4 text-primary focus:ring-primary"
+            />
+            <span className="text-sm">{t('download.embedSubtitles')}</span>
+          </label>
+        )}
+
         {hasHdrForQuality(selectedQuality) && !audioOnly && (
           <label className="flex items-center gap-2 cursor-pointer">
             <input
@@ -304,6 +372,107 @@ export function FormatSelector({ formats, onSelect, isDownloading = false }: For
         )}
       </div>
 
+      {/* 音频提取选项 */}
+      {audioOnly && (
+        <div className="space-y-3">
+          <div>
+            <label className="block text-sm font-medium mb-2">{t('download.audioCodec')}</label>
+            <div className="flex gap-2">
+              {AUDIO_CODECS.map((codec) => (
+                <button
+                  key={codec}
+                  onClick={() => setAudioOptions({ ...audioOptions, codec })}
+                  className={`btn ${audioOptions.codec === codec ? 'btn-primary' : 'btn-secondary'}`}
+                >
+                  {codec.toUpperCase()}
+                </button>
+              ))}
+            </div>
+          </div>
+
+          {audioOptions.codec !== 'flac' && audioOptions.codec !== 'wav' && (
+            <div>
+              <label className="block text-sm font-medium mb-2">{t('download.audioBitrate')}</label>
+              <select
+                value={audioOptions.bitrate ?? ''}
+                onChange={(e) => setAudioOptions({ ...audioOptions, bitrate: e.target.value ? Number(e.target.value) : null })}
+                className="input"
+              >
+                <option value="">{t('download.audioBitrateBest')}</option>
+                {AUDIO_BITRATES.map((bitrate) => (
+                  <option key={bitrate} value={bitrate}>{bitrate} kbps</option>
+                ))}
+              </select>
+            </div>
+          )}
+
+          <div className="flex flex-wrap gap-4">
+            {audioOptions.codec !== 'wav' && (
+              <label className="flex items-center gap-2 cursor-pointer">
+                <input
+                  type="checkbox"
+                  checked={audioOptions.embed_thumbnail}
+                  onChange={(e) => setAudioOptions({ ...audioOptions, embed_thumbnail: e.target.checked })}
+                  className="w-4 h-4 text-primary focus:ring-primary"
+                />
+                <span className="text-sm">{t('download.embedCover')}</span>
+              </label>
+            )}
+            <label className="flex items-center gap-2 cursor-pointer">
+              <input
+                type="checkbox"
+                checked={audioOptions.embed_metadata}
+                onChange={(e) => setAudioOptions({ ...audioOptions, embed_metadata: e.target.checked })}
+                className="w-4 h-4 text-primary focus:ring-primary"
+              />
+              <span className="text-sm">{t('download.embedTags')}</span>
+            </label>
+          </div>
+        </div>
+      )}
+
+      {/* 字幕选项 */}
+      {includeSubtitles && (
+        <div className="space-y-3">
+          <div>
+            <label className="block text-sm font-medium mb-2">{t('download.subtitleLanguages')}</label>
+            {subtitleChoices.length > 0 ? (
+              <div className="flex flex-wrap gap-2">
+                {subtitleChoices.map((track) => (
+                  <button
+                    key={track.language}
+                    onClick={() => toggleSubtitleLanguage(track.language)}
+                    className={`btn ${subtitleLanguages.includes(track.language) ? 'btn-primary' : 'btn-secondary'}`}
+                  >
+                    {track.name || track.language}
+                    {track.automatic && (
+                      <span className="ml-1 text-xs opacity-75">({t('download.subtitleAutomatic')})</span>
+                    )}
+                  </button>
+                ))}
+              </div>
+            ) : (
+              <p className="text-sm text-gray-500 dark:text-gray-400">{t('download.noSubtitles')}</p>
+            )}
+          </div>
+
+          <div>
+            <label className="block text-sm font-medium mb-2">{t('download.subtitleFormat')}</label>
+            <div className="flex gap-2">
+              {(['srt', 'vtt', 'ass'] as SubtitleFormat[]).map((format) => (
+                <button
+                  key={format}
+                  onClick={() => setSubtitleFormat(format)}
+                  className={`btn ${subtitleFormat === format ? 'btn-primary' : 'btn-secondary'}`}
+                >
+                  {format.toUpperCase()}
+                </button>
+              ))}
+            </div>
+          </div>
+        </div>
+      )}
+
       {/* 选中的格式信息 */}
       {selectedFormatInfo && (
         <div className="bg-gray-100 dark:bg-gray-700 rounded-lg p-4">
@@ -342,7 +511,7 @@ export function FormatSelector({ formats, onSelect, isDownloading = false }: For
               {selectedFormatInfo.acodec}
               {selectedFormatInfo.hdr && (
                 <span className="ml-2 text-purple-600 dark:text-purple-400 text-xs">
-                  HDR
+                  {DYNAMIC_RANGE_LABELS[selectedFormatInfo.dynamic_range] ?? 'HDR'}
                 </span>
               )}
               {selectedFormatInfo.format_id.includes('+') && (
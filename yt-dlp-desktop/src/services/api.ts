@@ -5,7 +5,18 @@ import type {
   VideoFormat,
   DownloadConfig,
   DownloadProgress,
-  DownloadHistoryItem,
+  DownloadHistoryPage,
+  DuplicateEvent,
+  FilenamePreset,
+  HistoryQuery,
+  HistoryExportFormat,
+  QueueItem,
+  PlaylistInfo,
+  PlaylistSelection,
+  MirrorJob,
+  MirrorRunSummary,
+  Subscription,
+  SubscriptionCheckResult,
 } from '../types';
 
 /**
@@ -26,27 +37,57 @@ export async function listFormats(url: string): Promise<VideoFormat[]> {
   return invoke<VideoFormat[]>('list_formats', { url });
 }
 
+/**
+ * 预览按文件名模板下载后的路径（不下载）
+ * @param url 视频 URL
+ * @param template 文件名模板（如 %(uploader)s/%(title)s.%(ext)s）
+ * @param outputPath 保存路径，为空时使用默认保存路径
+ * @returns 完整路径；模板无效时抛出错误
+ */
+export async function previewOutputFilename(url: string, template: string, outputPath?: string): Promise<string> {
+  return invoke<string>('preview_output_filename', { url, template, outputPath });
+}
+
+/**
+ * 获取文件名模板预设
+ */
+export async function getFilenamePresets(): Promise<FilenamePreset[]> {
+  return invoke<FilenamePreset[]>('get_filename_presets');
+}
+
 /**
  * 开始下载
  * @param config 下载配置
  * @param onProgress 进度回调
+ * @param onStarted 下载任务创建后回调（参数为任务 ID）
  * @returns 清理函数
  */
 export function startDownload(
   config: DownloadConfig,
-  onProgress: (progress: DownloadProgress) => void
+  onProgress: (progress: DownloadProgress) => void,
+  onStarted?: (taskId: string) => void
 ): () => void {
-  // 监听下载进度事件
-  const unlisten = listen('download-progress', (event) => {
-    const progress = event.payload as DownloadProgress;
+  let currentTaskId: string | null = null;
+
+  // 监听下载进度事件（任务 ID 确定后只处理本任务的事件）
+  const unlisten = listen<DownloadProgress>('download-progress', (event) => {
+    const progress = event.payload;
+    if (currentTaskId && progress.task_id !== currentTaskId) {
+      return;
+    }
     onProgress(progress);
   });
 
   // 启动下载
-  invoke('start_download', { config }).catch((error) => {
+  invoke<string>('start_download', { config }).then((taskId) => {
+    currentTaskId = taskId;
+    onStarted?.(taskId);
+  }).catch((error) => {
     console.error('Failed to start download:', error);
     onProgress({
+      task_id: '',
       status: 'error',
+      phase: 'preparing',
       percent: 0,
       speed: 0,
       eta: 0,
@@ -69,6 +110,230 @@ export async function cancelDownload(downloadId: string): Promise<void> {
   return invoke('cancel_download', { downloadId });
 }
 
+/**
+ * 监听单个下载任务的进度
+ * @param taskId 下载任务 ID
+ * @param onProgress 进度回调
+ * @returns 清理函数
+ */
+export function listenTaskProgress(
+  taskId: string,
+  onProgress: (progress: DownloadProgress) => void
+): () => void {
+  const unlisten = listen<DownloadProgress>(`download-progress:${taskId}`, (event) => {
+    onProgress(event.payload);
+  });
+
+  return () => {
+    unlisten.then(fn => fn());
+  };
+}
+
+/**
+ * 获取所有未结束下载的进度快照（页面重新加载后用于重新关联任务）
+ * @returns 每个任务的最新进度
+ */
+export async function getActiveDownloads(): Promise<DownloadProgress[]> {
+  return invoke<DownloadProgress[]>('get_active_downloads');
+}
+
+/**
+ * 暂停下载
+ * @param downloadId 下载任务 ID
+ */
+export async function pauseDownload(downloadId: string): Promise<void> {
+  return invoke('pause_download', { downloadId });
+}
+
+/**
+ * 恢复下载
+ * @param downloadId 下载任务 ID
+ */
+export async function resumeDownload(downloadId: string): Promise<void> {
+  return invoke('resume_download', { downloadId });
+}
+
+/**
+ * 确认重新下载已下载过的视频
+ * @param downloadId 下载任务 ID
+ */
+export async function redownload(downloadId: string): Promise<void> {
+  return invoke('redownload', { downloadId });
+}
+
+/**
+ * 监听“已下载过、等待确认”事件
+ * @param onDuplicate 回调
+ * @returns 清理函数
+ */
+export function listenDuplicates(onDuplicate: (event: DuplicateEvent) => void): () => void {
+  const unlisten = listen<DuplicateEvent>('download-duplicate', (event) => {
+    onDuplicate(event.payload);
+  });
+
+  return () => {
+    unlisten.then(fn => fn());
+  };
+}
+
+/**
+ * 获取播放列表或频道的条目
+ * @param url 播放列表或频道 URL
+ * @returns 播放列表信息
+ */
+export async function getPlaylistInfo(url: string): Promise<PlaylistInfo> {
+  return invoke<PlaylistInfo>('get_playlist_info', { url });
+}
+
+/**
+ * 将播放列表中选中的条目加入下载队列
+ * @param config 下载配置（url 为播放列表 URL）
+ * @param selection 条目选择（序号、范围、倒序）
+ * @returns 下载任务 ID 列表
+ */
+export async function enqueuePlaylist(
+  config: DownloadConfig,
+  selection: PlaylistSelection
+): Promise<string[]> {
+  return invoke<string[]>('enqueue_playlist', { config, selection });
+}
+
+/**
+ * 创建频道镜像任务
+ * @param config 下载配置模板（url 为频道或播放列表地址）
+ * @param dateAfter 只下载该日期及之后的视频（YYYYMMDD）
+ * @param dateBefore 只下载该日期及之前的视频（YYYYMMDD）
+ */
+export async function createMirrorJob(
+  config: DownloadConfig,
+  dateAfter: string | null = null,
+  dateBefore: string | null = null
+): Promise<MirrorJob> {
+  return invoke<MirrorJob>('create_mirror_job', { config, dateAfter, dateBefore });
+}
+
+/**
+ * 获取所有镜像任务
+ */
+export async function listMirrorJobs(): Promise<MirrorJob[]> {
+  return invoke<MirrorJob[]>('list_mirror_jobs');
+}
+
+/**
+ * 删除镜像任务
+ * @param jobId 镜像任务 ID
+ */
+export async function deleteMirrorJob(jobId: string): Promise<void> {
+  return invoke('delete_mirror_job', { jobId });
+}
+
+/**
+ * 运行镜像任务，只将新视频加入下载队列
+ * @param jobId 镜像任务 ID
+ * @returns 运行结果
+ */
+export async function runMirrorJob(jobId: string): Promise<MirrorRunSummary> {
+  return invoke<MirrorRunSummary>('run_mirror_job', { jobId });
+}
+
+/**
+ * 获取镜像任务的运行记录
+ * @param jobId 镜像任务 ID，为空时返回所有记录
+ */
+export async function listMirrorRuns(jobId: string | null = null): Promise<MirrorRunSummary[]> {
+  return invoke<MirrorRunSummary[]>('list_mirror_runs', { jobId });
+}
+
+/**
+ * 添加订阅
+ * @param url 频道、播放列表或 RSS/Atom 订阅源地址
+ * @param preset 下载预设
+ * @param intervalMinutes 检查间隔（分钟）
+ * @param backfill 首次检查时是否下载已有视频
+ */
+export async function addSubscription(
+  url: string,
+  preset: DownloadConfig,
+  intervalMinutes: number | null = null,
+  backfill = false
+): Promise<Subscription> {
+  return invoke<Subscription>('add_subscription', { url, preset, intervalMinutes, backfill });
+}
+
+/**
+ * 获取所有订阅
+ */
+export async function listSubscriptions(): Promise<Subscription[]> {
+  return invoke<Subscription[]>('list_subscriptions');
+}
+
+/**
+ * 删除订阅
+ * @param subscriptionId 订阅 ID
+ */
+export async function removeSubscription(subscriptionId: string): Promise<void> {
+  return invoke('remove_subscription', { subscriptionId });
+}
+
+/**
+ * 立即检查订阅
+ * @param subscriptionId 订阅 ID，为空时检查全部
+ */
+export async function checkSubscriptionsNow(
+  subscriptionId: string | null = null
+): Promise<SubscriptionCheckResult[]> {
+  return invoke<SubscriptionCheckResult[]>('check_now', { subscriptionId });
+}
+
+/**
+ * 批量添加下载任务到队列
+ * @param configs 下载配置列表
+ * @returns 下载任务 ID 列表
+ */
+export async function enqueueDownloads(configs: DownloadConfig[]): Promise<string[]> {
+  return invoke<string[]>('enqueue_downloads', { configs });
+}
+
+/**
+ * 获取下载队列
+ * @returns 队列任务列表
+ */
+export async function listQueue(): Promise<QueueItem[]> {
+  return invoke<QueueItem[]>('list_queue');
+}
+
+/**
+ * 调整下载队列顺序
+ * @param ids 新的任务 ID 顺序
+ */
+export async function reorderQueue(ids: string[]): Promise<void> {
+  return invoke('reorder_queue', { ids });
+}
+
+/**
+ * 从下载队列中移除任务
+ * @param downloadId 下载任务 ID
+ */
+export async function removeFromQueue(downloadId: string): Promise<void> {
+  return invoke('remove_from_queue', { downloadId });
+}
+
+/**
+ * 获取上次退出时未完成的下载
+ * @returns 被中断的队列任务
+ */
+export async function listInterruptedDownloads(): Promise<QueueItem[]> {
+  return invoke<QueueItem[]>('list_interrupted_downloads');
+}
+
+/**
+ * 恢复上次退出时未完成的下载
+ * @returns 恢复的任务数量
+ */
+export async function resumeInterruptedDownloads(): Promise<number> {
+  return invoke<number>('resume_interrupted_downloads');
+}
+
 /**
  * 选择保存路径
  * @returns 选择的路径，如果取消则返回 null
@@ -78,11 +343,59 @@ export async function selectSavePath(): Promise<string | null> {
 }
 
 /**
- * 获取下载历史
- * @returns 历史记录列表
+ * 分页获取下载历史
+ * @param page 页码（从 1 开始）
+ * @param pageSize 每页条数
+ * @returns 历史记录分页
+ */
+export async function getDownloadHistory(page = 1, pageSize = 50): Promise<DownloadHistoryPage> {
+  return invoke<DownloadHistoryPage>('get_download_history', { page, pageSize });
+}
+
+/**
+ * 按条件查询下载历史
+ * @param query 查询条件（标题搜索、日期范围、上传者、格式、状态）
+ * @returns 历史记录分页
+ */
+export async function queryDownloadHistory(query: HistoryQuery): Promise<DownloadHistoryPage> {
+  return invoke<DownloadHistoryPage>('query_download_history', { query });
+}
+
+/**
+ * 导出下载历史为 CSV 或 JSON
+ * @param query 查询条件
+ * @param format 导出格式
+ * @returns 保存路径，用户取消时为 null
+ */
+export async function exportDownloadHistory(
+  query: HistoryQuery,
+  format: HistoryExportFormat
+): Promise<string | null> {
+  return invoke<string | null>('export_download_history', { query, format });
+}
+
+/**
+ * 从 yt-dlp --download-archive 格式的文件导入下载存档
+ * @returns 新增条数，用户取消时为 null
+ */
+export async function importDownloadArchive(): Promise<number | null> {
+  return invoke<number | null>('import_download_archive');
+}
+
+/**
+ * 导出 yt-dlp --download-archive 格式的下载存档
+ * @returns 导出条数，用户取消时为 null
+ */
+export async function exportDownloadArchive(): Promise<number | null> {
+  return invoke<number | null>('export_download_archive');
+}
+
+/**
+ * 删除单条下载历史
+ * @param id 历史记录 ID
  */
-export async function getDownloadHistory(): Promise<DownloadHistoryItem[]> {
-  return invoke<DownloadHistoryItem[]>('get_download_history');
+export async function deleteHistoryItem(id: string): Promise<void> {
+  return invoke('delete_history_item', { id });
 }
 
 /**
@@ -2,7 +2,17 @@ import type {
   VideoInfo,
   VideoFormat,
   DownloadConfig,
-  DownloadHistoryItem,
+  DownloadHistoryPage,
+  HistoryQuery,
+  HistoryExportFormat,
+  DownloadProgress,
+  QueueItem,
+  PlaylistInfo,
+  PlaylistSelection,
+  MirrorJob,
+  MirrorRunSummary,
+  Subscription,
+  SubscriptionCheckResult,
 } from './index';
 
 /**
@@ -25,6 +35,84 @@ export interface TauriCommands {
    */
   list_formats(url: string): Promise<VideoFormat[]>;
 
+  /**
+   * 获取播放列表或频道的条目（平铺提取）
+   * @param url 播放列表或频道 URL
+   * @returns 播放列表信息
+   */
+  get_playlist_info(url: string): Promise<PlaylistInfo>;
+
+  /**
+   * 将播放列表中选中的条目加入下载队列
+   * @param config 下载配置（url 为播放列表 URL，格式和保存路径应用于所有条目）
+   * @param selection 条目选择
+   * @returns 下载任务 ID 列表
+   */
+  enqueue_playlist(config: DownloadConfig, selection: PlaylistSelection): Promise<string[]>;
+
+  /**
+   * 创建频道镜像任务
+   * @param config 下载配置模板（url 为频道或播放列表地址）
+   * @param date_after 只下载该日期及之后的视频（YYYYMMDD）
+   * @param date_before 只下载该日期及之前的视频（YYYYMMDD）
+   */
+  create_mirror_job(config: DownloadConfig, date_after: string | null, date_before: string | null): Promise<MirrorJob>;
+
+  /**
+   * 获取所有镜像任务
+   */
+  list_mirror_jobs(): Promise<MirrorJob[]>;
+
+  /**
+   * 删除镜像任务
+   * @param job_id 镜像任务 ID
+   */
+  delete_mirror_job(job_id: string): Promise<void>;
+
+  /**
+   * 运行镜像任务，只将存档中没有的视频加入下载队列
+   * @param job_id 镜像任务 ID
+   * @returns 运行结果
+   */
+  run_mirror_job(job_id: string): Promise<MirrorRunSummary>;
+
+  /**
+   * 获取镜像任务的运行记录（最新的在前）
+   * @param job_id 镜像任务 ID，为空时返回所有任务的记录
+   */
+  list_mirror_runs(job_id: string | null): Promise<MirrorRunSummary[]>;
+
+  /**
+   * 添加订阅，后台定期检查并自动下载新视频
+   * @param url 频道、播放列表或 RSS/Atom 订阅源地址
+   * @param preset 下载预设
+   * @param interval_minutes 检查间隔（分钟，默认 60，最少 5）
+   * @param backfill 首次检查时是否下载已有视频
+   */
+  add_subscription(
+    url: string,
+    preset: DownloadConfig,
+    interval_minutes: number | null,
+    backfill: boolean | null
+  ): Promise<Subscription>;
+
+  /**
+   * 获取所有订阅
+   */
+  list_subscriptions(): Promise<Subscription[]>;
+
+  /**
+   * 删除订阅
+   * @param subscription_id 订阅 ID
+   */
+  remove_subscription(subscription_id: string): Promise<void>;
+
+  /**
+   * 立即检查订阅
+   * @param subscription_id 订阅 ID，为空时检查全部
+   */
+  check_now(subscription_id: string | null): Promise<SubscriptionCheckResult[]>;
+
   /**
    * 开始下载
    * @param config 下载配置
@@ -38,6 +126,67 @@ export interface TauriCommands {
    */
   cancel_download(download_id: string): Promise<void>;
 
+  /**
+   * 暂停下载（保留已下载的部分）
+   * @param download_id 下载任务 ID
+   */
+  pause_download(download_id: string): Promise<void>;
+
+  /**
+   * 恢复已暂停的下载
+   * @param download_id 下载任务 ID
+   */
+  resume_download(download_id: string): Promise<void>;
+
+  /**
+   * 确认重新下载已下载过的视频
+   * @param download_id 下载任务 ID
+   */
+  redownload(download_id: string): Promise<void>;
+
+  /**
+   * 批量添加下载任务到队列
+   * @param configs 下载配置列表
+   * @returns 下载任务 ID 列表
+   */
+  enqueue_downloads(configs: DownloadConfig[]): Promise<string[]>;
+
+  /**
+   * 获取下载队列
+   * @returns 队列任务列表
+   */
+  list_queue(): Promise<QueueItem[]>;
+
+  /**
+   * 调整下载队列顺序
+   * @param ids 新的任务 ID 顺序
+   */
+  reorder_queue(ids: string[]): Promise<void>;
+
+  /**
+   * 从下载队列中移除任务
+   * @param download_id 下载任务 ID
+   */
+  remove_from_queue(download_id: string): Promise<void>;
+
+  /**
+   * 获取所有未结束下载的进度快照
+   * @returns 每个任务的最新进度
+   */
+  get_active_downloads(): Promise<DownloadProgress[]>;
+
+  /**
+   * 获取上次退出时未完成的下载
+   * @returns 被中断的队列任务
+   */
+  list_interrupted_downloads(): Promise<QueueItem[]>;
+
+  /**
+   * 恢复上次退出时未完成的下载
+   * @returns 恢复的任务数量
+   */
+  resume_interrupted_downloads(): Promise<number>;
+
   /**
    * 选择保存路径
    * @returns 选择的路径，如果取消则返回 null
@@ -45,10 +194,45 @@ export interface TauriCommands {
   select_save_path(): Promise<string | null>;
 
   /**
-   * 获取下载历史
-   * @returns 历史记录列表
+   * 分页获取下载历史（最新的在前）
+   * @param page 页码（从 1 开始）
+   * @param page_size 每页条数（默认 50）
+   * @returns 历史记录分页
+   */
+  get_download_history(page: number | null, page_size: number | null): Promise<DownloadHistoryPage>;
+
+  /**
+   * 按条件查询下载历史
+   * @param query 查询条件
+   * @returns 历史记录分页
+   */
+  query_download_history(query: HistoryQuery): Promise<DownloadHistoryPage>;
+
+  /**
+   * 导出符合条件的下载历史（忽略分页）
+   * @param query 查询条件
+   * @param format 导出格式
+   * @returns 保存路径，用户取消时为 null
+   */
+  export_download_history(query: HistoryQuery, format: HistoryExportFormat): Promise<string | null>;
+
+  /**
+   * 从 yt-dlp --download-archive 格式的文件导入下载存档
+   * @returns 新增条数，用户取消时为 null
+   */
+  import_download_archive(): Promise<number | null>;
+
+  /**
+   * 导出 yt-dlp --download-archive 格式的下载存档
+   * @returns 导出条数，用户取消时为 null
+   */
+  export_download_archive(): Promise<number | null>;
+
+  /**
+   * 删除单条下载历史
+   * @param id 历史记录 ID
    */
-  get_download_history(): Promise<DownloadHistoryItem[]>;
+  delete_history_item(id: string): Promise<void>;
 
   /**
    * 清空下载历史
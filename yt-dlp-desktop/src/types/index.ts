@@ -11,6 +11,32 @@ export interface VideoInfo {
   description: string;           // 描述
   view_count: number;            // 观看次数
   formats: VideoFormat[];        // 可用格式列表
+  subtitles: SubtitleTrack[];    // 可用字幕（人工字幕在前，其后是自动生成的字幕）
+}
+
+/**
+ * 可用的字幕语言
+ */
+export interface SubtitleTrack {
+  language: string;              // 语言代码（如 en、zh-Hans）
+  name: string | null;           // 语言名称（如 English）
+  automatic: boolean;            // 是否为自动生成的字幕
+  formats: string[];             // 可用的字幕格式（如 vtt、srv3）
+}
+
+/**
+ * 字幕转换格式
+ */
+export type SubtitleFormat = 'srt' | 'vtt' | 'ass';
+
+/**
+ * 字幕下载选项
+ */
+export interface SubtitleOptions {
+  languages: string[];           // 语言代码，为空时使用应用设置中的语言
+  include_automatic: boolean;    // 同时下载自动生成的字幕
+  format: SubtitleFormat;        // 转换后的字幕格式
+  embed: boolean;                // 嵌入视频；否则保存为与视频同名的字幕文件
 }
 
 /**
@@ -28,26 +54,60 @@ export interface VideoFormat {
   tbr: number | null;            // 比特率（可能为null）
   format_note: string;           // 格式说明
   hdr: boolean;                  // 是否支持 HDR
+  dynamic_range: DynamicRange;   // 动态范围
   is_dash: boolean;              // 是否是 DASH 格式
 }
 
+/**
+ * 视频动态范围（hdr10 包括 HDR10+ 和 HDR12）
+ */
+export type DynamicRange = 'sdr' | 'hdr10' | 'hlg' | 'dolbyvision';
+
 /**
  * 下载进度状态
  */
-export type DownloadStatus = 'downloading' | 'processing' | 'finished' | 'error';
+export type DownloadStatus = 'queued' | 'downloading' | 'processing' | 'paused' | 'finished' | 'error' | 'cancelled' | 'skipped';
+
+/**
+ * 下载阶段
+ */
+export type DownloadPhase = 'preparing' | 'thumbnail' | 'downloading' | 'merging' | 'extracting' | 'metadata' | 'cleanup' | 'completed';
+
+/**
+ * 下载产生的文件类型
+ */
+export type ArtifactKind = 'media' | 'thumbnail' | 'subtitle' | 'infojson';
+
+/**
+ * 下载产生的文件
+ */
+export interface DownloadArtifact {
+  kind: ArtifactKind;
+  path: string;
+  size: number;                  // 文件大小（字节）
+}
 
 /**
  * 下载进度
  */
 export interface DownloadProgress {
+  task_id: string;               // 下载任务 ID
   status: DownloadStatus;
+  phase: DownloadPhase;          // 当前阶段
   percent: number;               // 进度百分比 (0-100)
   speed: number;                 // 下载速度（bytes/s）
   eta: number;                   // 预计剩余时间（秒）
   downloaded: number;            // 已下载字节数
   total: number;                 // 总字节数
   filename: string;              // 文件名
-  file_path?: string;            // 文件完整路径（下载完成后）
+  file_path?: string;            // 主文件完整路径（下载完成后）
+  artifacts?: DownloadArtifact[]; // 下载产生的全部文件（主文件、缩略图、字幕等）
+  format_id?: string;            // 实际下载的格式（画质降级后可能与请求不同）
+  quality?: string;              // 实际下载的画质（如 720p）
+  playlist_index?: number;       // 播放列表下载时的位置（第几个）
+  playlist_count?: number;       // 播放列表下载的总数
+  fragment_index?: number;       // 分片下载（HLS/DASH）时的当前分片
+  fragment_count?: number;       // 分片总数
 }
 
 /**
@@ -60,6 +120,130 @@ export interface DownloadConfig {
   audio_only: boolean;           // 是否仅下载音频
   include_subtitles: boolean;    // 是否包含字幕
   prefer_hdr: boolean;           // 是否优先选择 HDR
+  subtitles?: SubtitleOptions | null; // 字幕选项，为空时使用应用设置中的默认选项
+  audio?: AudioOptions | null;   // 仅音频时的音频提取选项，为空时使用应用设置中的默认选项
+  filename_template?: string | null; // 文件名模板（相对于保存路径），为空时使用应用设置中的模板
+  playlist?: PlaylistPosition | null; // 来自播放列表时的位置
+  archive?: string | null;       // 下载存档文件（成功下载后记录视频 ID）
+  force?: boolean;               // 跳过“已下载”检查，强制重新下载
+}
+
+/**
+ * 音频提取的目标格式
+ */
+export type AudioCodec = 'mp3' | 'm4a' | 'opus' | 'flac' | 'wav';
+
+/**
+ * 音频提取选项
+ */
+export interface AudioOptions {
+  codec: AudioCodec;
+  bitrate: number | null;        // 目标码率（kbit/s），null 为最高质量；无损格式忽略
+  embed_thumbnail: boolean;      // 嵌入封面（WAV 不支持）
+  embed_metadata: boolean;       // 写入标签（ID3 / Vorbis 注释）
+}
+
+/**
+ * 文件名模板预设
+ */
+export interface FilenamePreset {
+  name: string;                  // 预设名称（如 "uploader/date - title [id]"）
+  template: string;              // yt-dlp 输出模板
+}
+
+/**
+ * 已存在同名文件时的处理方式
+ */
+export type CollisionPolicy = 'skip' | 'overwrite' | 'number' | 'id';
+
+/**
+ * 文件名处理设置
+ */
+export interface FilenameOptions {
+  collision: CollisionPolicy;    // skip 保留已有文件，overwrite 覆盖，number 添加 (1)，id 添加 [视频 ID]
+  restrict_ascii: boolean;       // 只使用 ASCII 字符
+  max_bytes: number;             // 文件名（不含扩展名）的最大字节数
+}
+
+/**
+ * 播放列表条目在本次下载中的位置
+ */
+export interface PlaylistPosition {
+  playlist_id: string;
+  playlist_title: string;
+  index: number;                 // 第几个（从 1 开始）
+  count: number;                 // 共几个
+}
+
+/**
+ * 播放列表/频道信息
+ */
+export interface PlaylistInfo {
+  id: string;
+  title: string;
+  uploader: string;
+  entries: PlaylistEntry[];
+}
+
+/**
+ * 播放列表条目
+ */
+export interface PlaylistEntry {
+  id: string;
+  title: string;
+  url: string;
+  duration: number | null;       // 时长（秒）
+  upload_date: string | null;    // 上传日期（YYYYMMDD，可能是近似值）
+  index: number;                 // 在播放列表中的序号（从 1 开始）
+}
+
+/**
+ * 播放列表条目选择
+ */
+export interface PlaylistSelection {
+  indices?: number[];            // 手动勾选的条目序号（为空时按 items 范围选择）
+  items?: string | null;         // 序号范围，如 "1-10,15,20-"
+  reverse?: boolean;             // 倒序下载
+}
+
+/**
+ * 下载队列任务状态
+ */
+export type QueueState =
+  | 'queued'
+  | 'running'
+  | 'paused'
+  | 'interrupted'
+  | 'finished'
+  | 'failed'
+  | 'cancelled'
+  | 'skipped'                    // 已下载过，按配置跳过
+  | 'duplicate';                 // 已下载过，等待确认是否重新下载
+
+/**
+ * 下载队列任务
+ */
+export interface QueueItem {
+  id: string;                    // 任务 ID
+  config: DownloadConfig;        // 下载配置
+  state: QueueState;             // 队列状态
+  error: string | null;          // 失败原因
+  added_at: string;              // 加入队列时间
+  started_at: string | null;     // 开始时间
+  finished_at: string | null;    // 结束时间
+  attempts: DownloadAttempt[];   // 每次启动下载的记录（包括自动重试）
+}
+
+/**
+ * 一次下载尝试
+ */
+export interface DownloadAttempt {
+  attempt: number;               // 第几次尝试（从 1 开始）
+  format_id: string;             // 本次使用的格式
+  started_at: string;
+  finished_at: string;
+  exit_code: number | null;      // yt-dlp 退出码
+  error: string | null;          // 失败原因
 }
 
 /**
@@ -71,16 +255,80 @@ export interface DownloadResult {
   error?: string;
 }
 
+/**
+ * 下载历史记录的结果
+ */
+export type HistoryStatus = 'finished' | 'failed' | 'skipped';
+
 /**
  * 下载历史记录
  */
 export interface DownloadHistoryItem {
-  id: string;
+  id: string;                    // 下载任务 ID
+  video_id: string;              // 视频 ID
   title: string;
+  uploader: string | null;       // 上传者/频道名
+  extractor: string | null;      // yt-dlp 提取器名（如 youtube）
   url: string;
-  file_path: string;
-  download_date: string;
+  file_path: string;             // 下载失败时为空
+  artifacts: DownloadArtifact[]; // 下载产生的全部文件
+  format_id: string;             // 下载的格式
+  quality: string | null;        // 实际下载的画质（如 720p）
+  rule: string | null;           // 决定保存目录的整理规则
+  filesize: number | null;       // 文件大小（字节）
+  duration: number | null;       // 视频时长（秒）
   thumbnail: string;
+  started_at: string;            // 开始时间
+  download_date: string;         // 完成（或失败）时间
+  status: HistoryStatus;
+  error: string | null;          // 失败原因
+  attempts: DownloadAttempt[];   // 每次启动下载的记录（包括自动重试和暂停后恢复）
+}
+
+/**
+ * 已下载过的视频
+ */
+export interface ArchiveMatch {
+  video_id: string;
+  title: string;
+  previous: DownloadHistoryItem | null; // 之前的下载记录，null 表示只在导入的存档中
+}
+
+/**
+ * 发现已下载过的视频、等待确认时的事件（download-duplicate）
+ */
+export interface DuplicateEvent {
+  task_id: string;
+  found: ArchiveMatch;
+}
+
+/**
+ * 下载历史查询条件（字段均可选）
+ */
+export interface HistoryQuery {
+  search?: string;               // 标题关键字
+  date_from?: string;            // 完成日期下限（YYYY-MM-DD）
+  date_to?: string;              // 完成日期上限（YYYY-MM-DD）
+  uploader?: string;
+  format_id?: string;
+  status?: HistoryStatus;
+  page?: number;                 // 页码（从 1 开始）
+  page_size?: number;
+}
+
+/**
+ * 下载历史导出格式
+ */
+export type HistoryExportFormat = 'csv' | 'json';
+
+/**
+ * 分页的下载历史
+ */
+export interface DownloadHistoryPage {
+  items: DownloadHistoryItem[];
+  total: number;                 // 总条数
+  page: number;                  // 页码（从 1 开始）
+  page_size: number;
 }
 
 /**
@@ -92,3 +340,63 @@ export type QualityOption = '2160p' | '1440p' | '1080p' | '720p' | '480p' | '360
  * 格式选项
  */
 export type FormatOption = 'mp4' | 'webm' | 'mkv';
+
+/**
+ * 频道/播放列表镜像任务
+ */
+export interface MirrorJob {
+  id: string;
+  config: DownloadConfig;        // 下载配置模板（url 为频道或播放列表地址）
+  date_after: string | null;     // 只下载该日期及之后的视频（YYYYMMDD）
+  date_before: string | null;    // 只下载该日期及之前的视频（YYYYMMDD）
+  created_at: string;
+  last_run_at: string | null;
+}
+
+/**
+ * 镜像任务运行结果
+ */
+export interface MirrorRunSummary {
+  job_id: string;
+  url: string;
+  started_at: string;
+  finished_at: string;
+  total_entries: number;         // 条目总数
+  already_archived: number;      // 存档中已有
+  already_queued: number;        // 已在下载队列中
+  filtered_out: number;          // 不在日期范围内
+  undated: number;               // 没有上传日期（仍会下载）
+  task_ids: string[];            // 本次加入队列的任务 ID
+}
+
+/**
+ * 订阅来源类型
+ */
+export type SubscriptionKind = 'channel' | 'playlist' | 'feed';
+
+/**
+ * 订阅（频道、播放列表或 RSS/Atom 订阅源）
+ */
+export interface Subscription {
+  id: string;
+  url: string;
+  kind: SubscriptionKind;
+  preset: DownloadConfig;        // 下载预设（url 字段不使用）
+  interval_minutes: number;      // 检查间隔（分钟）
+  backfill: boolean;             // 首次检查时是否下载已有视频
+  created_at: string;
+  last_checked_at: string | null;
+  last_error: string | null;
+  initialized: boolean;          // 首次检查已成功完成
+  seen: string[];                // 已处理过的条目 URL
+}
+
+/**
+ * 订阅检查结果
+ */
+export interface SubscriptionCheckResult {
+  subscription_id: string;
+  discovered: number;            // 发现的条目数
+  task_ids: string[];            // 本次加入队列的任务 ID
+  error: string | null;
+}